@@ -0,0 +1,45 @@
+-- 告警通知渠道
+CREATE TABLE notification_channels (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    channel_type VARCHAR(20) NOT NULL CHECK (channel_type IN ('email', 'webhook')),
+    target VARCHAR(500) NOT NULL,  -- 邮箱地址或 Webhook URL
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- 告警规则
+CREATE TABLE alert_rules (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    vm_instance_id UUID NOT NULL REFERENCES vm_instances(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    metric_type VARCHAR(50) NOT NULL CHECK (metric_type IN ('cpu', 'memory', 'disk', 'instance_down')),
+    comparator VARCHAR(10) NOT NULL DEFAULT 'gt' CHECK (comparator IN ('gt', 'gte', 'lt', 'lte')),
+    threshold FLOAT8,  -- instance_down 规则不需要阈值
+    duration_minutes INTEGER NOT NULL DEFAULT 5 CHECK (duration_minutes BETWEEN 1 AND 1440),
+    channel_ids UUID[] NOT NULL DEFAULT '{}',
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    state VARCHAR(20) NOT NULL DEFAULT 'ok' CHECK (state IN ('ok', 'firing')),
+    last_state_change TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- 告警历史
+CREATE TABLE alert_history (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    rule_id UUID NOT NULL REFERENCES alert_rules(id) ON DELETE CASCADE,
+    vm_instance_id UUID NOT NULL REFERENCES vm_instances(id) ON DELETE CASCADE,
+    status VARCHAR(20) NOT NULL CHECK (status IN ('firing', 'resolved')),
+    value FLOAT8,
+    message TEXT NOT NULL,
+    notified BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_notification_channels_user_id ON notification_channels(user_id);
+CREATE INDEX idx_alert_rules_user_id ON alert_rules(user_id);
+CREATE INDEX idx_alert_rules_enabled ON alert_rules(enabled);
+CREATE INDEX idx_alert_history_rule_id ON alert_history(rule_id, created_at DESC);
@@ -0,0 +1,19 @@
+-- 节点健康检查状态
+ALTER TABLE pve_nodes
+    ADD COLUMN health_status VARCHAR(20) NOT NULL DEFAULT 'unknown' CHECK (health_status IN ('unknown', 'healthy', 'unhealthy')),
+    ADD COLUMN consecutive_failures INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN last_error TEXT;
+
+-- 节点状态采样（与 PveNodeStatus 对应）
+CREATE TABLE pve_node_status (
+    pve_node_id UUID NOT NULL REFERENCES pve_nodes(id) ON DELETE CASCADE,
+    cpu_usage FLOAT8 NOT NULL,
+    memory_usage FLOAT8 NOT NULL,
+    disk_usage FLOAT8 NOT NULL,
+    uptime BIGINT NOT NULL,
+    load_1 FLOAT8 NOT NULL,
+    load_5 FLOAT8 NOT NULL,
+    load_15 FLOAT8 NOT NULL,
+    recorded_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (pve_node_id, recorded_at)
+);
@@ -0,0 +1,21 @@
+-- 节点资源对账报告：PVE 实际分配 vs 平台 vm_instances 记录
+CREATE TABLE node_capacity_reports (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    pve_node_id UUID NOT NULL REFERENCES pve_nodes(id) ON DELETE CASCADE,
+    pve_cpu INTEGER NOT NULL DEFAULT 0,
+    pve_memory_gb INTEGER NOT NULL DEFAULT 0,
+    pve_storage_gb INTEGER NOT NULL DEFAULT 0,
+    db_cpu INTEGER NOT NULL DEFAULT 0,
+    db_memory_gb INTEGER NOT NULL DEFAULT 0,
+    db_storage_gb INTEGER NOT NULL DEFAULT 0,
+    storage_used_gb INTEGER NOT NULL DEFAULT 0,  -- 节点存储实际占用
+    storage_total_gb INTEGER NOT NULL DEFAULT 0,
+    untracked_vmids INTEGER[] NOT NULL DEFAULT '{}',  -- PVE 上存在但平台未记录
+    missing_vmids INTEGER[] NOT NULL DEFAULT '{}',    -- 平台有记录但 PVE 上不存在
+    has_drift BOOLEAN NOT NULL DEFAULT FALSE,
+    overcommitted BOOLEAN NOT NULL DEFAULT FALSE,
+    error TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_node_capacity_reports_node ON node_capacity_reports(pve_node_id, created_at DESC);
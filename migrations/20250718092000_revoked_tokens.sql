@@ -0,0 +1,9 @@
+-- 已吊销的访问令牌，仅保存 jti 的哈希，过期后由清理任务删除
+CREATE TABLE revoked_tokens (
+    jti_hash VARCHAR(64) PRIMARY KEY,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    revoked_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_revoked_tokens_expires_at ON revoked_tokens(expires_at);
+
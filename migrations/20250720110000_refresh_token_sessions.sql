@@ -0,0 +1,11 @@
+-- user_sessions 改为保存刷新令牌：每行一个刷新令牌，同一次登录轮换产生的令牌属于同一 family
+ALTER TABLE user_sessions
+    ADD COLUMN family_id UUID NOT NULL DEFAULT uuid_generate_v4(),
+    ADD COLUMN rotated_at TIMESTAMP WITH TIME ZONE,  -- 已被新令牌替换
+    ADD COLUMN revoked_at TIMESTAMP WITH TIME ZONE;  -- 登出、踢下线或检测到重放
+
+ALTER TABLE user_sessions ALTER COLUMN family_id DROP DEFAULT;
+
+CREATE UNIQUE INDEX idx_user_sessions_token_hash ON user_sessions(token_hash);
+CREATE INDEX idx_user_sessions_family_id ON user_sessions(family_id);
+CREATE INDEX idx_user_sessions_user_id ON user_sessions(user_id);
@@ -0,0 +1,26 @@
+-- WebAuthn 凭据（通行密钥 / 硬件安全密钥）
+CREATE TABLE webauthn_credentials (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    credential_id TEXT NOT NULL UNIQUE,  -- base64url 编码
+    name VARCHAR(100) NOT NULL,  -- 用户自定义名称，如 "YubiKey 5"
+    passkey JSONB NOT NULL,
+    sign_count BIGINT NOT NULL DEFAULT 0,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    last_used_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX idx_webauthn_credentials_user_id ON webauthn_credentials(user_id);
+
+-- 进行中的注册/认证流程状态，只能使用一次
+CREATE TABLE webauthn_ceremonies (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    purpose VARCHAR(20) NOT NULL CHECK (purpose IN ('register', 'authenticate')),
+    login_challenge_id UUID REFERENCES login_challenges(id) ON DELETE CASCADE,  -- 作为登录第二步时关联的挑战
+    state JSONB NOT NULL,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_webauthn_ceremonies_expires_at ON webauthn_ceremonies(expires_at);
@@ -0,0 +1,5 @@
+-- 验证码错误次数，超过上限后该验证码作废
+ALTER TABLE email_verifications ADD COLUMN attempts INTEGER NOT NULL DEFAULT 0;
+
+-- 按地址统计发送频率、查找最新验证码
+CREATE INDEX idx_email_verifications_email ON email_verifications(LOWER(email), created_at);
@@ -0,0 +1,15 @@
+-- 短信验证码，记录请求来源 IP 用于按 IP 限制发送频率
+CREATE TABLE phone_verifications (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    phone VARCHAR(20) NOT NULL,
+    code VARCHAR(6) NOT NULL,
+    purpose VARCHAR(20) NOT NULL CHECK (purpose IN ('login', 'register', 'reset_password')),
+    ip_address INET NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    used BOOLEAN NOT NULL DEFAULT FALSE,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_phone_verifications_phone ON phone_verifications(phone, created_at);
+CREATE INDEX idx_phone_verifications_ip ON phone_verifications(ip_address, created_at);
@@ -0,0 +1,2 @@
+-- 密码哈希改用 Argon2id，盐值已编码在哈希字符串中
+ALTER TABLE users DROP COLUMN salt;
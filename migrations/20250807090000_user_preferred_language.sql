@@ -0,0 +1,2 @@
+-- 界面和通知的语言偏好，为空时按请求的 Accept-Language 选择
+ALTER TABLE users ADD COLUMN preferred_language VARCHAR(10);
@@ -0,0 +1,24 @@
+-- 实名认证：证件号以密文保存，id_card_hash 为带密钥的摘要，用于判断证件是否已被其他账号使用
+ALTER TABLE users ALTER COLUMN id_card TYPE TEXT;
+ALTER TABLE users ADD COLUMN id_verified_at TIMESTAMP WITH TIME ZONE;
+
+CREATE TABLE identity_verifications (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    real_name VARCHAR(100) NOT NULL,
+    id_card_encrypted TEXT NOT NULL,
+    id_card_hash VARCHAR(64) NOT NULL,
+    id_card_masked VARCHAR(18) NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'approved', 'rejected')),
+    provider VARCHAR(50) NOT NULL,
+    provider_message TEXT,
+    reviewed_by UUID REFERENCES users(id),
+    review_note TEXT,
+    reviewed_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+-- 每个用户同时只能有一条待审核申请，同一证件只能认证一个账号
+CREATE UNIQUE INDEX idx_identity_verifications_pending ON identity_verifications(user_id) WHERE status = 'pending';
+CREATE UNIQUE INDEX idx_identity_verifications_approved ON identity_verifications(id_card_hash) WHERE status = 'approved';
+CREATE INDEX idx_identity_verifications_status ON identity_verifications(status, created_at);
@@ -0,0 +1,8 @@
+-- 更换邮箱、手机号时向新地址发送的验证码
+ALTER TABLE email_verifications DROP CONSTRAINT email_verifications_purpose_check;
+ALTER TABLE email_verifications ADD CONSTRAINT email_verifications_purpose_check
+    CHECK (purpose IN ('login', 'register', 'reset_password', 'change_email'));
+
+ALTER TABLE phone_verifications DROP CONSTRAINT phone_verifications_purpose_check;
+ALTER TABLE phone_verifications ADD CONSTRAINT phone_verifications_purpose_check
+    CHECK (purpose IN ('login', 'register', 'reset_password', 'change_phone'));
@@ -0,0 +1,2 @@
+-- 自助注销时间。注销后账户保留 ID 以关联依法留存的账单和支付记录，个人信息已清除
+ALTER TABLE users ADD COLUMN deleted_at TIMESTAMP WITH TIME ZONE;
@@ -0,0 +1,44 @@
+-- 组织账户：多名成员共用余额和实例
+CREATE TABLE organizations (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    name VARCHAR(100) NOT NULL,
+    balance DECIMAL(10,2) NOT NULL DEFAULT 0.00,  -- 组织余额(CNY)
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE organization_members (
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    role VARCHAR(20) NOT NULL CHECK (role IN ('owner', 'admin', 'operator', 'viewer', 'billing')),
+    joined_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (organization_id, user_id)
+);
+
+CREATE INDEX idx_organization_members_user_id ON organization_members(user_id);
+
+-- 成员邀请，链接中的令牌只保存哈希；同一组织同一邮箱只保留一条待接受的邀请
+CREATE TABLE organization_invitations (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    email VARCHAR(255) NOT NULL,
+    role VARCHAR(20) NOT NULL CHECK (role IN ('owner', 'admin', 'operator', 'viewer', 'billing')),
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    invited_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    accepted_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    accepted_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX idx_organization_invitations_pending
+    ON organization_invitations(organization_id, LOWER(email)) WHERE accepted_at IS NULL;
+
+-- 实例、账单和支付记录可归属于组织；为空时属于 user_id 本人，非空时 user_id 为创建或转入的成员
+ALTER TABLE vm_instances ADD COLUMN organization_id UUID REFERENCES organizations(id);
+ALTER TABLE invoices ADD COLUMN organization_id UUID REFERENCES organizations(id);
+ALTER TABLE payment_records ADD COLUMN organization_id UUID REFERENCES organizations(id);
+
+CREATE INDEX idx_vm_instances_organization_id ON vm_instances(organization_id);
+CREATE INDEX idx_invoices_organization_id ON invoices(organization_id);
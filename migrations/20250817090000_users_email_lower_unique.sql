@@ -0,0 +1,5 @@
+-- 邮箱不区分大小写：统一保存为小写，并按 LOWER(email) 建唯一索引。
+-- 已存在仅大小写不同的重复邮箱时迁移会失败，需先人工合并或修改这些账户
+UPDATE users SET email = LOWER(email) WHERE email <> LOWER(email);
+
+CREATE UNIQUE INDEX idx_users_email_lower ON users (LOWER(email));
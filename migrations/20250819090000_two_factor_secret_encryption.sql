@@ -0,0 +1,6 @@
+-- TOTP 密钥改为用 DATA_ENCRYPTION_KEY 加密保存（base64(nonce || ciphertext)）。
+-- 迁移时无法取得密钥，已有的明文密钥在下次验证成功时改写为密文
+ALTER TABLE user_two_factor ADD COLUMN secret_encrypted TEXT;
+ALTER TABLE user_two_factor ALTER COLUMN secret DROP NOT NULL;
+ALTER TABLE user_two_factor ADD CONSTRAINT chk_user_two_factor_secret
+    CHECK (secret IS NOT NULL OR secret_encrypted IS NOT NULL);
@@ -0,0 +1,2 @@
+-- init.sql 中 metric_type 的注释已过时，以 models::monitoring::ALL_METRICS 为准
+COMMENT ON COLUMN monitoring_data.metric_type IS 'cpu, memory, disk, disk_read, disk_write, net_in, net_out';
@@ -0,0 +1,384 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use log::info;
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::database::DbPool;
+use crate::models::identity::{IdentityQueueQuery, IdentityVerification, ReviewIdentityRequest, IDENTITY_COLUMNS};
+use crate::models::pve_node::{NodeCapacityReport, PveNode, PVE_NODE_COLUMNS};
+use crate::services::audit_service::{
+    self, AuditEntry, ACTION_IDENTITY_REVIEWED, ACTION_IDENTITY_VIEWED, ACTION_ROLE_CHANGED, ACTION_TWO_FACTOR_RESET,
+    TARGET_IDENTITY_VERIFICATION, TARGET_USER,
+};
+use crate::services::revocation_service::RevocationStore;
+use crate::services::{capacity_service, identity_service, session_service, two_factor_service};
+use crate::middleware::auth::AuthUser;
+use crate::middleware::rbac::Role;
+use crate::utils::crypto::FieldCipher;
+use crate::utils::error::AppError;
+use crate::utils::i18n;
+use crate::utils::locale::Locale;
+use crate::utils::request::client_ip;
+use crate::utils::validation::ValidatedJson;
+
+#[derive(sqlx::FromRow)]
+struct LatestNodeStatus {
+    pve_node_id: Uuid,
+    cpu_usage: f64,
+    memory_usage: f64,
+    disk_usage: f64,
+    uptime: i64,
+    load_1: f64,
+    load_5: f64,
+    load_15: f64,
+    recorded_at: chrono::DateTime<chrono::Utc>,
+}
+
+pub async fn node_health(
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let nodes = sqlx::query_as::<_, PveNode>(&format!(
+        "SELECT {} FROM pve_nodes ORDER BY name",
+        PVE_NODE_COLUMNS
+    ))
+    .fetch_all(&**pool)
+    .await
+    .map_err(|e| AppError::internal("查询节点失败", e))?;
+
+    let statuses = sqlx::query_as::<_, LatestNodeStatus>(
+        r#"
+        SELECT DISTINCT ON (pve_node_id) *
+        FROM pve_node_status
+        ORDER BY pve_node_id, recorded_at DESC
+        "#
+    )
+    .fetch_all(&**pool)
+    .await
+    .map_err(|e| AppError::internal("查询节点状态失败", e))?;
+
+    let nodes: Vec<_> = nodes
+        .iter()
+        .map(|node| {
+            let latest = statuses.iter().find(|s| s.pve_node_id == node.id).map(|s| json!({
+                "cpu_usage": s.cpu_usage,
+                "memory_usage": s.memory_usage,
+                "disk_usage": s.disk_usage,
+                "uptime": s.uptime,
+                "load_average": [s.load_1, s.load_5, s.load_15],
+                "recorded_at": s.recorded_at.to_rfc3339()
+            }));
+            json!({
+                "node": node,
+                "schedulable": node.is_schedulable(),
+                "latest_status": latest
+            })
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(json!({"nodes": nodes})))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CapacityReportQuery {
+    #[serde(default)]
+    pub drift_only: bool,
+}
+
+/// 各节点最近一次资源对账结果
+pub async fn capacity_report(
+    pool: web::Data<PgPool>,
+    query: web::Query<CapacityReportQuery>,
+) -> Result<HttpResponse, AppError> {
+    let reports = sqlx::query_as::<_, NodeCapacityReport>(
+        r#"
+        SELECT * FROM (
+            SELECT DISTINCT ON (pve_node_id) *
+            FROM node_capacity_reports
+            ORDER BY pve_node_id, created_at DESC
+        ) latest
+        WHERE NOT $1 OR has_drift OR overcommitted OR error IS NOT NULL
+        ORDER BY created_at DESC
+        "#
+    )
+    .bind(query.drift_only)
+    .fetch_all(&**pool)
+    .await
+    .map_err(|e| AppError::internal("查询资源对账报告失败", e))?;
+
+    Ok(HttpResponse::Ok().json(json!({"reports": reports})))
+}
+
+/// 立即执行一次资源对账
+pub async fn sync_capacity(
+    pool: web::Data<DbPool>,
+    locale: Locale,
+) -> Result<HttpResponse, AppError> {
+    let reports = capacity_service::sync_all(&pool)
+        .await
+        .map_err(|e| AppError::internal("资源对账失败", e))?;
+    Ok(HttpResponse::Ok().json(json!({"message": i18n::translate(locale, "资源对账完成"), "reports": reports})))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangeRoleRequest {
+    pub role: Role,
+    pub reason: Option<String>,
+}
+
+/// 修改用户角色，旧角色、新角色和原因写入审计记录
+pub async fn change_user_role(
+    pool: web::Data<DbPool>,
+    revocations: web::Data<RevocationStore>,
+    req: HttpRequest,
+    admin: AuthUser,
+    locale: Locale,
+    user_id: web::Path<Uuid>,
+    body: web::Json<ChangeRoleRequest>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+    // 避免管理员误把自己降级后失去管理权限
+    if user_id == admin.id {
+        return Err(AppError::bad_request("不能修改自己的角色"));
+    }
+
+    let mut tx = pool.begin().await.map_err(|e| AppError::internal("开启事务失败", e))?;
+
+    let current = sqlx::query_scalar::<_, String>(
+        "SELECT role FROM users WHERE id = $1 AND status <> 'deleted' FOR UPDATE"
+    )
+    .bind(user_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| AppError::internal("查询用户角色失败", e))?
+    .ok_or_else(|| AppError::not_found("用户不存在"))?;
+
+    let new_role = body.role.as_str();
+    if current == new_role {
+        return Ok(HttpResponse::Ok().json(json!({"message": i18n::translate(locale, "角色未变化"), "user_id": user_id, "role": body.role})));
+    }
+
+    sqlx::query("UPDATE users SET role = $1, updated_at = NOW() WHERE id = $2")
+        .bind(new_role)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| AppError::internal("修改用户角色失败", e))?;
+
+    let entry = AuditEntry {
+        actor_id: admin.id,
+        action: ACTION_ROLE_CHANGED,
+        target_type: TARGET_USER,
+        target_id: user_id,
+        details: json!({"from": current, "to": new_role, "reason": body.reason}),
+        ip_address: &client_ip(&req),
+    };
+    audit_service::record(&mut *tx, entry)
+        .await
+        .map_err(|e| AppError::internal("写入审计记录失败", e))?;
+
+    // 角色随令牌签发，与角色变更在同一事务中吊销会话，用户需重新登录才能拿到新角色
+    session_service::revoke_all_sessions(&mut *tx, user_id)
+        .await
+        .map_err(|e| AppError::internal("吊销用户会话失败", e))?;
+
+    tx.commit().await.map_err(|e| AppError::internal("提交事务失败", e))?;
+    revocations.forget_user(user_id);
+
+    info!("管理员 {} 将用户 {} 的角色从 {} 改为 {}", admin.username, user_id, current, new_role);
+    Ok(HttpResponse::Ok().json(json!({"message": i18n::translate(locale, "角色已更新"), "user_id": user_id, "role": body.role})))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResetTwoFactorRequest {
+    pub reason: Option<String>,
+}
+
+/// 用户丢失验证器和恢复码时，由管理员核实身份后清除两步验证
+pub async fn reset_two_factor(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+    admin: AuthUser,
+    locale: Locale,
+    user_id: web::Path<Uuid>,
+    body: web::Json<ResetTwoFactorRequest>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+
+    let mut tx = pool.begin().await.map_err(|e| AppError::internal("开启事务失败", e))?;
+
+    let exists = sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM users WHERE id = $1 AND status <> 'deleted')")
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| AppError::internal("查询用户失败", e))?;
+    if !exists {
+        return Err(AppError::not_found("用户不存在"));
+    }
+
+    let was_enabled = two_factor_service::reset(&mut tx, user_id)
+        .await
+        .map_err(|e| AppError::internal("重置两步验证失败", e))?;
+
+    let entry = AuditEntry {
+        actor_id: admin.id,
+        action: ACTION_TWO_FACTOR_RESET,
+        target_type: TARGET_USER,
+        target_id: user_id,
+        details: json!({"was_enabled": was_enabled, "reason": body.reason}),
+        ip_address: &client_ip(&req),
+    };
+    audit_service::record(&mut *tx, entry)
+        .await
+        .map_err(|e| AppError::internal("写入审计记录失败", e))?;
+
+    tx.commit().await.map_err(|e| AppError::internal("提交事务失败", e))?;
+
+    info!("管理员 {} 重置了用户 {} 的两步验证", admin.username, user_id);
+    Ok(HttpResponse::Ok().json(json!({"message": i18n::translate(locale, "两步验证已重置"), "user_id": user_id})))
+}
+
+/// 实名认证审核队列，默认按提交时间列出待审核的申请
+pub async fn list_identity_verifications(
+    pool: web::Data<DbPool>,
+    query: web::Query<IdentityQueueQuery>,
+) -> Result<HttpResponse, AppError> {
+    let status = query.status.as_deref().unwrap_or(identity_service::STATUS_PENDING);
+    if ![identity_service::STATUS_PENDING, identity_service::STATUS_APPROVED, identity_service::STATUS_REJECTED].contains(&status) {
+        return Err(AppError::bad_request("无效的审核状态"));
+    }
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+
+    let verifications = sqlx::query_as::<_, IdentityVerification>(&format!(
+        "SELECT {} FROM identity_verifications WHERE status = $1 ORDER BY created_at LIMIT $2",
+        IDENTITY_COLUMNS
+    ))
+    .bind(status)
+    .bind(limit)
+    .fetch_all(&**pool)
+    .await
+    .map_err(|e| AppError::internal("查询实名认证申请失败", e))?;
+
+    Ok(HttpResponse::Ok().json(json!({"verifications": verifications})))
+}
+
+/// 审核时查看完整证件号，每次查看都记录审计日志
+pub async fn get_identity_verification(
+    pool: web::Data<DbPool>,
+    cipher: Option<web::Data<FieldCipher>>,
+    req: HttpRequest,
+    admin: AuthUser,
+    verification_id: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let cipher = cipher.ok_or_else(|| AppError::unavailable("实名认证服务未配置"))?;
+    let verification_id = verification_id.into_inner();
+
+    let verification = sqlx::query_as::<_, IdentityVerification>(&format!(
+        "SELECT {} FROM identity_verifications WHERE id = $1",
+        IDENTITY_COLUMNS
+    ))
+    .bind(verification_id)
+    .fetch_optional(&**pool)
+    .await
+    .map_err(|e| AppError::internal("查询实名认证申请失败", e))?
+    .ok_or_else(|| AppError::not_found("实名认证申请不存在"))?;
+
+    let encrypted = sqlx::query_scalar::<_, String>("SELECT id_card_encrypted FROM identity_verifications WHERE id = $1")
+        .bind(verification_id)
+        .fetch_one(&**pool)
+        .await
+        .map_err(|e| AppError::internal("查询实名认证申请失败", e))?;
+    let id_card = cipher.decrypt(&encrypted).map_err(|e| AppError::internal("解密证件号失败", e))?;
+
+    let entry = AuditEntry {
+        actor_id: admin.id,
+        action: ACTION_IDENTITY_VIEWED,
+        target_type: TARGET_IDENTITY_VERIFICATION,
+        target_id: verification_id,
+        details: json!({"user_id": verification.user_id}),
+        ip_address: &client_ip(&req),
+    };
+    audit_service::record(&**pool, entry)
+        .await
+        .map_err(|e| AppError::internal("写入审计记录失败", e))?;
+
+    Ok(HttpResponse::Ok().json(json!({"verification": verification, "id_card": id_card})))
+}
+
+/// 人工审核实名认证申请，通过后写入用户的实名信息
+pub async fn review_identity_verification(
+    pool: web::Data<DbPool>,
+    req: HttpRequest,
+    admin: AuthUser,
+    locale: Locale,
+    verification_id: web::Path<Uuid>,
+    body: ValidatedJson<ReviewIdentityRequest>,
+) -> Result<HttpResponse, AppError> {
+    let verification_id = verification_id.into_inner();
+
+    let mut tx = pool.begin().await.map_err(|e| AppError::internal("开启事务失败", e))?;
+
+    let (user_id, real_name, id_card_encrypted, status) = sqlx::query_as::<_, (Uuid, String, String, String)>(
+        "SELECT user_id, real_name, id_card_encrypted, status FROM identity_verifications WHERE id = $1 FOR UPDATE"
+    )
+    .bind(verification_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| AppError::internal("查询实名认证申请失败", e))?
+    .ok_or_else(|| AppError::not_found("实名认证申请不存在"))?;
+    if status != identity_service::STATUS_PENDING {
+        return Err(AppError::conflict("该申请已审核"));
+    }
+
+    let new_status = if body.approved {
+        identity_service::STATUS_APPROVED
+    } else {
+        identity_service::STATUS_REJECTED
+    };
+    sqlx::query(
+        r#"
+        UPDATE identity_verifications
+        SET status = $2, reviewed_by = $3, review_note = $4, reviewed_at = NOW()
+        WHERE id = $1
+        "#
+    )
+    .bind(verification_id)
+    .bind(new_status)
+    .bind(admin.id)
+    .bind(&body.note)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| match e.as_database_error().and_then(|db| db.constraint()) {
+        Some("idx_identity_verifications_approved") => AppError::conflict("该证件已被其他账号认证"),
+        _ => AppError::internal("更新实名认证申请失败", e),
+    })?;
+
+    if body.approved {
+        identity_service::mark_verified(&mut *tx, user_id, &real_name, &id_card_encrypted)
+            .await
+            .map_err(|e| AppError::internal("更新实名信息失败", e))?;
+    }
+
+    let entry = AuditEntry {
+        actor_id: admin.id,
+        action: ACTION_IDENTITY_REVIEWED,
+        target_type: TARGET_IDENTITY_VERIFICATION,
+        target_id: verification_id,
+        details: json!({"user_id": user_id, "approved": body.approved, "note": body.note}),
+        ip_address: &client_ip(&req),
+    };
+    audit_service::record(&mut *tx, entry)
+        .await
+        .map_err(|e| AppError::internal("写入审计记录失败", e))?;
+
+    tx.commit().await.map_err(|e| AppError::internal("提交事务失败", e))?;
+
+    info!("管理员 {} 审核实名认证 {}: {}", admin.username, verification_id, new_status);
+    let message = if body.approved { "实名认证已通过" } else { "实名认证已驳回" };
+    Ok(HttpResponse::Ok().json(json!({
+        "message": i18n::translate(locale, message),
+        "verification_id": verification_id,
+        "status": new_status
+    })))
+}
@@ -0,0 +1,290 @@
+use actix_web::{web, HttpResponse};
+use log::warn;
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::middleware::auth::AuthUser;
+use crate::models::alert::{
+    AlertHistory, AlertHistoryQuery, AlertRule, AlertRuleRequest, CreateChannelRequest,
+    NotificationChannel, CHANNEL_EMAIL, CHANNEL_WEBHOOK, RULE_INSTANCE_DOWN,
+};
+use crate::models::monitoring::{METRIC_CPU, METRIC_DISK, METRIC_MEMORY};
+use crate::models::organization::{OrgPermission, OrgRole};
+use crate::services::alert_service;
+use crate::utils::error::AppError;
+use crate::utils::i18n;
+use crate::utils::locale::Locale;
+use crate::utils::validation::ValidatedJson;
+
+const RULE_METRICS: [&str; 4] = [METRIC_CPU, METRIC_MEMORY, METRIC_DISK, RULE_INSTANCE_DOWN];
+const COMPARATORS: [&str; 4] = ["gt", "gte", "lt", "lte"];
+
+pub async fn list_channels(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> Result<HttpResponse, AppError> {
+    let channels = sqlx::query_as::<_, NotificationChannel>(
+        "SELECT * FROM notification_channels WHERE user_id = $1 ORDER BY created_at"
+    )
+    .bind(user.id)
+    .fetch_all(&**pool)
+    .await
+    .map_err(|e| AppError::internal("查询通知渠道失败", e))?;
+
+    Ok(HttpResponse::Ok().json(json!({"channels": channels})))
+}
+
+pub async fn create_channel(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+    locale: Locale,
+    channel_data: ValidatedJson<CreateChannelRequest>,
+) -> Result<HttpResponse, AppError> {
+    let target = channel_data.target.trim();
+    let valid = match channel_data.channel_type.as_str() {
+        CHANNEL_EMAIL => target.parse::<lettre::Address>().is_ok(),
+        CHANNEL_WEBHOOK => match alert_service::resolve_webhook_target(target).await {
+            Ok(_) => true,
+            Err(reason) => {
+                warn!("用户 {} 提交的 webhook 地址 {} 被拒绝: {}", user.username, target, reason);
+                return Err(AppError::bad_request("通知地址必须是可访问的公网地址"));
+            }
+        },
+        _ => return Err(AppError::bad_request("不支持的通知渠道类型")),
+    };
+    if !valid {
+        return Err(AppError::bad_request("无效的通知地址"));
+    }
+
+    let channel = sqlx::query_as::<_, NotificationChannel>(
+        r#"
+        INSERT INTO notification_channels (user_id, name, channel_type, target)
+        VALUES ($1, $2, $3, $4)
+        RETURNING *
+        "#
+    )
+    .bind(user.id)
+    .bind(&channel_data.name)
+    .bind(&channel_data.channel_type)
+    .bind(target)
+    .fetch_one(&**pool)
+    .await
+    .map_err(|e| AppError::internal("创建通知渠道失败", e))?;
+
+    Ok(HttpResponse::Ok().json(json!({"message": i18n::translate(locale, "通知渠道创建成功"), "channel": channel})))
+}
+
+pub async fn delete_channel(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+    locale: Locale,
+    channel_id: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let channel_id = channel_id.into_inner();
+
+    let mut tx = pool.begin().await.map_err(|e| AppError::internal("开启事务失败", e))?;
+
+    let deleted = sqlx::query("DELETE FROM notification_channels WHERE id = $1 AND user_id = $2")
+        .bind(channel_id)
+        .bind(user.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| AppError::internal("删除通知渠道失败", e))?;
+    if deleted.rows_affected() == 0 {
+        return Err(AppError::not_found("通知渠道不存在"));
+    }
+
+    // 同时从引用它的告警规则中移除
+    sqlx::query(
+        "UPDATE alert_rules SET channel_ids = array_remove(channel_ids, $1) WHERE user_id = $2"
+    )
+    .bind(channel_id)
+    .bind(user.id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| AppError::internal("更新告警规则渠道失败", e))?;
+
+    tx.commit().await.map_err(|e| AppError::internal("提交事务失败", e))?;
+    Ok(HttpResponse::Ok().json(json!({"message": i18n::translate(locale, "通知渠道已删除")})))
+}
+
+pub async fn list_rules(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> Result<HttpResponse, AppError> {
+    let rules = sqlx::query_as::<_, AlertRule>(
+        "SELECT * FROM alert_rules WHERE user_id = $1 ORDER BY created_at"
+    )
+    .bind(user.id)
+    .fetch_all(&**pool)
+    .await
+    .map_err(|e| AppError::internal("查询告警规则失败", e))?;
+
+    Ok(HttpResponse::Ok().json(json!({"rules": rules})))
+}
+
+/// 校验规则参数以及实例、通知渠道的归属
+async fn validate_rule(pool: &PgPool, user_id: Uuid, rule: &AlertRuleRequest) -> Result<(), AppError> {
+    if !RULE_METRICS.contains(&rule.metric_type.as_str()) {
+        return Err(AppError::bad_request("不支持的监控指标"));
+    }
+    if rule.comparator.as_deref().is_some_and(|c| !COMPARATORS.contains(&c)) {
+        return Err(AppError::bad_request("不支持的比较方式"));
+    }
+    if rule.metric_type != RULE_INSTANCE_DOWN && rule.threshold.is_none() {
+        return Err(AppError::bad_request("缺少告警阈值"));
+    }
+
+    // 个人实例需是本人的，组织实例需有查看权限
+    let owns_instance = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM vm_instances v
+            WHERE v.id = $1 AND v.status <> 'deleted'
+              AND CASE WHEN v.organization_id IS NULL THEN v.user_id = $2
+                       ELSE EXISTS(
+                           SELECT 1 FROM organization_members m
+                           WHERE m.organization_id = v.organization_id AND m.user_id = $2 AND m.role = ANY($3)
+                       )
+                  END
+        )
+        "#
+    )
+    .bind(rule.vm_instance_id)
+    .bind(user_id)
+    .bind(OrgRole::with_permission(OrgPermission::ViewInstances))
+    .fetch_one(pool)
+    .await
+    .map_err(|e| AppError::internal("校验告警规则失败", e))?;
+    if !owns_instance {
+        return Err(AppError::bad_request("实例不存在"));
+    }
+
+    let owned_channels = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM notification_channels WHERE id = ANY($1) AND user_id = $2"
+    )
+    .bind(&rule.channel_ids)
+    .bind(user_id)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| AppError::internal("校验告警规则失败", e))?;
+    if owned_channels as usize != rule.channel_ids.len() {
+        return Err(AppError::bad_request("通知渠道不存在"));
+    }
+
+    Ok(())
+}
+
+pub async fn create_rule(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+    locale: Locale,
+    rule_data: ValidatedJson<AlertRuleRequest>,
+) -> Result<HttpResponse, AppError> {
+    validate_rule(&pool, user.id, &rule_data).await?;
+
+    let rule = sqlx::query_as::<_, AlertRule>(
+        r#"
+        INSERT INTO alert_rules
+            (user_id, vm_instance_id, name, metric_type, comparator, threshold, duration_minutes, channel_ids, enabled)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        RETURNING *
+        "#
+    )
+    .bind(user.id)
+    .bind(rule_data.vm_instance_id)
+    .bind(&rule_data.name)
+    .bind(&rule_data.metric_type)
+    .bind(rule_data.comparator.as_deref().unwrap_or("gt"))
+    .bind(rule_data.threshold)
+    .bind(rule_data.duration_minutes.unwrap_or(5))
+    .bind(&rule_data.channel_ids)
+    .bind(rule_data.enabled.unwrap_or(true))
+    .fetch_one(&**pool)
+    .await
+    .map_err(|e| AppError::internal("创建告警规则失败", e))?;
+
+    Ok(HttpResponse::Ok().json(json!({"message": i18n::translate(locale, "告警规则创建成功"), "rule": rule})))
+}
+
+pub async fn update_rule(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+    locale: Locale,
+    rule_id: web::Path<Uuid>,
+    rule_data: ValidatedJson<AlertRuleRequest>,
+) -> Result<HttpResponse, AppError> {
+    validate_rule(&pool, user.id, &rule_data).await?;
+
+    // 规则条件变化后重置为 ok 状态，重新开始评估
+    let rule = sqlx::query_as::<_, AlertRule>(
+        r#"
+        UPDATE alert_rules
+        SET vm_instance_id = $3, name = $4, metric_type = $5, comparator = $6, threshold = $7,
+            duration_minutes = $8, channel_ids = $9, enabled = $10, state = 'ok', updated_at = NOW()
+        WHERE id = $1 AND user_id = $2
+        RETURNING *
+        "#
+    )
+    .bind(rule_id.into_inner())
+    .bind(user.id)
+    .bind(rule_data.vm_instance_id)
+    .bind(&rule_data.name)
+    .bind(&rule_data.metric_type)
+    .bind(rule_data.comparator.as_deref().unwrap_or("gt"))
+    .bind(rule_data.threshold)
+    .bind(rule_data.duration_minutes.unwrap_or(5))
+    .bind(&rule_data.channel_ids)
+    .bind(rule_data.enabled.unwrap_or(true))
+    .fetch_optional(&**pool)
+    .await
+    .map_err(|e| AppError::internal("更新告警规则失败", e))?
+    .ok_or_else(|| AppError::not_found("告警规则不存在"))?;
+
+    Ok(HttpResponse::Ok().json(json!({"message": i18n::translate(locale, "告警规则更新成功"), "rule": rule})))
+}
+
+pub async fn delete_rule(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+    locale: Locale,
+    rule_id: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let result = sqlx::query("DELETE FROM alert_rules WHERE id = $1 AND user_id = $2")
+        .bind(rule_id.into_inner())
+        .bind(user.id)
+        .execute(&**pool)
+        .await
+        .map_err(|e| AppError::internal("删除告警规则失败", e))?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::not_found("告警规则不存在"));
+    }
+    Ok(HttpResponse::Ok().json(json!({"message": i18n::translate(locale, "告警规则已删除")})))
+}
+
+pub async fn list_history(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+    query: web::Query<AlertHistoryQuery>,
+) -> Result<HttpResponse, AppError> {
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+
+    let history = sqlx::query_as::<_, AlertHistory>(
+        r#"
+        SELECT h.* FROM alert_history h
+        JOIN alert_rules r ON r.id = h.rule_id
+        WHERE r.user_id = $1 AND ($2::UUID IS NULL OR h.rule_id = $2)
+        ORDER BY h.created_at DESC
+        LIMIT $3
+        "#
+    )
+    .bind(user.id)
+    .bind(query.rule_id)
+    .bind(limit)
+    .fetch_all(&**pool)
+    .await
+    .map_err(|e| AppError::internal("查询告警历史失败", e))?;
+
+    Ok(HttpResponse::Ok().json(json!({"history": history})))
+}
@@ -0,0 +1,65 @@
+use actix_web::{web, HttpResponse};
+use log::info;
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::middleware::auth::AuthUser;
+use crate::models::api_key::CreateApiKeyRequest;
+use crate::services::api_key_service;
+use crate::utils::error::AppError;
+use crate::utils::i18n;
+use crate::utils::locale::Locale;
+use crate::utils::validation::ValidatedJson;
+
+const MAX_KEYS_PER_USER: usize = 20;
+
+pub async fn list(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> Result<HttpResponse, AppError> {
+    let keys = api_key_service::list(&pool, user.id)
+        .await
+        .map_err(|e| AppError::internal("查询 API 密钥失败", e))?;
+    Ok(HttpResponse::Ok().json(json!({"api_keys": keys})))
+}
+
+/// 创建 API 密钥，明文只在本次响应中返回
+pub async fn create(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+    locale: Locale,
+    body: ValidatedJson<CreateApiKeyRequest>,
+) -> Result<HttpResponse, AppError> {
+    let keys = api_key_service::list(&pool, user.id)
+        .await
+        .map_err(|e| AppError::internal("查询 API 密钥失败", e))?;
+    if keys.len() >= MAX_KEYS_PER_USER {
+        return Err(AppError::bad_request("API 密钥数量已达上限"));
+    }
+
+    let created = api_key_service::create(&pool, user.id, &body)
+        .await
+        .map_err(|e| AppError::internal("创建 API 密钥失败", e))?;
+    info!("用户 {} 创建了 API 密钥 {}", user.username, created.info.key_prefix);
+    Ok(HttpResponse::Created().json(json!({
+        "message": i18n::translate(locale, "API 密钥已创建，请立即保存，关闭后将无法再次查看"),
+        "key": created.key,
+        "api_key": created.info
+    })))
+}
+
+pub async fn revoke(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+    locale: Locale,
+    key_id: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let revoked = api_key_service::revoke(&pool, user.id, key_id.into_inner())
+        .await
+        .map_err(|e| AppError::internal("吊销 API 密钥失败", e))?;
+    if !revoked {
+        return Err(AppError::not_found("API 密钥不存在"));
+    }
+    Ok(HttpResponse::Ok().json(json!({"message": i18n::translate(locale, "API 密钥已吊销")})))
+}
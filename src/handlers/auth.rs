@@ -1,39 +1,71 @@
+use actix_web::http::header;
 use actix_web::{web, HttpResponse, Responder};
 use serde_json::json;
-use bcrypt::{hash, DEFAULT_COST};
-use chrono::{Duration, Utc};
+use chrono::Utc;
 use uuid::Uuid;
 use sqlx::PgPool;
 use log::{info, error};
-use crate::models::user::{User, LoginRequest, EmailVerification, ResetPasswordRequest};
+use crate::models::user::{
+    User, LoginRequest, PhoneCodePurpose, PhoneLoginRequest, PhoneResetPasswordRequest, ResetPasswordRequest,
+    RefreshRequest, SendEmailCodeRequest, SendPhoneCodeRequest, TwoFactorLoginRequest, VerificationPurpose,
+    VerifyEmailRequest, VerifyPhoneRequest,
+};
+use crate::services::captcha_service::CaptchaVerifier;
+use crate::services::email_service::{self, EmailService};
+use crate::services::email_template::EmailTemplate;
+use crate::services::login_throttle_service::{self, LoginGate};
+use crate::services::revocation_service::RevocationStore;
+use crate::services::session_service::{self, RotateOutcome};
+use crate::services::sms_service::{self, SmsProvider};
+use crate::services::two_factor_service::{self, ChallengeLookup};
+use crate::services::verification_service::{self, VerificationError};
+use crate::services::webauthn_service::{self, PasskeyService};
+use crate::models::webauthn::{FinishPasskeyLoginRequest, StartPasskeyLoginRequest};
+use crate::middleware::auth::AuthUser;
 use crate::utils::create_jwt;
+use crate::utils::crypto::FieldCipher;
+use crate::utils::jwt::{JwtKeys, TokenSubject};
+use crate::utils::error::AppError;
+use crate::utils::i18n;
+use crate::utils::locale::Locale;
+use crate::utils::password;
+use crate::utils::validation::{validate_password, validate_phone, validate_username, ValidatedJson};
+use crate::utils::request::{client_ip, user_agent};
 
 use serde::{Deserialize, Serialize};
+use validator::Validate;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Validate)]
 pub struct RegisterRequest {
+    #[validate(custom = "validate_username")]
     pub username: String,
+    #[validate(email(message = "邮箱格式不正确"))]
     pub email: Option<String>,
+    #[validate(custom = "validate_phone")]
     pub phone: Option<String>,
+    #[validate(custom = "validate_password")]
     pub password: String,
 }
 
 pub async fn register(
     pool: web::Data<PgPool>,
-    user_data: web::Json<RegisterRequest>,
-) -> impl Responder {
-    let hashed_password = match hash(&user_data.password, DEFAULT_COST) {
-        Ok(hash) => hash,
-        Err(_) => return HttpResponse::InternalServerError().json(json!({"error": "密码加密失败"}))
-    };
+    locale: Locale,
+    user_data: ValidatedJson<RegisterRequest>,
+) -> Result<HttpResponse, AppError> {
+    // 手机号统一保存为不带区号的 11 位号码，便于验证码登录时查找；邮箱不区分大小写，统一保存为小写
+    let phone = user_data.phone.as_deref().and_then(sms_service::normalize_phone);
+    let email = user_data.email.as_deref().map(|email| email.trim().to_lowercase());
+
+    let hashed_password = password::hash_password(&user_data.password)
+        .await
+        .map_err(|e| AppError::internal("密码加密失败", e))?;
 
     let user = User {
         id: Uuid::new_v4(),
         username: user_data.username.clone(),
-        email: user_data.email.clone(),
-        phone: user_data.phone.clone(),
+        email,
+        phone,
         password_hash: hashed_password,
-        salt: Uuid::new_v4().to_string(),
         real_name: None,
         status: "active".to_string(),
         phone_verified: false,
@@ -48,12 +80,15 @@ pub async fn register(
         province: None,
         city: None,
         two_factor_enabled: false,
+        preferred_language: None,
+        id_verified_at: None,
+        deleted_at: None,
     };
 
-    match sqlx::query_as::<_, User>(
+    let user = sqlx::query_as::<_, User>(
         r#"
-        INSERT INTO users (username, email, phone, password_hash, salt, status)
-        VALUES ($1, $2, $3, $4, $5, $6)
+        INSERT INTO users (username, email, phone, password_hash, status)
+        VALUES ($1, $2, $3, $4, $5)
         RETURNING *
         "#
     )
@@ -61,268 +96,796 @@ pub async fn register(
     .bind(&user.email)
     .bind(&user.phone)
     .bind(&user.password_hash)
-    .bind(&user.salt)
     .bind("active")
     .fetch_one(&**pool)
     .await
-    {
-        Ok(user) => HttpResponse::Ok().json(json!({
-            "message": "注册成功",
-            "user": {
-                "id": user.id,
-                "username": user.username,
-                "email": user.email,
-                "phone": user.phone
-            }
-        })),
-        Err(e) => {
-            error!("注册失败: {}", e);
-            if e.to_string().contains("duplicate key") {
-                HttpResponse::Conflict().json(json!({"error": "用户名/邮箱/手机号已存在"}))
-            } else {
-                HttpResponse::InternalServerError().json(json!({
-                    "error": "注册失败",
-                    "details": e.to_string()
-                }))
-            }
+    .map_err(|e| match e.as_database_error() {
+        Some(db) if db.is_unique_violation() => AppError::conflict("用户名/邮箱/手机号已存在"),
+        _ => AppError::internal("注册失败", e),
+    })?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "message": i18n::translate(locale, "注册成功"),
+        "user": {
+            "id": user.id,
+            "username": user.username,
+            "email": user.email,
+            "phone": user.phone
         }
-    }
+    })))
 }
 
+/// 密码登录。失败次数按账户和来源 IP 分别统计，超过阈值后要求人机验证、递增等待时间并临时锁定
 pub async fn login(
     pool: web::Data<PgPool>,
-    login_data: web::Json<LoginRequest>,
-) -> impl Responder {
-    let user = match sqlx::query_as::<_, User>(
-        "SELECT * FROM users WHERE username = $1 OR email = $1 OR phone = $1"
+    keys: web::Data<JwtKeys>,
+    email_service: Option<web::Data<EmailService>>,
+    captcha: Option<web::Data<CaptchaVerifier>>,
+    login_data: ValidatedJson<LoginRequest>,
+    req: actix_web::HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let ip_address = client_ip(&req);
+    let user = sqlx::query_as::<_, User>(
+        "SELECT * FROM users WHERE username = $1 OR LOWER(email) = LOWER($1) OR phone = $1"
     )
     .bind(&login_data.username)
     .fetch_optional(&**pool)
     .await
-    {
-        Ok(Some(user)) => user,
-        Ok(None) => return HttpResponse::Unauthorized().json(json!({"error": "用户名或密码错误"})),
-        Err(_) => return HttpResponse::InternalServerError().json(json!({"error": "登录失败"}))
+    .map_err(|e| AppError::internal("查询用户失败", e))?;
+    // 账户不存在时按登录名计数，响应与账户存在时保持一致
+    let account_key = match &user {
+        Some(user) => user.id.to_string(),
+        None => format!("name:{}", login_data.username.trim().to_lowercase()),
+    };
+
+    let gate = login_throttle_service::check(&pool, &account_key, &ip_address)
+        .await
+        .map_err(|e| AppError::internal("查询登录失败记录失败", e))?;
+    let captcha_required = match gate {
+        LoginGate::Allowed { captcha_required } => captcha_required,
+        LoginGate::Delayed(secs) => {
+            return Err(AppError::too_many_requests(format!("登录失败次数过多，请 {} 秒后重试", secs), Some(secs)));
+        }
+        LoginGate::AccountLocked(until) => {
+            return Err(AppError::Locked { message: "登录失败次数过多，账户已临时锁定".into(), until });
+        }
+        LoginGate::IpLocked(until) => {
+            let secs = (until - Utc::now()).num_seconds().max(1);
+            return Err(AppError::too_many_requests("当前网络登录失败次数过多，请稍后重试", Some(secs)));
+        }
     };
 
-    if !bcrypt::verify(&login_data.password, &user.password_hash).unwrap_or(false) {
-        return HttpResponse::Unauthorized().json(json!({"error": "用户名或密码错误"}));
+    // 未配置人机验证服务时不校验（启动时会告警），仍由递增等待和临时锁定兜底
+    if captcha_required && let Some(captcha) = &captcha {
+        let Some(token) = login_data.captcha_token.as_deref() else {
+            return Err(AppError::CaptchaRequired("请完成人机验证".into()));
+        };
+        let passed = captcha.verify(token, &ip_address).await.map_err(|e| {
+            error!("人机验证请求失败: {}", e);
+            AppError::unavailable("人机验证服务不可用")
+        })?;
+        if !passed {
+            return Err(AppError::CaptchaRequired("人机验证失败".into()));
+        }
     }
 
-    let jwt_secret = std::env::var("JWT_SECRET").unwrap_or_else(|_| "secret".to_string());
-    let expiry = Utc::now() + Duration::days(7);
-    
-    match create_jwt(&user.username, &jwt_secret, expiry) {
-        Ok(token) => {
-            // 更新用户登录信息
-            let _ = sqlx::query(
-                "UPDATE users SET last_login = $1, login_count = login_count + 1 WHERE id = $2"
-            )
-            .bind(Utc::now())
-            .bind(&user.id)
-            .execute(&**pool)
-            .await;
-
-            HttpResponse::Ok().json(json!({
-                "message": "登录成功",
-                "token": token,
-                "expires_at": expiry.to_rfc3339(),
-                "user": {
-                    "id": user.id,
-                    "username": user.username,
-                    "email": user.email,
-                    "phone": user.phone
+    let verification = match &user {
+        Some(user) => Some(password::verify_password(&login_data.password, &user.password_hash).await),
+        None => {
+            password::verify_dummy(&login_data.password).await;
+            None
+        }
+    };
+    let user = match (user, verification) {
+        (Some(user), Some(verification)) if verification.valid => {
+            if verification.needs_rehash {
+                rehash_password(&pool, user.id, &login_data.password).await;
+            }
+            user
+        }
+        (user, _) => {
+            let outcome = login_throttle_service::record_failure(&pool, &account_key, &ip_address)
+                .await
+                .map_err(|e| AppError::internal("记录登录失败次数失败", e))?;
+            if let Some(locked_until) = outcome.account_locked_until {
+                if let Some(user) = &user {
+                    info!("用户 {} 连续登录失败，账户锁定至 {}", user.username, locked_until);
+                    let locale = Locale::for_user(user.preferred_language.as_deref(), &req);
+                    notify_account_locked(email_service, user, &ip_address, locked_until, locale);
                 }
-            }))
-        },
-        Err(_) => HttpResponse::InternalServerError().json(json!({"error": "令牌生成失败"}))
+                return Err(AppError::Locked {
+                    message: "登录失败次数过多，账户已临时锁定".into(),
+                    until: locked_until,
+                });
+            }
+            return Err(AppError::InvalidCredentials { captcha_required: outcome.captcha_required });
+        }
+    };
+
+    if let Err(e) = login_throttle_service::clear_account(&pool, &account_key).await {
+        error!("清除登录失败记录失败: {}", e);
     }
+    ensure_active(&user)?;
+
+    begin_login(&pool, &keys, &user, &req).await
 }
 
-pub async fn logout(
-    pool: web::Data<PgPool>,
-    req: actix_web::HttpRequest,
-) -> impl Responder {
-    let auth_header = req.headers().get("Authorization");
-    let token = match auth_header {
-        Some(header) => {
-            let header_str = header.to_str().unwrap_or("");
-            if header_str.starts_with("Bearer ") {
-                header_str[7..].to_string()
-            } else {
-                return HttpResponse::BadRequest().json(json!({"error": "无效的授权头"}));
-            }
+/// 登录成功后把 bcrypt 或参数过时的哈希升级为当前的 Argon2id 参数，失败不影响本次登录
+async fn rehash_password(pool: &PgPool, user_id: Uuid, plain: &str) {
+    let hashed = match password::hash_password(plain).await {
+        Ok(hashed) => hashed,
+        Err(e) => {
+            error!("{}", e);
+            return;
         }
-        None => return HttpResponse::Unauthorized().json(json!({"error": "缺少授权令牌"})),
     };
+    if let Err(e) = sqlx::query("UPDATE users SET password_hash = $1 WHERE id = $2")
+        .bind(&hashed)
+        .bind(user_id)
+        .execute(pool)
+        .await
+    {
+        error!("更新密码哈希失败: {}", e);
+    }
+}
 
-    let jwt_secret = std::env::var("JWT_SECRET").unwrap_or_else(|_| "secret".to_string());
-    let claims = match crate::utils::validate_jwt(&token, &jwt_secret) {
-        Ok(claims) => claims,
-        Err(_) => return HttpResponse::Unauthorized().json(json!({"error": "无效令牌"})),
+/// 在后台发送账户锁定通知，不阻塞登录响应
+fn notify_account_locked(
+    email_service: Option<web::Data<EmailService>>,
+    user: &User,
+    ip_address: &str,
+    locked_until: chrono::DateTime<Utc>,
+    locale: Locale,
+) {
+    let (Some(email_service), Some(email)) = (email_service, user.email.clone()) else {
+        return;
     };
+    let username = user.username.clone();
+    let ip_address = ip_address.to_string();
+    actix_web::rt::spawn(async move {
+        let template = EmailTemplate::AccountLocked {
+            username: &username,
+            ip_address: &ip_address,
+            locked_until,
+        };
+        if let Err(e) = email_service.send_template(&email, &template, locale).await {
+            error!("发送账户锁定通知失败: {}", e);
+        }
+    });
+}
 
-    // 将令牌加入黑名单
-    match sqlx::query(
-        r#"
-        INSERT INTO user_sessions (user_id, token_hash, expires_at)
-        VALUES ($1, $2, $3)
-        "#
-    )
-    .bind(Uuid::parse_str(&claims.sub).ok())
-    .bind(&token)
-    .bind(Utc::now() + Duration::hours(1)) // 令牌失效时间比JWT过期时间稍长
-    .execute(&**pool)
-    .await
-    {
-        Ok(_) => HttpResponse::Ok().json(json!({"message": "登出成功"})),
-        Err(_) => HttpResponse::InternalServerError().json(json!({"error": "登出失败"})),
+/// 登录挑战无效或验证码错误次数过多
+fn challenge_error(lookup: ChallengeLookup) -> AppError {
+    match lookup {
+        ChallengeLookup::TooManyAttempts => AppError::too_many_requests("验证码错误次数过多，请重新登录", None),
+        _ => AppError::unauthorized("登录已过期，请重新登录"),
     }
 }
 
-pub async fn refresh(
+/// 登录第二步：提交 TOTP 验证码或恢复码
+pub async fn login_two_factor(
     pool: web::Data<PgPool>,
+    keys: web::Data<JwtKeys>,
+    cipher: Option<web::Data<FieldCipher>>,
+    body: web::Json<TwoFactorLoginRequest>,
     req: actix_web::HttpRequest,
-) -> impl Responder {
-    let auth_header = req.headers().get("Authorization");
-    let token = match auth_header {
-        Some(header) => {
-            let header_str = header.to_str().unwrap_or("");
-            if header_str.starts_with("Bearer ") {
-                header_str[7..].to_string()
-            } else {
-                return HttpResponse::BadRequest().json(json!({"error": "无效的授权头"}));
-            }
+) -> Result<HttpResponse, AppError> {
+    let (challenge_id, user_id) = match two_factor_service::find_challenge(&pool, &body.challenge_token)
+        .await
+        .map_err(|e| AppError::internal("查询登录挑战失败", e))?
+    {
+        ChallengeLookup::Valid { id, user_id } => (id, user_id),
+        lookup => return Err(challenge_error(lookup)),
+    };
+
+    let verified = match (&body.code, &body.recovery_code) {
+        (Some(code), _) => {
+            let cipher = cipher.ok_or_else(|| AppError::unavailable("两步验证服务未配置"))?;
+            two_factor_service::verify_code(&pool, &cipher, user_id, code).await
+        }
+        (None, Some(recovery_code)) => two_factor_service::use_recovery_code(&pool, user_id, recovery_code).await,
+        (None, None) => return Err(AppError::bad_request("请提供验证码或恢复码")),
+    }
+    .map_err(|e| AppError::internal("校验两步验证码失败", e))?;
+    if !verified {
+        if let Err(e) = two_factor_service::record_failed_attempt(&pool, challenge_id).await {
+            error!("记录验证失败次数失败: {}", e);
         }
-        None => return HttpResponse::Unauthorized().json(json!({"error": "缺少授权令牌"})),
+        return Err(AppError::unauthorized("验证码错误"));
+    }
+
+    let consumed = two_factor_service::consume_challenge(&pool, challenge_id)
+        .await
+        .map_err(|e| AppError::internal("更新登录挑战失败", e))?;
+    if !consumed {
+        return Err(AppError::unauthorized("登录已过期，请重新登录"));
+    }
+
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(&**pool)
+        .await
+        .map_err(|e| AppError::internal("查询用户失败", e))?;
+
+    complete_login(&pool, &keys, &user, &req).await
+}
+
+/// 通行密钥登录第一步，可免密码登录，也可作为密码登录后的第二步
+pub async fn start_passkey_login(
+    pool: web::Data<PgPool>,
+    passkeys: web::Data<PasskeyService>,
+    body: web::Json<StartPasskeyLoginRequest>,
+) -> Result<HttpResponse, AppError> {
+    let (user_id, login_challenge_id) = match (&body.challenge_token, &body.username) {
+        (Some(token), _) => match two_factor_service::find_challenge(&pool, token)
+            .await
+            .map_err(|e| AppError::internal("查询登录挑战失败", e))?
+        {
+            ChallengeLookup::Valid { id, user_id } => (user_id, Some(id)),
+            lookup => return Err(challenge_error(lookup)),
+        },
+        (None, Some(username)) => {
+            let user_id = sqlx::query_scalar::<_, Uuid>(
+                "SELECT id FROM users WHERE (username = $1 OR LOWER(email) = LOWER($1) OR phone = $1) AND status = 'active'"
+            )
+            .bind(username)
+            .fetch_optional(&**pool)
+            .await
+            .map_err(|e| AppError::internal("查询用户失败", e))?
+            .ok_or_else(|| AppError::bad_request("该账号无法使用通行密钥登录"))?;
+            (user_id, None)
+        }
+        (None, None) => return Err(AppError::bad_request("请提供用户名")),
     };
 
-    let jwt_secret = std::env::var("JWT_SECRET").unwrap_or_else(|_| "secret".to_string());
-    let claims = match crate::utils::validate_jwt(&token, &jwt_secret) {
-        Ok(claims) => claims,
-        Err(_) => return HttpResponse::Unauthorized().json(json!({"error": "无效令牌"})),
+    let (ceremony_id, options) = passkeys
+        .start_authentication(&pool, user_id, login_challenge_id)
+        .await?
+        .ok_or_else(|| AppError::bad_request("该账号无法使用通行密钥登录"))?;
+    Ok(HttpResponse::Ok().json(json!({
+        "ceremony_id": ceremony_id,
+        "options": options
+    })))
+}
+
+pub async fn finish_passkey_login(
+    pool: web::Data<PgPool>,
+    keys: web::Data<JwtKeys>,
+    passkeys: web::Data<PasskeyService>,
+    body: web::Json<FinishPasskeyLoginRequest>,
+    req: actix_web::HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let verified = passkeys.finish_authentication(&pool, body.ceremony_id, &body.credential).await?;
+
+    if let Some(challenge_id) = verified.login_challenge_id {
+        let consumed = two_factor_service::consume_challenge(&pool, challenge_id)
+            .await
+            .map_err(|e| AppError::internal("更新登录挑战失败", e))?;
+        if !consumed {
+            return Err(AppError::unauthorized("登录已过期，请重新登录"));
+        }
+    }
+
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1 AND status = 'active'")
+        .bind(verified.user_id)
+        .fetch_optional(&**pool)
+        .await
+        .map_err(|e| AppError::internal("查询用户失败", e))?
+        .ok_or_else(|| AppError::unauthorized("账号不可用"))?;
+
+    complete_login(&pool, &keys, &user, &req).await
+}
+
+/// 第一因素通过后：已启用两步验证时先返回挑战令牌，验证码或通行密钥通过后再签发令牌
+async fn begin_login(
+    pool: &PgPool,
+    keys: &JwtKeys,
+    user: &User,
+    req: &actix_web::HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    if user.two_factor_enabled {
+        let (challenge_token, expires_at) = two_factor_service::create_challenge(pool, user.id)
+            .await
+            .map_err(|e| AppError::internal("创建登录挑战失败", e))?;
+        let mut methods = vec!["totp", "recovery_code"];
+        match webauthn_service::has_passkeys(pool, user.id).await {
+            Ok(true) => methods.push("webauthn"),
+            Ok(false) => (),
+            Err(e) => error!("查询通行密钥失败: {}", e),
+        }
+
+        let locale = Locale::for_user(user.preferred_language.as_deref(), req);
+        return Ok(HttpResponse::Ok().json(json!({
+            "message": i18n::translate(locale, "请完成两步验证"),
+            "two_factor_required": true,
+            "two_factor_methods": methods,
+            "challenge_token": challenge_token,
+            "challenge_expires_at": expires_at.to_rfc3339()
+        })));
+    }
+
+    complete_login(pool, keys, user, req).await
+}
+
+/// 已停用或已注销的账户不能登录，密码校验通过后才提示，避免泄露账户状态
+fn ensure_active(user: &User) -> Result<(), AppError> {
+    if user.status == "active" {
+        Ok(())
+    } else {
+        Err(AppError::forbidden("账户已停用，无法登录"))
+    }
+}
+
+/// 创建会话并签发访问令牌和刷新令牌，签发前再次确认账户状态
+async fn complete_login(
+    pool: &PgPool,
+    keys: &JwtKeys,
+    user: &User,
+    req: &actix_web::HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    ensure_active(user)?;
+    let refresh = session_service::create_session(
+        pool,
+        user.id,
+        &client_ip(req),
+        user_agent(req).as_deref(),
+    )
+    .await
+    .map_err(|e| AppError::internal("创建会话失败", e))?;
+
+    let expiry = Utc::now() + session_service::access_token_ttl();
+
+    let subject = TokenSubject {
+        user_id: user.id,
+        username: &user.username,
+        role: &user.role,
     };
+    let token = create_jwt(keys, &subject, refresh.family_id, expiry)
+        .map_err(|e| AppError::internal("令牌生成失败", e))?;
 
-    // 将旧令牌加入黑名单
+    // 更新用户登录信息
     let _ = sqlx::query(
-        r#"
-        INSERT INTO user_sessions (user_id, token_hash, expires_at)
-        VALUES ($1, $2, $3)
-        "#
+        "UPDATE users SET last_login = $1, last_login_ip = $3::INET, login_count = login_count + 1 WHERE id = $2"
     )
-    .bind(Uuid::parse_str(&claims.sub).ok())
-    .bind(&token)
-    .bind(Utc::now() + Duration::hours(1))
-    .execute(&**pool)
+    .bind(Utc::now())
+    .bind(user.id)
+    .bind(client_ip(req))
+    .execute(pool)
     .await;
 
-    // 生成新令牌
-    let expiry = Utc::now() + Duration::days(7);
-    match create_jwt(&claims.sub, &jwt_secret, expiry) {
-        Ok(new_token) => HttpResponse::Ok().json(json!({
-            "message": "令牌刷新成功",
-            "token": new_token,
-            "expires_at": expiry.to_rfc3339()
-        })),
-        Err(_) => HttpResponse::InternalServerError().json(json!({"error": "令牌生成失败"}))
-    }
+    let locale = Locale::for_user(user.preferred_language.as_deref(), req);
+    Ok(HttpResponse::Ok().json(json!({
+        "message": i18n::translate(locale, "登录成功"),
+        "access_token": token,
+        "token_type": "Bearer",
+        "expires_at": expiry.to_rfc3339(),
+        "refresh_token": refresh.token,
+        "refresh_expires_at": refresh.expires_at.to_rfc3339(),
+        "user": {
+            "id": user.id,
+            "username": user.username,
+            "email": user.email,
+            "phone": user.phone
+        }
+    })))
 }
 
-pub async fn verify(
+pub async fn logout(
+    pool: web::Data<PgPool>,
+    revocations: web::Data<RevocationStore>,
+    user: AuthUser,
+    locale: Locale,
+) -> Result<HttpResponse, AppError> {
+    let (session_id, claims) = user.session().ok_or_else(|| AppError::forbidden("API 密钥不能执行此操作"))?;
+
+    // 吊销当前访问令牌以及所属会话的刷新令牌
+    revocations
+        .revoke(&pool, claims)
+        .await
+        .map_err(|e| AppError::internal("吊销令牌失败", e))?;
+    session_service::revoke_family(&pool, session_id)
+        .await
+        .map_err(|e| AppError::internal("吊销会话失败", e))?;
+    revocations.forget_user(user.id);
+
+    info!("用户 {} 已登出", user.username);
+    Ok(HttpResponse::Ok().json(json!({"message": i18n::translate(locale, "登出成功")})))
+}
+
+pub async fn refresh(
     pool: web::Data<PgPool>,
-    verification_data: web::Json<EmailVerification>,
-) -> impl Responder {
-    // 查询验证码记录
-    let record = match sqlx::query_as::<_, EmailVerification>(
-        "SELECT * FROM email_verifications 
-        WHERE email = $1 AND code = $2 AND purpose = $3 AND used = false AND expires_at > NOW()"
+    keys: web::Data<JwtKeys>,
+    refresh_data: web::Json<RefreshRequest>,
+    req: actix_web::HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let outcome = session_service::rotate(
+        &pool,
+        &refresh_data.refresh_token,
+        &client_ip(&req),
+        user_agent(&req).as_deref(),
     )
-    .bind(&verification_data.email)
-    .bind(&verification_data.code)
-    .bind(&verification_data.purpose)
-    .fetch_optional(&**pool)
     .await
-    {
-        Ok(Some(record)) => record,
-        Ok(None) => return HttpResponse::BadRequest().json(json!({"error": "无效验证码"})),
-        Err(_) => return HttpResponse::InternalServerError().json(json!({"error": "验证失败"}))
+    .map_err(|e| AppError::internal("刷新令牌失败", e))?;
+
+    let (user_id, refresh) = match outcome {
+        RotateOutcome::Rotated { user_id, refresh } => (user_id, refresh),
+        RotateOutcome::Invalid => return Err(AppError::unauthorized("无效令牌")),
+        RotateOutcome::Reused => return Err(AppError::unauthorized("刷新令牌已失效，请重新登录")),
     };
 
-    // 标记验证码为已使用
-    let _ = sqlx::query(
-        "UPDATE email_verifications SET used = true WHERE id = $1"
+    // 角色可能已被修改，重新读取
+    let (username, role, preferred_language) = sqlx::query_as::<_, (String, String, Option<String>)>(
+        "SELECT username, role, preferred_language FROM users WHERE id = $1"
     )
-    .bind(&record.id)
-    .execute(&**pool)
-    .await;
+    .bind(user_id)
+    .fetch_one(&**pool)
+    .await
+    .map_err(|e| AppError::internal("查询用户失败", e))?;
 
-    // 根据验证目的更新用户状态
-    match verification_data.purpose.as_str() {
-        "register" => {
-            let _ = sqlx::query(
-                "UPDATE users SET email_verified = true WHERE email = $1"
-            )
-            .bind(&verification_data.email)
-            .execute(&**pool)
-            .await;
+    // 生成新令牌
+    let expiry = Utc::now() + session_service::access_token_ttl();
+    let subject = TokenSubject {
+        user_id,
+        username: &username,
+        role: &role,
+    };
+    let new_token = create_jwt(&keys, &subject, refresh.family_id, expiry)
+        .map_err(|e| AppError::internal("令牌生成失败", e))?;
+
+    let locale = Locale::for_user(preferred_language.as_deref(), &req);
+    Ok(HttpResponse::Ok().json(json!({
+        "message": i18n::translate(locale, "令牌刷新成功"),
+        "access_token": new_token,
+        "token_type": "Bearer",
+        "expires_at": expiry.to_rfc3339(),
+        "refresh_token": refresh.token,
+        "refresh_expires_at": refresh.expires_at.to_rfc3339()
+    })))
+}
+
+pub async fn list_sessions(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> Result<HttpResponse, AppError> {
+    let sessions = session_service::list_sessions(&pool, user.id)
+        .await
+        .map_err(|e| AppError::internal("查询会话失败", e))?;
+
+    let sessions: Vec<_> = sessions
+        .into_iter()
+        .map(|session| {
+            let current = user.session().is_some_and(|(session_id, _)| session.id == session_id);
+            json!({"session": session, "current": current})
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(json!({"sessions": sessions})))
+}
+
+pub async fn revoke_session(
+    pool: web::Data<PgPool>,
+    revocations: web::Data<RevocationStore>,
+    user: AuthUser,
+    locale: Locale,
+    session_id: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let revoked = session_service::revoke_user_session(&pool, user.id, session_id.into_inner())
+        .await
+        .map_err(|e| AppError::internal("注销会话失败", e))?;
+    revocations.forget_user(user.id);
+    if !revoked {
+        return Err(AppError::not_found("会话不存在"));
+    }
+    Ok(HttpResponse::Ok().json(json!({"message": i18n::translate(locale, "会话已注销")})))
+}
+
+/// 退出其他设备：吊销当前会话以外的全部刷新令牌，其访问令牌随之失效
+pub async fn revoke_other_sessions(
+    pool: web::Data<PgPool>,
+    revocations: web::Data<RevocationStore>,
+    user: AuthUser,
+    locale: Locale,
+) -> Result<HttpResponse, AppError> {
+    let (session_id, _) = user.session().ok_or_else(|| AppError::forbidden("API 密钥不能执行此操作"))?;
+
+    let count = session_service::revoke_other_sessions(&pool, user.id, session_id)
+        .await
+        .map_err(|e| AppError::internal("注销会话失败", e))?;
+    revocations.forget_user(user.id);
+    Ok(HttpResponse::Ok().json(json!({"message": i18n::translate(locale, "已退出其他设备"), "revoked": count})))
+}
+
+/// 发送邮箱验证码。无论邮箱是否注册都返回相同结果，避免被用来探测账户
+pub async fn send_email_code(
+    pool: web::Data<PgPool>,
+    email_service: Option<web::Data<EmailService>>,
+    req: actix_web::HttpRequest,
+    locale: Locale,
+    body: ValidatedJson<SendEmailCodeRequest>,
+) -> Result<HttpResponse, AppError> {
+    let email_service = email_service.ok_or_else(|| AppError::unavailable("邮件服务未配置"))?;
+    let email = body.email.trim();
+    if !email_service::is_valid_address(email) {
+        return Err(AppError::bad_request("邮箱格式不正确"));
+    }
+
+    // 注册验证只发给尚未验证的账户，重置密码只发给正常状态的账户；同时取出收件人的语言偏好
+    let recipient = match body.purpose {
+        VerificationPurpose::Register => sqlx::query_scalar::<_, Option<String>>(
+            "SELECT preferred_language FROM users WHERE LOWER(email) = LOWER($1) AND email_verified = false AND status = 'active'"
+        ),
+        VerificationPurpose::ResetPassword => sqlx::query_scalar::<_, Option<String>>(
+            "SELECT preferred_language FROM users WHERE LOWER(email) = LOWER($1) AND status = 'active'"
+        ),
+        // 更换邮箱需要登录，走个人资料接口
+        VerificationPurpose::ChangeEmail => return Err(AppError::bad_request("不支持的验证码用途")),
+    }
+    .bind(email)
+    .fetch_optional(&**pool)
+    .await
+    .map_err(|e| AppError::internal("查询用户失败", e))?;
+
+    // 不符合条件的地址同样计入频率限制，响应上与正常发送无法区分
+    let issued = verification_service::issue_email_code(&pool, email, body.purpose).await?;
+
+    if let Some(preferred_language) = recipient {
+        let template = EmailTemplate::VerificationCode {
+            code: &issued.code,
+            purpose: body.purpose,
+            ttl_minutes: verification_service::CODE_TTL_MINUTES,
+        };
+        if let Err(e) = email_service.send_template(email, &template, Locale::for_user(preferred_language.as_deref(), &req)).await {
+            error!("发送验证码邮件失败: {}", e);
+            if let Err(e) = verification_service::discard_email_code(&pool, issued.id).await {
+                error!("删除验证码失败: {}", e);
+            }
+            return Err(AppError::unavailable("邮件发送失败，请稍后重试"));
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
+        "message": i18n::translate(locale, "验证码已发送"),
+        "expires_in": verification_service::CODE_TTL_MINUTES * 60
+    })))
+}
+
+/// 校验邮箱验证码。注册验证码校验后立即作废；重置密码验证码只做检查，提交新密码时才作废
+pub async fn verify(
+    pool: web::Data<PgPool>,
+    locale: Locale,
+    verification_data: ValidatedJson<VerifyEmailRequest>,
+) -> Result<HttpResponse, AppError> {
+    let email = verification_data.email.trim();
+    let valid = match verification_data.purpose {
+        VerificationPurpose::Register => {
+            verification_service::consume_email_code(&pool, email, verification_data.purpose, &verification_data.code).await
         }
-        "reset_password" => {
-            // 重置密码流程会在下一步处理
+        VerificationPurpose::ResetPassword => {
+            verification_service::check_email_code(&pool, email, verification_data.purpose, &verification_data.code).await
         }
-        _ => {}
+        VerificationPurpose::ChangeEmail => return Err(AppError::bad_request("不支持的验证码用途")),
+    }
+    .map_err(|e| AppError::internal("校验验证码失败", e))?;
+    if !valid {
+        return Err(AppError::bad_request("无效验证码"));
     }
 
-    HttpResponse::Ok().json(json!({
-        "message": "验证成功",
-        "email": verification_data.email,
+    if verification_data.purpose == VerificationPurpose::Register
+        && let Some(user_id) = user_id_by_email(&pool, email).await?
+    {
+        sqlx::query("UPDATE users SET email_verified = true WHERE id = $1")
+            .bind(user_id)
+            .execute(&**pool)
+            .await
+            .map_err(|e| AppError::internal("更新邮箱验证状态失败", e))?;
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
+        "message": i18n::translate(locale, "验证成功"),
+        "email": email,
         "purpose": verification_data.purpose
-    }))
+    })))
+}
+
+/// 按邮箱查找用户，邮箱按 LOWER(email) 唯一
+async fn user_id_by_email(pool: &PgPool, email: &str) -> Result<Option<Uuid>, AppError> {
+    sqlx::query_scalar::<_, Uuid>("SELECT id FROM users WHERE LOWER(email) = LOWER($1)")
+        .bind(email)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| AppError::internal("查询用户失败", e))
 }
 
 pub async fn reset_password(
     pool: web::Data<PgPool>,
-    reset_data: web::Json<ResetPasswordRequest>,
-) -> impl Responder {
-    // 验证验证码
-    let _ = match sqlx::query_as::<_, EmailVerification>(
-        "SELECT * FROM email_verifications 
-        WHERE email = $1 AND code = $2 AND purpose = 'reset_password' AND used = false AND expires_at > NOW()"
-    )
-    .bind(&reset_data.email)
-    .bind(&reset_data.code)
-    .fetch_optional(&**pool)
-    .await
-    {
-        Ok(Some(_)) => (),
-        _ => return HttpResponse::BadRequest().json(json!({"error": "无效验证码"}))
-    };
+    revocations: web::Data<RevocationStore>,
+    locale: Locale,
+    reset_data: ValidatedJson<ResetPasswordRequest>,
+) -> Result<HttpResponse, AppError> {
+    let email = reset_data.email.trim();
 
-    // 标记验证码为已使用
-    let _ = sqlx::query(
-        "UPDATE email_verifications SET used = true 
-        WHERE email = $1 AND code = $2 AND purpose = 'reset_password'"
-    )
-    .bind(&reset_data.email)
-    .bind(&reset_data.code)
-    .execute(&**pool)
-    .await;
+    // 验证并作废验证码
+    let valid = verification_service::consume_email_code(&pool, email, VerificationPurpose::ResetPassword, &reset_data.code)
+        .await
+        .map_err(|e| AppError::internal("校验验证码失败", e))?;
+    if !valid {
+        return Err(AppError::bad_request("无效验证码"));
+    }
 
     // 加密新密码
-    let hashed_password = match hash(&reset_data.new_password, DEFAULT_COST) {
-        Ok(hash) => hash,
-        Err(_) => return HttpResponse::InternalServerError().json(json!({"error": "密码加密失败"}))
+    let hashed_password = password::hash_password(&reset_data.new_password)
+        .await
+        .map_err(|e| AppError::internal("密码加密失败", e))?;
+
+    // 更新用户密码，并吊销全部会话：重置密码通常意味着账户可能已泄露
+    let Some(user_id) = user_id_by_email(&pool, email).await? else {
+        return Ok(HttpResponse::Ok().json(json!({"message": i18n::translate(locale, "密码重置成功")})));
     };
+    let mut tx = pool.begin().await.map_err(|e| AppError::internal("开启事务失败", e))?;
+    sqlx::query("UPDATE users SET password_hash = $1 WHERE id = $2")
+        .bind(&hashed_password)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| AppError::internal("重置密码失败", e))?;
+    session_service::revoke_all_sessions(&mut *tx, user_id)
+        .await
+        .map_err(|e| AppError::internal("注销会话失败", e))?;
+    tx.commit().await.map_err(|e| AppError::internal("提交事务失败", e))?;
+    revocations.forget_user(user_id);
+
+    Ok(HttpResponse::Ok().json(json!({"message": i18n::translate(locale, "密码重置成功")})))
+}
+
+/// 发送短信验证码。与邮箱验证码一样，不通过响应暴露号码是否注册
+pub async fn send_phone_code(
+    pool: web::Data<PgPool>,
+    sms: Option<web::Data<dyn SmsProvider>>,
+    req: actix_web::HttpRequest,
+    locale: Locale,
+    body: ValidatedJson<SendPhoneCodeRequest>,
+) -> Result<HttpResponse, AppError> {
+    let sms = sms.ok_or_else(|| AppError::unavailable("短信服务未配置"))?;
+    let phone = normalized_phone(&body.phone)?;
+
+    let eligible = match body.purpose {
+        PhoneCodePurpose::Register => sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS(SELECT 1 FROM users WHERE phone = $1 AND phone_verified = false AND status = 'active')"
+        ),
+        PhoneCodePurpose::Login | PhoneCodePurpose::ResetPassword => sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS(SELECT 1 FROM users WHERE phone = $1 AND status = 'active')"
+        ),
+        PhoneCodePurpose::ChangePhone => return Err(AppError::bad_request("不支持的验证码用途")),
+    }
+    .bind(&phone)
+    .fetch_one(&**pool)
+    .await
+    .map_err(|e| AppError::internal("查询用户失败", e))?;
+
+    let issued = verification_service::issue_phone_code(&pool, &phone, body.purpose, &client_ip(&req)).await?;
+
+    if eligible && let Err(e) = sms.send_code(&phone, &issued.code, body.purpose).await {
+        error!("发送短信验证码失败: {}", e);
+        if let Err(e) = verification_service::discard_phone_code(&pool, issued.id).await {
+            error!("删除验证码失败: {}", e);
+        }
+        return Err(AppError::unavailable("短信发送失败，请稍后重试"));
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
+        "message": i18n::translate(locale, "验证码已发送"),
+        "expires_in": verification_service::CODE_TTL_MINUTES * 60
+    })))
+}
+
+/// 注册后验证手机号
+pub async fn verify_phone(
+    pool: web::Data<PgPool>,
+    locale: Locale,
+    body: ValidatedJson<VerifyPhoneRequest>,
+) -> Result<HttpResponse, AppError> {
+    let phone = normalized_phone(&body.phone)?;
+
+    let valid = verification_service::consume_phone_code(&pool, &phone, PhoneCodePurpose::Register, &body.code)
+        .await
+        .map_err(|e| AppError::internal("校验验证码失败", e))?;
+    if !valid {
+        return Err(AppError::bad_request("无效验证码"));
+    }
+
+    sqlx::query("UPDATE users SET phone_verified = true WHERE phone = $1")
+        .bind(&phone)
+        .execute(&**pool)
+        .await
+        .map_err(|e| AppError::internal("更新手机验证状态失败", e))?;
+
+    Ok(HttpResponse::Ok().json(json!({"message": i18n::translate(locale, "验证成功"), "phone": phone})))
+}
 
-    // 更新用户密码
-    match sqlx::query(
-        "UPDATE users SET password_hash = $1 WHERE email = $2"
+/// 短信验证码登录，可代替密码作为第一步，已启用两步验证的账户仍需完成第二步
+pub async fn login_phone(
+    pool: web::Data<PgPool>,
+    keys: web::Data<JwtKeys>,
+    body: ValidatedJson<PhoneLoginRequest>,
+    req: actix_web::HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let phone = normalized_phone(&body.phone)?;
+
+    let valid = verification_service::consume_phone_code(&pool, &phone, PhoneCodePurpose::Login, &body.code)
+        .await
+        .map_err(|e| AppError::internal("校验验证码失败", e))?;
+    if !valid {
+        return Err(AppError::unauthorized("验证码错误或已过期"));
+    }
+
+    // 能收到验证码即证明持有该号码
+    let user = sqlx::query_as::<_, User>(
+        "UPDATE users SET phone_verified = true WHERE phone = $1 AND status = 'active' RETURNING *"
     )
-    .bind(&hashed_password)
-    .bind(&reset_data.email)
-    .execute(&**pool)
+    .bind(&phone)
+    .fetch_optional(&**pool)
     .await
-    {
-        Ok(_) => HttpResponse::Ok().json(json!({"message": "密码重置成功"})),
-        Err(_) => HttpResponse::InternalServerError().json(json!({"error": "密码重置失败"}))
+    .map_err(|e| AppError::internal("查询用户失败", e))?
+    .ok_or_else(|| AppError::unauthorized("验证码错误或已过期"))?;
+
+    begin_login(&pool, &keys, &user, &req).await
+}
+
+pub async fn reset_password_by_phone(
+    pool: web::Data<PgPool>,
+    revocations: web::Data<RevocationStore>,
+    locale: Locale,
+    body: ValidatedJson<PhoneResetPasswordRequest>,
+) -> Result<HttpResponse, AppError> {
+    let phone = normalized_phone(&body.phone)?;
+
+    let valid = verification_service::consume_phone_code(&pool, &phone, PhoneCodePurpose::ResetPassword, &body.code)
+        .await
+        .map_err(|e| AppError::internal("校验验证码失败", e))?;
+    if !valid {
+        return Err(AppError::bad_request("无效验证码"));
     }
+
+    let hashed_password = password::hash_password(&body.new_password)
+        .await
+        .map_err(|e| AppError::internal("密码加密失败", e))?;
+
+    let mut tx = pool.begin().await.map_err(|e| AppError::internal("开启事务失败", e))?;
+    let user_id = sqlx::query_scalar::<_, Uuid>("UPDATE users SET password_hash = $1 WHERE phone = $2 RETURNING id")
+        .bind(&hashed_password)
+        .bind(&phone)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| AppError::internal("重置密码失败", e))?;
+    if let Some(user_id) = user_id {
+        session_service::revoke_all_sessions(&mut *tx, user_id)
+            .await
+            .map_err(|e| AppError::internal("注销会话失败", e))?;
+    }
+    tx.commit().await.map_err(|e| AppError::internal("提交事务失败", e))?;
+    if let Some(user_id) = user_id {
+        revocations.forget_user(user_id);
+    }
+
+    Ok(HttpResponse::Ok().json(json!({"message": i18n::translate(locale, "密码重置成功")})))
+}
+
+/// 请求体已校验过格式，这里取统一后的号码
+fn normalized_phone(phone: &str) -> Result<String, AppError> {
+    sms_service::normalize_phone(phone).ok_or_else(|| AppError::bad_request("手机号格式不正确"))
+}
+
+impl From<VerificationError> for AppError {
+    fn from(e: VerificationError) -> Self {
+        match e {
+            VerificationError::Database(e) => AppError::internal("生成验证码失败", e),
+            VerificationError::TooFrequent(secs) => AppError::too_many_requests(e.to_string(), Some(secs)),
+            e => AppError::too_many_requests(e.to_string(), None),
+        }
+    }
+}
+
+pub async fn jwks(keys: web::Data<JwtKeys>) -> impl Responder {
+    HttpResponse::Ok()
+        .insert_header((header::CACHE_CONTROL, "public, max-age=300"))
+        .json(keys.jwks())
 }
@@ -0,0 +1,26 @@
+use actix_web::{web, HttpResponse};
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::middleware::auth::AuthUser;
+use crate::middleware::rbac::{authorize_resource, OwnedResource};
+use crate::models::billing::{Invoice, INVOICE_COLUMNS};
+use crate::utils::error::AppError;
+
+pub async fn get_invoice(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+    invoice_id: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let invoice_id = invoice_id.into_inner();
+    authorize_resource(&pool, &user, OwnedResource::Invoice, invoice_id).await?;
+
+    let invoice = sqlx::query_as::<_, Invoice>(&format!("SELECT {} FROM invoices WHERE id = $1", INVOICE_COLUMNS))
+        .bind(invoice_id)
+        .fetch_one(&**pool)
+        .await
+        .map_err(|e| AppError::internal("查询账单失败", e))?;
+
+    Ok(HttpResponse::Ok().json(json!({"invoice": invoice})))
+}
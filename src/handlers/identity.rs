@@ -0,0 +1,144 @@
+use actix_web::{web, HttpResponse};
+use chrono::{DateTime, Utc};
+use log::{error, info};
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::middleware::auth::AuthUser;
+use crate::models::identity::{IdentityVerification, SubmitIdentityRequest, IDENTITY_COLUMNS};
+use crate::services::identity_service::{self, IdentityCheck, IdentityProvider};
+use crate::utils::crypto::FieldCipher;
+use crate::utils::error::AppError;
+use crate::utils::i18n;
+use crate::utils::locale::Locale;
+use crate::utils::validation::ValidatedJson;
+
+/// 实名认证状态和最近一次申请
+pub async fn status(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> Result<HttpResponse, AppError> {
+    let required = identity_service::verification_required(&pool)
+        .await
+        .map_err(|e| AppError::internal("查询实名配置失败", e))?;
+
+    let verified_at = sqlx::query_scalar::<_, Option<DateTime<Utc>>>("SELECT id_verified_at FROM users WHERE id = $1")
+        .bind(user.id)
+        .fetch_one(&**pool)
+        .await
+        .map_err(|e| AppError::internal("查询用户失败", e))?;
+
+    let latest = sqlx::query_as::<_, IdentityVerification>(&format!(
+        "SELECT {} FROM identity_verifications WHERE user_id = $1 ORDER BY created_at DESC LIMIT 1",
+        IDENTITY_COLUMNS
+    ))
+    .bind(user.id)
+    .fetch_optional(&**pool)
+    .await
+    .map_err(|e| AppError::internal("查询实名认证申请失败", e))?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "required": required,
+        "verified": verified_at.is_some(),
+        "verified_at": verified_at,
+        "verification": latest
+    })))
+}
+
+/// 提交姓名和身份证号。服务商核验一致时直接通过，无法判断或服务商不可用时进入人工审核
+pub async fn submit(
+    pool: web::Data<PgPool>,
+    provider: web::Data<dyn IdentityProvider>,
+    cipher: Option<web::Data<FieldCipher>>,
+    user: AuthUser,
+    locale: Locale,
+    body: ValidatedJson<SubmitIdentityRequest>,
+) -> Result<HttpResponse, AppError> {
+    let cipher = cipher.ok_or_else(|| AppError::unavailable("实名认证服务未配置"))?;
+    let real_name = body.real_name.trim();
+    let id_card = identity_service::normalize_id_card(&body.id_card)
+        .ok_or_else(|| AppError::bad_request("身份证号格式不正确"))?;
+
+    if identity_service::is_verified(&pool, user.id)
+        .await
+        .map_err(|e| AppError::internal("查询实名认证状态失败", e))?
+    {
+        return Err(AppError::conflict("已完成实名认证"));
+    }
+    if identity_service::has_pending(&pool, user.id)
+        .await
+        .map_err(|e| AppError::internal("查询实名认证申请失败", e))?
+    {
+        return Err(AppError::conflict("实名认证正在审核中"));
+    }
+    let id_card_hash = cipher.fingerprint(&id_card);
+    if identity_service::id_card_taken(&pool, &id_card_hash, user.id)
+        .await
+        .map_err(|e| AppError::internal("查询实名认证申请失败", e))?
+    {
+        return Err(AppError::conflict("该证件已被其他账号认证"));
+    }
+
+    let check = match provider.verify(real_name, &id_card).await {
+        Ok(check) => check,
+        Err(e) => {
+            error!("实名核验失败，转人工审核: {}", e);
+            IdentityCheck::ManualReview
+        }
+    };
+    let (status, provider_message) = match check {
+        IdentityCheck::Matched => (identity_service::STATUS_APPROVED, None),
+        IdentityCheck::Mismatched => (identity_service::STATUS_REJECTED, Some("姓名与身份证号不一致")),
+        IdentityCheck::ManualReview => (identity_service::STATUS_PENDING, None),
+    };
+    let id_card_encrypted = cipher.encrypt(&id_card);
+
+    let mut tx = pool.begin().await.map_err(|e| AppError::internal("开启事务失败", e))?;
+
+    let verification = sqlx::query_as::<_, IdentityVerification>(&format!(
+        r#"
+        INSERT INTO identity_verifications
+            (user_id, real_name, id_card_encrypted, id_card_hash, id_card_masked, status, provider, provider_message, reviewed_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, CASE WHEN $6 = 'pending' THEN NULL ELSE NOW() END)
+        RETURNING {}
+        "#,
+        IDENTITY_COLUMNS
+    ))
+    .bind(user.id)
+    .bind(real_name)
+    .bind(&id_card_encrypted)
+    .bind(&id_card_hash)
+    .bind(identity_service::mask_id_card(&id_card))
+    .bind(status)
+    .bind(provider.name())
+    .bind(provider_message)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| match e.as_database_error().and_then(|db| db.constraint()) {
+        // 并发提交时由唯一索引兜底
+        Some("idx_identity_verifications_pending") => AppError::conflict("实名认证正在审核中"),
+        Some("idx_identity_verifications_approved") => AppError::conflict("该证件已被其他账号认证"),
+        _ => AppError::internal("保存实名认证申请失败", e),
+    })?;
+
+    if status == identity_service::STATUS_APPROVED {
+        identity_service::mark_verified(&mut *tx, user.id, real_name, &id_card_encrypted)
+            .await
+            .map_err(|e| AppError::internal("更新实名信息失败", e))?;
+    }
+
+    tx.commit().await.map_err(|e| AppError::internal("提交事务失败", e))?;
+
+    let message = match status {
+        identity_service::STATUS_APPROVED => {
+            info!("用户 {} 通过实名认证（{}）", user.username, provider.name());
+            "实名认证已通过"
+        }
+        identity_service::STATUS_PENDING => "已提交实名认证，请等待审核",
+        _ => return Err(AppError::bad_request("姓名与身份证号不一致")),
+    };
+    Ok(HttpResponse::Ok().json(json!({
+        "message": i18n::translate(locale, message),
+        "verification": verification
+    })))
+}
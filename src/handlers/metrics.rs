@@ -0,0 +1,102 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use log::error;
+use sqlx::PgPool;
+
+use crate::utils::crypto::secrets_equal;
+use crate::utils::error::AppError;
+use crate::utils::metrics::{
+    self, ACTIVE_USERS, DB_POOL_CONNECTIONS, DB_POOL_MAX_CONNECTIONS, NODE_CAPACITY_UTILIZATION,
+    VM_INSTANCES,
+};
+
+#[derive(sqlx::FromRow)]
+struct NodeCapacity {
+    name: String,
+    max_cpu: i32,
+    max_memory_gb: i32,
+    max_storage_gb: i32,
+    used_cpu: Option<i32>,
+    used_memory_gb: Option<i32>,
+    used_storage_gb: Option<i32>,
+}
+
+fn ratio(used: Option<i32>, max: i32) -> f64 {
+    if max <= 0 {
+        return 0.0;
+    }
+    used.unwrap_or(0) as f64 / max as f64
+}
+
+/// 抓取时刷新连接池和业务类指标
+async fn refresh_gauges(pool: &PgPool) -> Result<(), sqlx::Error> {
+    let size = pool.size() as i64;
+    let idle = pool.num_idle() as i64;
+    DB_POOL_CONNECTIONS.with_label_values(&["idle"]).set(idle);
+    DB_POOL_CONNECTIONS.with_label_values(&["active"]).set(size - idle);
+    DB_POOL_MAX_CONNECTIONS.set(pool.options().get_max_connections() as i64);
+
+    let active_users = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM users WHERE status = 'active'")
+        .fetch_one(pool)
+        .await?;
+    ACTIVE_USERS.set(active_users);
+
+    let instances = sqlx::query_as::<_, (Option<String>, i64)>(
+        "SELECT status, COUNT(*) FROM vm_instances GROUP BY status"
+    )
+    .fetch_all(pool)
+    .await?;
+    VM_INSTANCES.reset();
+    for (status, count) in instances {
+        let status = status.unwrap_or_else(|| "unknown".to_string());
+        VM_INSTANCES.with_label_values(&[&status]).set(count);
+    }
+
+    let nodes = sqlx::query_as::<_, NodeCapacity>(
+        r#"
+        SELECT name, max_cpu, max_memory_gb, max_storage_gb, used_cpu, used_memory_gb, used_storage_gb
+        FROM pve_nodes WHERE status <> 'disabled'
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+    NODE_CAPACITY_UTILIZATION.reset();
+    for node in nodes {
+        NODE_CAPACITY_UTILIZATION
+            .with_label_values(&[&node.name, "cpu"])
+            .set(ratio(node.used_cpu, node.max_cpu));
+        NODE_CAPACITY_UTILIZATION
+            .with_label_values(&[&node.name, "memory"])
+            .set(ratio(node.used_memory_gb, node.max_memory_gb));
+        NODE_CAPACITY_UTILIZATION
+            .with_label_values(&[&node.name, "storage"])
+            .set(ratio(node.used_storage_gb, node.max_storage_gb));
+    }
+
+    Ok(())
+}
+
+pub async fn export(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    // 配置了 METRICS_TOKEN 时要求抓取端携带 Bearer 令牌
+    if let Some(expected) = std::env::var("METRICS_TOKEN").ok().filter(|t| !t.is_empty()) {
+        let provided = req
+            .headers()
+            .get("Authorization")
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.strip_prefix("Bearer "));
+        if !provided.is_some_and(|provided| secrets_equal(provided, &expected)) {
+            return Err(AppError::unauthorized("无效的抓取令牌"));
+        }
+    }
+
+    if let Err(e) = refresh_gauges(&pool).await {
+        error!("刷新业务指标失败: {}", e);
+    }
+
+    let body = metrics::render().map_err(|e| AppError::internal("导出监控指标失败", e))?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4; charset=utf-8")
+        .body(body))
+}
@@ -1,109 +1,50 @@
-use actix_web::{HttpResponse, Responder, web};
-use bcrypt::verify;
-use chrono::{Duration, Utc};
-use serde_json::{Value, json};
-use reqwest::Client as ReqwestClient;
-use reqwest::header::AUTHORIZATION;
-use crate::models::LoginRequest;
-use crate::utils::create_jwt;
+use actix_web::{HttpResponse, Responder};
+use crate::services::pve_service::ProxmoxClient;
+use crate::utils::error::AppError;
 
+pub mod admin;
+pub mod alert;
+pub mod api_key;
 pub mod auth;
-
-struct ProxmoxClient {
-    client: ReqwestClient,
-    base_url: String,
-    auth_header: String,
-}
-
-impl ProxmoxClient {
-    fn new() -> Self {
-        dotenv::dotenv().ok();
-        let url = std::env::var("PROXMOX_URL").unwrap();
-        let realm = std::env::var("PROXMOX_REALM").unwrap();
-        let username = std::env::var("PROXMOX_USERNAME").unwrap();
-        let token_name = std::env::var("PROXMOX_TOKEN_NAME").unwrap();
-        let token_secret = std::env::var("PROXMOX_TOKEN_SECRET").unwrap();
-
-        let base_url = url.trim_end_matches('/').trim_end_matches("/api2/json").to_string();
-        let auth_header = format!(
-            "PVEAPIToken={}!{}={}",
-            format!("{username}@{realm}"), token_name, token_secret
-        );
-
-        let client = ReqwestClient::builder()
-            .danger_accept_invalid_certs(true)
-            .build()
-            .unwrap();
-
-        ProxmoxClient {
-            client,
-            base_url,
-            auth_header,
-        }
-    }
-
-    async fn make_request(&self, endpoint: &str) -> Result<Value, String> {
-        let api_url = format!("{}/api2/json/{}", self.base_url, endpoint.trim_start_matches('/'));
-
-        match self.client.post(&api_url)
-            .header(AUTHORIZATION, &self.auth_header)
-            .send()
-            .await
-        {
-            Ok(resp) => {
-                if resp.status().is_success() {
-                    resp.json::<Value>().await.map_err(|e| e.to_string())
-                } else {
-                    Err(resp.status().to_string())
-                }
-            },
-            Err(e) => Err(e.to_string())
-        }
-    }
-}
+pub mod billing;
+pub mod identity;
+pub mod metrics;
+pub mod organization;
+pub mod passkey;
+pub mod staff;
+pub mod ticket;
+pub mod two_factor;
+pub mod user;
+pub mod vm;
 
 pub async fn health_check() -> impl Responder {
     HttpResponse::Ok().json("OK")
 }
 
-pub async fn login(login_data: web::Json<LoginRequest>) -> impl Responder {
-    // TODO: 实际验证用户逻辑
-    let is_valid = verify(&login_data.password, "hashed_password_from_db").unwrap_or(false);
-    
-    if !is_valid {
-        return HttpResponse::Unauthorized().json("Invalid credentials");
-    }
-
-    let secret = std::env::var("JWT_SECRET").expect("JWT_SECRET must be set");
-    let expiry = Utc::now() + Duration::hours(24);
-    let token = create_jwt(&login_data.username, &secret, expiry).unwrap();
-
-    HttpResponse::Ok().json(token)
-}
-
-pub async fn get_version() -> impl Responder {
+pub async fn get_version() -> Result<HttpResponse, AppError> {
     let client = ProxmoxClient::new();
-    match client.make_request("version").await {
-        Ok(version) => HttpResponse::Ok().json(version),
-        Err(e) => HttpResponse::InternalServerError().json(json!({"error": e}))
-    }
+    let version = client
+        .make_request("version")
+        .await
+        .map_err(|e| AppError::internal("查询 PVE 版本失败", anyhow::anyhow!(e)))?;
+    Ok(HttpResponse::Ok().json(version))
 }
 
-pub async fn get_nodes() -> impl Responder {
+pub async fn get_nodes() -> Result<HttpResponse, AppError> {
     let client = ProxmoxClient::new();
-    match client.make_request("nodes").await {
-        Ok(nodes) => {
-            println!("PVE nodes info: {:#?}", nodes);
-            HttpResponse::Ok().json(nodes)
-        },
-        Err(e) => HttpResponse::InternalServerError().json(json!({"error": e}))
-    }
+    let nodes = client
+        .make_request("nodes")
+        .await
+        .map_err(|e| AppError::internal("查询 PVE 节点失败", anyhow::anyhow!(e)))?;
+    println!("PVE nodes info: {:#?}", nodes);
+    Ok(HttpResponse::Ok().json(nodes))
 }
 
-pub async fn pve_version() -> impl Responder {
+pub async fn pve_version() -> Result<HttpResponse, AppError> {
     let client = ProxmoxClient::new();
-    match client.make_request("version").await {
-        Ok(version) => HttpResponse::Ok().json(version),
-        Err(e) => HttpResponse::InternalServerError().json(json!({"error": e}))
-    }
+    let version = client
+        .make_request("version")
+        .await
+        .map_err(|e| AppError::internal("查询 PVE 版本失败", anyhow::anyhow!(e)))?;
+    Ok(HttpResponse::Ok().json(version))
 }
@@ -0,0 +1,448 @@
+use actix_web::{web, HttpResponse};
+use log::{error, info};
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::middleware::auth::AuthUser;
+use crate::middleware::rbac::authorize_organization;
+use crate::models::billing::{Invoice, INVOICE_COLUMNS};
+use crate::models::organization::{
+    AcceptInvitationRequest, CreateOrganizationRequest, InviteMemberRequest, Membership, OrgPermission, OrgRole,
+    Organization, OrganizationInvitation, OrganizationMember, UpdateMemberRequest, INVITATION_COLUMNS,
+    ORGANIZATION_COLUMNS,
+};
+use crate::models::vm_instance::{VmInstance, VM_INSTANCE_COLUMNS};
+use crate::services::email_service::{self, EmailService};
+use crate::services::email_template::EmailTemplate;
+use crate::services::organization_service::{self, AcceptOutcome};
+use crate::utils::error::AppError;
+use crate::utils::i18n;
+use crate::utils::locale::Locale;
+use crate::utils::validation::ValidatedJson;
+
+/// 每个用户最多创建的组织数
+const MAX_ORGANIZATIONS_PER_USER: i64 = 10;
+
+/// 当前用户加入的全部组织
+pub async fn list(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> Result<HttpResponse, AppError> {
+    let organizations = sqlx::query_as::<_, Membership>(
+        r#"
+        SELECT o.id, o.name, m.role, m.joined_at
+        FROM organization_members m
+        JOIN organizations o ON o.id = m.organization_id
+        WHERE m.user_id = $1
+        ORDER BY m.joined_at
+        "#
+    )
+    .bind(user.id)
+    .fetch_all(&**pool)
+    .await
+    .map_err(|e| AppError::internal("查询组织失败", e))?;
+
+    Ok(HttpResponse::Ok().json(json!({"organizations": organizations})))
+}
+
+/// 创建组织，创建人成为所有者
+pub async fn create(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+    locale: Locale,
+    body: ValidatedJson<CreateOrganizationRequest>,
+) -> Result<HttpResponse, AppError> {
+    let created = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM organizations WHERE created_by = $1")
+        .bind(user.id)
+        .fetch_one(&**pool)
+        .await
+        .map_err(|e| AppError::internal("查询组织失败", e))?;
+    if created >= MAX_ORGANIZATIONS_PER_USER {
+        return Err(AppError::bad_request("创建的组织数量已达上限"));
+    }
+
+    let mut tx = pool.begin().await.map_err(|e| AppError::internal("开启事务失败", e))?;
+
+    let organization = sqlx::query_as::<_, Organization>(&format!(
+        "INSERT INTO organizations (name, created_by) VALUES ($1, $2) RETURNING {}",
+        ORGANIZATION_COLUMNS
+    ))
+    .bind(body.name.trim())
+    .bind(user.id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| AppError::internal("创建组织失败", e))?;
+
+    sqlx::query("INSERT INTO organization_members (organization_id, user_id, role) VALUES ($1, $2, $3)")
+        .bind(organization.id)
+        .bind(user.id)
+        .bind(OrgRole::Owner.as_str())
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| AppError::internal("创建组织失败", e))?;
+
+    tx.commit().await.map_err(|e| AppError::internal("提交事务失败", e))?;
+
+    info!("用户 {} 创建了组织 {}", user.username, organization.id);
+    Ok(HttpResponse::Created().json(json!({
+        "message": i18n::translate(locale, "组织已创建"),
+        "organization": organization
+    })))
+}
+
+/// 组织详情。余额只对有账务权限的成员返回
+pub async fn get(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+    organization_id: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let organization_id = organization_id.into_inner();
+    let role = authorize_organization(&pool, &user, organization_id, None).await?;
+
+    let organization = sqlx::query_as::<_, Organization>(&format!(
+        "SELECT {} FROM organizations WHERE id = $1",
+        ORGANIZATION_COLUMNS
+    ))
+    .bind(organization_id)
+    .fetch_one(&**pool)
+    .await
+    .map_err(|e| AppError::internal("查询组织失败", e))?;
+
+    let balance = if role.can(OrgPermission::ViewBilling) {
+        let balance = sqlx::query_scalar::<_, f64>("SELECT balance::FLOAT8 FROM organizations WHERE id = $1")
+            .bind(organization_id)
+            .fetch_one(&**pool)
+            .await
+            .map_err(|e| AppError::internal("查询组织余额失败", e))?;
+        Some(balance)
+    } else {
+        None
+    };
+
+    Ok(HttpResponse::Ok().json(json!({
+        "organization": organization,
+        "role": role,
+        "balance": balance
+    })))
+}
+
+pub async fn list_members(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+    organization_id: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let organization_id = organization_id.into_inner();
+    authorize_organization(&pool, &user, organization_id, None).await?;
+
+    let members = sqlx::query_as::<_, OrganizationMember>(
+        r#"
+        SELECT m.user_id, u.username, u.email, m.role, m.joined_at
+        FROM organization_members m
+        JOIN users u ON u.id = m.user_id
+        WHERE m.organization_id = $1
+        ORDER BY m.joined_at
+        "#
+    )
+    .bind(organization_id)
+    .fetch_all(&**pool)
+    .await
+    .map_err(|e| AppError::internal("查询组织成员失败", e))?;
+
+    Ok(HttpResponse::Ok().json(json!({"members": members})))
+}
+
+/// 调整成员角色。管理员只能在运维、只读成员和财务之间调整，组织至少保留一名所有者
+pub async fn update_member(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+    locale: Locale,
+    path: web::Path<(Uuid, Uuid)>,
+    body: web::Json<UpdateMemberRequest>,
+) -> Result<HttpResponse, AppError> {
+    let (organization_id, member_id) = path.into_inner();
+    let actor = authorize_organization(&pool, &user, organization_id, Some(OrgPermission::ManageMembers)).await?;
+
+    let mut tx = pool.begin().await.map_err(|e| AppError::internal("开启事务失败", e))?;
+    let owners = organization_service::lock_owner_count(&mut tx, organization_id)
+        .await
+        .map_err(|e| AppError::internal("查询组织成员失败", e))?;
+    let current = organization_service::member_role(&mut *tx, organization_id, member_id)
+        .await
+        .map_err(|e| AppError::internal("查询组织成员失败", e))?
+        .ok_or_else(|| AppError::not_found("成员不存在"))?;
+
+    if !actor.can_manage(current) || !actor.can_manage(body.role) {
+        return Err(AppError::forbidden("权限不足"));
+    }
+    if current == body.role {
+        return Err(AppError::bad_request("角色未变化"));
+    }
+    if current == OrgRole::Owner && owners <= 1 {
+        return Err(AppError::conflict("组织至少需要保留一名所有者"));
+    }
+
+    sqlx::query("UPDATE organization_members SET role = $3 WHERE organization_id = $1 AND user_id = $2")
+        .bind(organization_id)
+        .bind(member_id)
+        .bind(body.role.as_str())
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| AppError::internal("更新成员角色失败", e))?;
+    tx.commit().await.map_err(|e| AppError::internal("提交事务失败", e))?;
+
+    info!(
+        "用户 {} 把组织 {} 成员 {} 的角色由 {} 调整为 {}",
+        user.username, organization_id, member_id, current.as_str(), body.role.as_str()
+    );
+    Ok(HttpResponse::Ok().json(json!({
+        "message": i18n::translate(locale, "成员角色已更新"),
+        "user_id": member_id,
+        "role": body.role
+    })))
+}
+
+/// 移除成员，成员也可以移除自己以退出组织。被移除成员在组织实例上的告警规则一并删除
+pub async fn remove_member(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+    locale: Locale,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, AppError> {
+    let (organization_id, member_id) = path.into_inner();
+    let leaving = member_id == user.id;
+    let permission = if leaving { None } else { Some(OrgPermission::ManageMembers) };
+    let actor = authorize_organization(&pool, &user, organization_id, permission).await?;
+
+    let mut tx = pool.begin().await.map_err(|e| AppError::internal("开启事务失败", e))?;
+    let owners = organization_service::lock_owner_count(&mut tx, organization_id)
+        .await
+        .map_err(|e| AppError::internal("查询组织成员失败", e))?;
+    let current = organization_service::member_role(&mut *tx, organization_id, member_id)
+        .await
+        .map_err(|e| AppError::internal("查询组织成员失败", e))?
+        .ok_or_else(|| AppError::not_found("成员不存在"))?;
+
+    if !leaving && !actor.can_manage(current) {
+        return Err(AppError::forbidden("权限不足"));
+    }
+    if current == OrgRole::Owner && owners <= 1 {
+        return Err(AppError::conflict("组织至少需要保留一名所有者"));
+    }
+
+    sqlx::query("DELETE FROM organization_members WHERE organization_id = $1 AND user_id = $2")
+        .bind(organization_id)
+        .bind(member_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| AppError::internal("移除成员失败", e))?;
+    sqlx::query(
+        r#"
+        DELETE FROM alert_rules
+        WHERE user_id = $2 AND vm_instance_id IN (SELECT id FROM vm_instances WHERE organization_id = $1)
+        "#
+    )
+    .bind(organization_id)
+    .bind(member_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| AppError::internal("移除成员失败", e))?;
+    tx.commit().await.map_err(|e| AppError::internal("提交事务失败", e))?;
+
+    info!("用户 {} 从组织 {} 移除了成员 {}", user.username, organization_id, member_id);
+    let message = if leaving { "已退出组织" } else { "成员已移除" };
+    Ok(HttpResponse::Ok().json(json!({"message": i18n::translate(locale, message)})))
+}
+
+/// 尚未接受且未过期的邀请
+pub async fn list_invitations(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+    organization_id: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let organization_id = organization_id.into_inner();
+    authorize_organization(&pool, &user, organization_id, Some(OrgPermission::ManageMembers)).await?;
+
+    let invitations = sqlx::query_as::<_, OrganizationInvitation>(&format!(
+        r#"
+        SELECT {} FROM organization_invitations
+        WHERE organization_id = $1 AND accepted_at IS NULL AND expires_at > NOW()
+        ORDER BY created_at DESC
+        "#,
+        INVITATION_COLUMNS
+    ))
+    .bind(organization_id)
+    .fetch_all(&**pool)
+    .await
+    .map_err(|e| AppError::internal("查询邀请失败", e))?;
+
+    Ok(HttpResponse::Ok().json(json!({"invitations": invitations})))
+}
+
+/// 通过邮件邀请成员，重复邀请同一邮箱会替换之前的邀请
+pub async fn invite(
+    pool: web::Data<PgPool>,
+    email_service: Option<web::Data<EmailService>>,
+    user: AuthUser,
+    locale: Locale,
+    organization_id: web::Path<Uuid>,
+    body: ValidatedJson<InviteMemberRequest>,
+) -> Result<HttpResponse, AppError> {
+    let organization_id = organization_id.into_inner();
+    let actor = authorize_organization(&pool, &user, organization_id, Some(OrgPermission::ManageMembers)).await?;
+    if !actor.can_manage(body.role) {
+        return Err(AppError::forbidden("权限不足"));
+    }
+    let email_service = email_service.ok_or_else(|| AppError::unavailable("邮件服务未配置"))?;
+    let email = body.email.trim();
+    if !email_service::is_valid_address(email) {
+        return Err(AppError::bad_request("邮箱格式不正确"));
+    }
+
+    let already_member = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM organization_members m JOIN users u ON u.id = m.user_id
+            WHERE m.organization_id = $1 AND LOWER(u.email) = LOWER($2)
+        )
+        "#
+    )
+    .bind(organization_id)
+    .bind(email)
+    .fetch_one(&**pool)
+    .await
+    .map_err(|e| AppError::internal("查询组织成员失败", e))?;
+    if already_member {
+        return Err(AppError::conflict("该用户已是组织成员"));
+    }
+
+    // 邮件使用受邀人已设置的语言，没有账户时与邀请人一致
+    let (organization, recipient_language) = sqlx::query_as::<_, (String, Option<String>)>(
+        r#"
+        SELECT o.name, (SELECT preferred_language FROM users WHERE LOWER(email) = LOWER($2) AND status = 'active')
+        FROM organizations o WHERE o.id = $1
+        "#
+    )
+    .bind(organization_id)
+    .bind(email)
+    .fetch_one(&**pool)
+    .await
+    .map_err(|e| AppError::internal("查询组织失败", e))?;
+
+    let (token, invitation) = organization_service::create_invitation(&pool, organization_id, email, body.role, user.id)
+        .await
+        .map_err(|e| AppError::internal("创建邀请失败", e))?;
+
+    let url = organization_service::invitation_url(&token);
+    let template = EmailTemplate::OrganizationInvitation {
+        organization: &organization,
+        inviter: &user.username,
+        role: body.role,
+        url: &url,
+        ttl_days: organization_service::INVITATION_TTL_DAYS,
+    };
+    let recipient_locale = recipient_language.as_deref().and_then(Locale::parse).unwrap_or(locale);
+    if let Err(e) = email_service.send_template(email, &template, recipient_locale).await {
+        error!("发送组织邀请邮件失败: {}", e);
+        if let Err(e) = organization_service::discard_invitation(&pool, invitation.id).await {
+            error!("删除邀请失败: {}", e);
+        }
+        return Err(AppError::unavailable("邮件发送失败，请稍后重试"));
+    }
+
+    info!("用户 {} 邀请 {} 加入组织 {}", user.username, email, organization_id);
+    Ok(HttpResponse::Created().json(json!({
+        "message": i18n::translate(locale, "邀请已发送"),
+        "invitation": invitation
+    })))
+}
+
+pub async fn revoke_invitation(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+    locale: Locale,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, AppError> {
+    let (organization_id, invitation_id) = path.into_inner();
+    authorize_organization(&pool, &user, organization_id, Some(OrgPermission::ManageMembers)).await?;
+
+    let result = sqlx::query(
+        "DELETE FROM organization_invitations WHERE id = $1 AND organization_id = $2 AND accepted_at IS NULL"
+    )
+    .bind(invitation_id)
+    .bind(organization_id)
+    .execute(&**pool)
+    .await
+    .map_err(|e| AppError::internal("撤销邀请失败", e))?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::not_found("邀请不存在"));
+    }
+
+    Ok(HttpResponse::Ok().json(json!({"message": i18n::translate(locale, "邀请已撤销")})))
+}
+
+/// 登录后用邮件中的邀请令牌加入组织
+pub async fn accept_invitation(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+    locale: Locale,
+    body: ValidatedJson<AcceptInvitationRequest>,
+) -> Result<HttpResponse, AppError> {
+    let outcome = organization_service::accept_invitation(&pool, body.token.trim(), user.id)
+        .await
+        .map_err(|e| AppError::internal("接受邀请失败", e))?;
+
+    match outcome {
+        AcceptOutcome::Joined { organization_id, role } => {
+            info!("用户 {} 以 {} 身份加入组织 {}", user.username, role.as_str(), organization_id);
+            Ok(HttpResponse::Ok().json(json!({
+                "message": i18n::translate(locale, "已加入组织"),
+                "organization_id": organization_id,
+                "role": role
+            })))
+        }
+        AcceptOutcome::AlreadyMember => Err(AppError::conflict("您已是该组织成员")),
+        AcceptOutcome::Invalid => Err(AppError::bad_request("邀请链接无效或已过期")),
+    }
+}
+
+/// 组织名下未删除的实例
+pub async fn list_instances(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+    organization_id: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let organization_id = organization_id.into_inner();
+    authorize_organization(&pool, &user, organization_id, Some(OrgPermission::ViewInstances)).await?;
+
+    let instances = sqlx::query_as::<_, VmInstance>(&format!(
+        "SELECT {} FROM vm_instances WHERE organization_id = $1 AND status <> 'deleted' ORDER BY created_at",
+        VM_INSTANCE_COLUMNS
+    ))
+    .bind(organization_id)
+    .fetch_all(&**pool)
+    .await
+    .map_err(|e| AppError::internal("查询实例失败", e))?;
+
+    Ok(HttpResponse::Ok().json(json!({"instances": instances})))
+}
+
+pub async fn list_invoices(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+    organization_id: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let organization_id = organization_id.into_inner();
+    authorize_organization(&pool, &user, organization_id, Some(OrgPermission::ViewBilling)).await?;
+
+    let invoices = sqlx::query_as::<_, Invoice>(&format!(
+        "SELECT {} FROM invoices WHERE organization_id = $1 ORDER BY created_at DESC",
+        INVOICE_COLUMNS
+    ))
+    .bind(organization_id)
+    .fetch_all(&**pool)
+    .await
+    .map_err(|e| AppError::internal("查询账单失败", e))?;
+
+    Ok(HttpResponse::Ok().json(json!({"invoices": invoices})))
+}
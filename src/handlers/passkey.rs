@@ -0,0 +1,98 @@
+use actix_web::{web, HttpResponse};
+use log::{info, warn};
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::middleware::auth::AuthUser;
+use crate::models::webauthn::{FinishPasskeyRegistrationRequest, RenamePasskeyRequest};
+use crate::services::webauthn_service::{self, PasskeyError, PasskeyService};
+use crate::utils::error::AppError;
+use crate::utils::i18n;
+use crate::utils::locale::Locale;
+use crate::utils::validation::ValidatedJson;
+
+/// 校验失败不返回具体原因
+impl From<PasskeyError> for AppError {
+    fn from(e: PasskeyError) -> Self {
+        match e {
+            PasskeyError::CeremonyExpired => AppError::bad_request("验证已过期，请重试"),
+            PasskeyError::AlreadyRegistered => AppError::conflict("该验证器已注册"),
+            PasskeyError::Webauthn(e) => {
+                warn!("WebAuthn 校验失败: {}", e);
+                AppError::unauthorized("验证器校验失败")
+            }
+            PasskeyError::UnknownCredential => AppError::unauthorized("验证器校验失败"),
+            e => AppError::internal("通行密钥操作失败", e),
+        }
+    }
+}
+
+pub async fn list(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> Result<HttpResponse, AppError> {
+    let passkeys = webauthn_service::list_passkeys(&pool, user.id)
+        .await
+        .map_err(|e| AppError::internal("查询通行密钥失败", e))?;
+    Ok(HttpResponse::Ok().json(json!({"passkeys": passkeys})))
+}
+
+/// 注册第一步：返回浏览器 navigator.credentials.create 所需的参数
+pub async fn start_registration(
+    pool: web::Data<PgPool>,
+    passkeys: web::Data<PasskeyService>,
+    user: AuthUser,
+) -> Result<HttpResponse, AppError> {
+    let (ceremony_id, options) = passkeys.start_registration(&pool, user.id, &user.username).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "ceremony_id": ceremony_id,
+        "options": options
+    })))
+}
+
+pub async fn finish_registration(
+    pool: web::Data<PgPool>,
+    passkeys: web::Data<PasskeyService>,
+    user: AuthUser,
+    locale: Locale,
+    body: ValidatedJson<FinishPasskeyRegistrationRequest>,
+) -> Result<HttpResponse, AppError> {
+    let passkey = passkeys
+        .finish_registration(&pool, user.id, body.ceremony_id, body.name.trim(), &body.credential)
+        .await?;
+    info!("用户 {} 注册了通行密钥 {}", user.username, passkey.name);
+    Ok(HttpResponse::Ok().json(json!({"message": i18n::translate(locale, "通行密钥已添加"), "passkey": passkey})))
+}
+
+pub async fn rename(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+    locale: Locale,
+    passkey_id: web::Path<Uuid>,
+    body: ValidatedJson<RenamePasskeyRequest>,
+) -> Result<HttpResponse, AppError> {
+    let renamed = webauthn_service::rename_passkey(&pool, user.id, passkey_id.into_inner(), body.name.trim())
+        .await
+        .map_err(|e| AppError::internal("修改通行密钥名称失败", e))?;
+    if !renamed {
+        return Err(AppError::not_found("通行密钥不存在"));
+    }
+    Ok(HttpResponse::Ok().json(json!({"message": i18n::translate(locale, "名称已更新")})))
+}
+
+pub async fn revoke(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+    locale: Locale,
+    passkey_id: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let revoked = webauthn_service::revoke_passkey(&pool, user.id, passkey_id.into_inner())
+        .await
+        .map_err(|e| AppError::internal("移除通行密钥失败", e))?;
+    if !revoked {
+        return Err(AppError::not_found("通行密钥不存在"));
+    }
+    info!("用户 {} 移除了通行密钥", user.username);
+    Ok(HttpResponse::Ok().json(json!({"message": i18n::translate(locale, "通行密钥已移除")})))
+}
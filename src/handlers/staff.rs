@@ -0,0 +1,28 @@
+use actix_web::{web, HttpResponse};
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::user::UserSummary;
+use crate::utils::error::AppError;
+
+/// 客服处理工单时查看用户基本资料
+pub async fn get_user(
+    pool: web::Data<PgPool>,
+    user_id: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let user = sqlx::query_as::<_, UserSummary>(
+        r#"
+        SELECT id, username, email, phone, real_name, status, role,
+               balance::FLOAT8 AS balance, created_at, last_login
+        FROM users WHERE id = $1
+        "#
+    )
+    .bind(user_id.into_inner())
+    .fetch_optional(&**pool)
+    .await
+    .map_err(|e| AppError::internal("查询用户失败", e))?
+    .ok_or_else(|| AppError::not_found("用户不存在"))?;
+
+    Ok(HttpResponse::Ok().json(json!({"user": user})))
+}
@@ -0,0 +1,35 @@
+use actix_web::{web, HttpResponse};
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::middleware::auth::AuthUser;
+use crate::middleware::rbac::{authorize_resource, OwnedResource};
+use crate::models::ticket::{Ticket, TicketMessage};
+use crate::utils::error::AppError;
+
+/// 工单详情及全部回复，提交人和客服可见
+pub async fn get_ticket(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+    ticket_id: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let ticket_id = ticket_id.into_inner();
+    authorize_resource(&pool, &user, OwnedResource::Ticket, ticket_id).await?;
+
+    let ticket = sqlx::query_as::<_, Ticket>("SELECT * FROM tickets WHERE id = $1")
+        .bind(ticket_id)
+        .fetch_one(&**pool)
+        .await
+        .map_err(|e| AppError::internal("查询工单失败", e))?;
+
+    let messages = sqlx::query_as::<_, TicketMessage>(
+        "SELECT * FROM ticket_messages WHERE ticket_id = $1 ORDER BY created_at"
+    )
+    .bind(ticket_id)
+    .fetch_all(&**pool)
+    .await
+    .map_err(|e| AppError::internal("查询工单回复失败", e))?;
+
+    Ok(HttpResponse::Ok().json(json!({"ticket": ticket, "messages": messages})))
+}
@@ -0,0 +1,74 @@
+use actix_web::{web, HttpResponse};
+use log::info;
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::middleware::auth::AuthUser;
+use crate::models::user::TwoFactorCodeRequest;
+use crate::utils::crypto::FieldCipher;
+use crate::utils::error::AppError;
+use crate::utils::i18n;
+use crate::utils::locale::Locale;
+use crate::utils::validation::ValidatedJson;
+use crate::services::two_factor_service;
+
+pub async fn status(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> Result<HttpResponse, AppError> {
+    let enabled = sqlx::query_scalar::<_, bool>("SELECT two_factor_enabled FROM users WHERE id = $1")
+        .bind(user.id)
+        .fetch_one(&**pool)
+        .await
+        .map_err(|e| AppError::internal("查询两步验证状态失败", e))?;
+
+    let remaining = two_factor_service::remaining_recovery_codes(&pool, user.id)
+        .await
+        .map_err(|e| AppError::internal("查询恢复码失败", e))?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "enabled": enabled,
+        "recovery_codes_remaining": remaining
+    })))
+}
+
+/// 生成 TOTP 密钥，前端用 otpauth_uri 展示二维码
+pub async fn enroll(
+    pool: web::Data<PgPool>,
+    cipher: Option<web::Data<FieldCipher>>,
+    user: AuthUser,
+    locale: Locale,
+) -> Result<HttpResponse, AppError> {
+    let cipher = cipher.ok_or_else(|| AppError::unavailable("两步验证服务未配置"))?;
+    let enrollment = two_factor_service::start_enrollment(&pool, &cipher, user.id, &user.username)
+        .await
+        .map_err(|e| AppError::internal("生成两步验证密钥失败", e))?
+        .ok_or_else(|| AppError::conflict("已启用两步验证"))?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "message": i18n::translate(locale, "请使用验证器扫描二维码并输入验证码完成绑定"),
+        "secret": enrollment.secret,
+        "otpauth_uri": enrollment.otpauth_uri
+    })))
+}
+
+/// 校验第一个验证码，成功后启用并返回恢复码（仅展示一次）
+pub async fn confirm(
+    pool: web::Data<PgPool>,
+    cipher: Option<web::Data<FieldCipher>>,
+    user: AuthUser,
+    locale: Locale,
+    body: ValidatedJson<TwoFactorCodeRequest>,
+) -> Result<HttpResponse, AppError> {
+    let cipher = cipher.ok_or_else(|| AppError::unavailable("两步验证服务未配置"))?;
+    let recovery_codes = two_factor_service::confirm_enrollment(&pool, &cipher, user.id, &body.code)
+        .await
+        .map_err(|e| AppError::internal("确认两步验证失败", e))?
+        .ok_or_else(|| AppError::bad_request("验证码错误或未开始绑定"))?;
+
+    info!("用户 {} 已启用两步验证", user.username);
+    Ok(HttpResponse::Ok().json(json!({
+        "message": i18n::translate(locale, "两步验证已启用，请妥善保存恢复码"),
+        "recovery_codes": recovery_codes
+    })))
+}
@@ -0,0 +1,551 @@
+use actix_multipart::Multipart;
+use actix_web::http::header;
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::Utc;
+use futures::TryStreamExt;
+use log::{error, info};
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::middleware::auth::AuthUser;
+use crate::models::billing::{Invoice, PaymentRecord, INVOICE_COLUMNS, PAYMENT_COLUMNS};
+use crate::models::organization::Membership;
+use crate::models::ticket::{Ticket, TicketMessage};
+use crate::models::user::{
+    ChangeEmailRequest, ChangePasswordRequest, ChangePhoneRequest, ConfirmEmailChangeRequest, ConfirmPhoneChangeRequest,
+    DeleteAccountRequest, PhoneCodePurpose, UpdateLanguageRequest, UpdateProfileRequest, UserProfile, VerificationPurpose,
+    PROFILE_COLUMNS,
+};
+use crate::models::vm_instance::{VmInstance, VM_INSTANCE_COLUMNS};
+use crate::services::account_service;
+use crate::services::email_service::{self, EmailService};
+use crate::services::email_template::EmailTemplate;
+use crate::services::revocation_service::RevocationStore;
+use crate::services::session_service;
+use crate::services::sms_service::{self, SmsProvider};
+use crate::services::storage_service::ObjectStorage;
+use crate::services::verification_service;
+use crate::utils::error::AppError;
+use crate::utils::i18n;
+use crate::utils::locale::Locale;
+use crate::utils::password;
+use crate::utils::request::client_ip;
+use crate::utils::validation::ValidatedJson;
+
+/// 头像文件大小上限
+const AVATAR_MAX_BYTES: usize = 2 * 1024 * 1024;
+
+/// 设置接口消息和通知邮件使用的语言，传 null 则恢复为跟随 Accept-Language
+pub async fn update_language(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+    body: web::Json<UpdateLanguageRequest>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let language = match body.language.as_deref() {
+        Some(tag) => Some(Locale::parse(tag).ok_or_else(|| AppError::bad_request("不支持的语言"))?),
+        None => None,
+    };
+
+    sqlx::query("UPDATE users SET preferred_language = $1, updated_at = NOW() WHERE id = $2")
+        .bind(language.map(Locale::code))
+        .bind(user.id)
+        .execute(&**pool)
+        .await
+        .map_err(|e| AppError::internal("更新语言偏好失败", e))?;
+
+    // 本次响应即按新设置返回
+    let locale = language.unwrap_or_else(|| Locale::from_request(&req));
+    Ok(HttpResponse::Ok().json(json!({
+        "message": i18n::translate(locale, "语言设置已更新"),
+        "language": language.map(Locale::code)
+    })))
+}
+
+async fn fetch_profile(pool: &PgPool, user_id: Uuid) -> Result<UserProfile, AppError> {
+    sqlx::query_as::<_, UserProfile>(&format!("SELECT {} FROM users WHERE id = $1", PROFILE_COLUMNS))
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| AppError::internal("查询用户失败", e))?
+        .ok_or_else(|| AppError::not_found("用户不存在"))
+}
+
+fn trimmed(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim)
+}
+
+/// 删除被替换掉的头像，失败只记录日志
+async fn discard_avatar(storage: &dyn ObjectStorage, user_id: Uuid, url: Option<&str>) {
+    if let Some(key) = url.and_then(|url| storage.object_key(url))
+        && key.starts_with(&avatar_prefix(user_id))
+        && let Err(e) = storage.delete(key).await
+    {
+        error!("删除旧头像失败: {}", e);
+    }
+}
+
+pub async fn get_profile(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> Result<HttpResponse, AppError> {
+    let profile = fetch_profile(&pool, user.id).await?;
+    Ok(HttpResponse::Ok().json(profile))
+}
+
+/// 修改姓名、头像和所在地。实名认证通过后姓名以认证结果为准，不能再修改
+pub async fn update_profile(
+    pool: web::Data<PgPool>,
+    storage: web::Data<dyn ObjectStorage>,
+    user: AuthUser,
+    locale: Locale,
+    body: ValidatedJson<UpdateProfileRequest>,
+) -> Result<HttpResponse, AppError> {
+    let current = fetch_profile(&pool, user.id).await?;
+    let avatar_url = trimmed(&body.avatar_url);
+    // 本站存储的地址只能由上传接口写入，原样提交当前头像视为未修改
+    if let Some(url) = avatar_url
+        && storage.object_key(url).is_some()
+        && Some(url) != current.avatar_url.as_deref()
+    {
+        return Err(AppError::bad_request("头像地址格式不正确"));
+    }
+    let real_name = trimmed(&body.real_name);
+    if current.id_verified_at.is_some() && real_name.is_some_and(|name| Some(name) != current.real_name.as_deref()) {
+        return Err(AppError::conflict("实名认证后不能修改姓名"));
+    }
+
+    // 参数为 NULL 时保持原值，空字符串清空
+    let profile = sqlx::query_as::<_, UserProfile>(&format!(
+        r#"
+        UPDATE users SET
+            real_name = CASE WHEN $2::TEXT IS NULL THEN real_name ELSE NULLIF($2, '') END,
+            avatar_url = CASE WHEN $3::TEXT IS NULL THEN avatar_url ELSE NULLIF($3, '') END,
+            province = CASE WHEN $4::TEXT IS NULL THEN province ELSE NULLIF($4, '') END,
+            city = CASE WHEN $5::TEXT IS NULL THEN city ELSE NULLIF($5, '') END,
+            updated_at = NOW()
+        WHERE id = $1
+        RETURNING {}
+        "#,
+        PROFILE_COLUMNS
+    ))
+    .bind(user.id)
+    .bind(real_name)
+    .bind(avatar_url)
+    .bind(trimmed(&body.province))
+    .bind(trimmed(&body.city))
+    .fetch_one(&**pool)
+    .await
+    .map_err(|e| AppError::internal("更新个人资料失败", e))?;
+
+    if current.avatar_url != profile.avatar_url {
+        discard_avatar(&**storage, user.id, current.avatar_url.as_deref()).await;
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
+        "message": i18n::translate(locale, "个人资料已更新"),
+        "profile": profile
+    })))
+}
+
+/// 上传头像，multipart 表单的 file 字段。按文件头识别格式，只接受 PNG、JPEG 和 WebP
+pub async fn upload_avatar(
+    pool: web::Data<PgPool>,
+    storage: web::Data<dyn ObjectStorage>,
+    user: AuthUser,
+    locale: Locale,
+    mut payload: Multipart,
+) -> Result<HttpResponse, AppError> {
+    let mut content = None;
+    while let Some(mut field) = payload.try_next().await.map_err(|_| AppError::bad_request("上传内容格式不正确"))? {
+        if field.name() != Some("file") {
+            continue;
+        }
+        let mut bytes = Vec::new();
+        while let Some(chunk) = field.try_next().await.map_err(|_| AppError::bad_request("上传内容格式不正确"))? {
+            if bytes.len() + chunk.len() > AVATAR_MAX_BYTES {
+                return Err(AppError::bad_request(format!("头像文件不能超过 {} MB", AVATAR_MAX_BYTES / 1024 / 1024)));
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+        content = Some(bytes);
+        break;
+    }
+    let content = content.ok_or_else(|| AppError::bad_request("请选择要上传的头像文件"))?;
+    let (content_type, extension) = image_type(&content).ok_or_else(|| AppError::bad_request("头像只支持 PNG、JPEG 或 WebP 格式"))?;
+
+    let key = format!("{}{}.{}", avatar_prefix(user.id), Uuid::new_v4().simple(), extension);
+    let avatar_url = storage
+        .put(&key, content_type, content)
+        .await
+        .map_err(|e| AppError::internal("保存头像失败", e))?;
+
+    let previous = sqlx::query_scalar::<_, Option<String>>(
+        r#"
+        UPDATE users u SET avatar_url = $2, updated_at = NOW()
+        FROM users previous
+        WHERE u.id = $1 AND previous.id = u.id
+        RETURNING previous.avatar_url
+        "#
+    )
+    .bind(user.id)
+    .bind(&avatar_url)
+    .fetch_one(&**pool)
+    .await
+    .map_err(|e| AppError::internal("更新头像失败", e))?;
+    discard_avatar(&**storage, user.id, previous.as_deref()).await;
+
+    info!("用户 {} 上传了新头像（{}）", user.username, storage.name());
+    Ok(HttpResponse::Ok().json(json!({
+        "message": i18n::translate(locale, "头像已更新"),
+        "avatar_url": avatar_url
+    })))
+}
+
+/// 用户头像的对象键前缀，只删除该前缀下的对象，避免误删他人文件
+fn avatar_prefix(user_id: Uuid) -> String {
+    format!("avatars/{}/", user_id)
+}
+
+/// 根据文件头判断图片格式，返回 (Content-Type, 扩展名)
+fn image_type(content: &[u8]) -> Option<(&'static str, &'static str)> {
+    if content.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some(("image/png", "png"))
+    } else if content.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some(("image/jpeg", "jpg"))
+    } else if content.len() >= 12 && &content[..4] == b"RIFF" && &content[8..12] == b"WEBP" {
+        Some(("image/webp", "webp"))
+    } else {
+        None
+    }
+}
+
+/// 修改密码需要验证当前密码，成功后其他设备需重新登录
+pub async fn change_password(
+    pool: web::Data<PgPool>,
+    revocations: web::Data<RevocationStore>,
+    user: AuthUser,
+    locale: Locale,
+    body: ValidatedJson<ChangePasswordRequest>,
+) -> Result<HttpResponse, AppError> {
+    let (session_id, _) = user.session().ok_or_else(|| AppError::forbidden("API 密钥不能执行此操作"))?;
+
+    let stored = sqlx::query_scalar::<_, String>("SELECT password_hash FROM users WHERE id = $1")
+        .bind(user.id)
+        .fetch_one(&**pool)
+        .await
+        .map_err(|e| AppError::internal("查询用户失败", e))?;
+    if !password::verify_password(&body.current_password, &stored).await.valid {
+        return Err(AppError::bad_request("当前密码错误"));
+    }
+    if body.new_password == body.current_password {
+        return Err(AppError::bad_request("新密码不能与当前密码相同"));
+    }
+
+    let hashed_password = password::hash_password(&body.new_password)
+        .await
+        .map_err(|e| AppError::internal("密码加密失败", e))?;
+    sqlx::query("UPDATE users SET password_hash = $1, updated_at = NOW() WHERE id = $2")
+        .bind(&hashed_password)
+        .bind(user.id)
+        .execute(&**pool)
+        .await
+        .map_err(|e| AppError::internal("修改密码失败", e))?;
+
+    let revoked = session_service::revoke_other_sessions(&pool, user.id, session_id)
+        .await
+        .map_err(|e| AppError::internal("注销会话失败", e))?;
+    revocations.forget_user(user.id);
+
+    info!("用户 {} 修改了密码，注销 {} 个其他会话", user.username, revoked);
+    Ok(HttpResponse::Ok().json(json!({"message": i18n::translate(locale, "密码已修改"), "revoked": revoked})))
+}
+
+/// 更换邮箱第一步：向新邮箱发送验证码
+pub async fn request_email_change(
+    pool: web::Data<PgPool>,
+    email_service: Option<web::Data<EmailService>>,
+    user: AuthUser,
+    locale: Locale,
+    body: ValidatedJson<ChangeEmailRequest>,
+) -> Result<HttpResponse, AppError> {
+    let email_service = email_service.ok_or_else(|| AppError::unavailable("邮件服务未配置"))?;
+    let email = body.email.trim();
+    if !email_service::is_valid_address(email) {
+        return Err(AppError::bad_request("邮箱格式不正确"));
+    }
+    ensure_email_available(&pool, email, user.id).await?;
+
+    let issued = verification_service::issue_email_code(&pool, email, VerificationPurpose::ChangeEmail).await?;
+    let template = EmailTemplate::VerificationCode {
+        code: &issued.code,
+        purpose: VerificationPurpose::ChangeEmail,
+        ttl_minutes: verification_service::CODE_TTL_MINUTES,
+    };
+    if let Err(e) = email_service.send_template(email, &template, locale).await {
+        error!("发送验证码邮件失败: {}", e);
+        if let Err(e) = verification_service::discard_email_code(&pool, issued.id).await {
+            error!("删除验证码失败: {}", e);
+        }
+        return Err(AppError::unavailable("邮件发送失败，请稍后重试"));
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
+        "message": i18n::translate(locale, "验证码已发送"),
+        "expires_in": verification_service::CODE_TTL_MINUTES * 60
+    })))
+}
+
+/// 更换邮箱第二步：校验新邮箱收到的验证码后替换，新邮箱视为已验证
+pub async fn confirm_email_change(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+    locale: Locale,
+    body: ValidatedJson<ConfirmEmailChangeRequest>,
+) -> Result<HttpResponse, AppError> {
+    let email = body.email.trim().to_lowercase();
+    let email = email.as_str();
+    ensure_email_available(&pool, email, user.id).await?;
+
+    let valid = verification_service::consume_email_code(&pool, email, VerificationPurpose::ChangeEmail, &body.code)
+        .await
+        .map_err(|e| AppError::internal("校验验证码失败", e))?;
+    if !valid {
+        return Err(AppError::bad_request("无效验证码"));
+    }
+
+    sqlx::query("UPDATE users SET email = $1, email_verified = true, updated_at = NOW() WHERE id = $2")
+        .bind(email)
+        .bind(user.id)
+        .execute(&**pool)
+        .await
+        .map_err(|e| match e.as_database_error() {
+            Some(db) if db.is_unique_violation() => AppError::conflict("该邮箱已被其他账号使用"),
+            _ => AppError::internal("更换邮箱失败", e),
+        })?;
+
+    info!("用户 {} 更换了绑定邮箱", user.username);
+    Ok(HttpResponse::Ok().json(json!({"message": i18n::translate(locale, "邮箱已更换"), "email": email})))
+}
+
+async fn ensure_email_available(pool: &PgPool, email: &str, user_id: Uuid) -> Result<(), AppError> {
+    let taken = sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM users WHERE LOWER(email) = LOWER($1) AND id <> $2)")
+        .bind(email)
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| AppError::internal("查询用户失败", e))?;
+    if taken {
+        return Err(AppError::conflict("该邮箱已被其他账号使用"));
+    }
+    Ok(())
+}
+
+/// 更换手机号第一步：向新号码发送验证码
+pub async fn request_phone_change(
+    pool: web::Data<PgPool>,
+    sms: Option<web::Data<dyn SmsProvider>>,
+    user: AuthUser,
+    locale: Locale,
+    req: HttpRequest,
+    body: ValidatedJson<ChangePhoneRequest>,
+) -> Result<HttpResponse, AppError> {
+    let sms = sms.ok_or_else(|| AppError::unavailable("短信服务未配置"))?;
+    let phone = normalized_phone(&body.phone)?;
+    ensure_phone_available(&pool, &phone, user.id).await?;
+
+    let issued = verification_service::issue_phone_code(&pool, &phone, PhoneCodePurpose::ChangePhone, &client_ip(&req)).await?;
+    if let Err(e) = sms.send_code(&phone, &issued.code, PhoneCodePurpose::ChangePhone).await {
+        error!("发送短信验证码失败: {}", e);
+        if let Err(e) = verification_service::discard_phone_code(&pool, issued.id).await {
+            error!("删除验证码失败: {}", e);
+        }
+        return Err(AppError::unavailable("短信发送失败，请稍后重试"));
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
+        "message": i18n::translate(locale, "验证码已发送"),
+        "expires_in": verification_service::CODE_TTL_MINUTES * 60
+    })))
+}
+
+/// 更换手机号第二步：校验新号码收到的验证码后替换，新号码视为已验证
+pub async fn confirm_phone_change(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+    locale: Locale,
+    body: ValidatedJson<ConfirmPhoneChangeRequest>,
+) -> Result<HttpResponse, AppError> {
+    let phone = normalized_phone(&body.phone)?;
+    ensure_phone_available(&pool, &phone, user.id).await?;
+
+    let valid = verification_service::consume_phone_code(&pool, &phone, PhoneCodePurpose::ChangePhone, &body.code)
+        .await
+        .map_err(|e| AppError::internal("校验验证码失败", e))?;
+    if !valid {
+        return Err(AppError::bad_request("无效验证码"));
+    }
+
+    sqlx::query("UPDATE users SET phone = $1, phone_verified = true, updated_at = NOW() WHERE id = $2")
+        .bind(&phone)
+        .bind(user.id)
+        .execute(&**pool)
+        .await
+        .map_err(|e| match e.as_database_error() {
+            Some(db) if db.is_unique_violation() => AppError::conflict("该手机号已被其他账号使用"),
+            _ => AppError::internal("更换手机号失败", e),
+        })?;
+
+    info!("用户 {} 更换了绑定手机号", user.username);
+    Ok(HttpResponse::Ok().json(json!({"message": i18n::translate(locale, "手机号已更换"), "phone": phone})))
+}
+
+async fn ensure_phone_available(pool: &PgPool, phone: &str, user_id: Uuid) -> Result<(), AppError> {
+    let taken = sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM users WHERE phone = $1 AND id <> $2)")
+        .bind(phone)
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| AppError::internal("查询用户失败", e))?;
+    if taken {
+        return Err(AppError::conflict("该手机号已被其他账号使用"));
+    }
+    Ok(())
+}
+
+/// 请求体已校验过格式，这里取统一后的号码
+fn normalized_phone(phone: &str) -> Result<String, AppError> {
+    sms_service::normalize_phone(phone).ok_or_else(|| AppError::bad_request("手机号格式不正确"))
+}
+
+/// 导出个人数据：资料、个人名下的实例、账单和支付记录、工单及所在组织，以 JSON 文件下载
+pub async fn export_data(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> Result<HttpResponse, AppError> {
+    let profile = fetch_profile(&pool, user.id).await?;
+
+    let instances = sqlx::query_as::<_, VmInstance>(&format!(
+        "SELECT {} FROM vm_instances WHERE user_id = $1 AND organization_id IS NULL ORDER BY created_at",
+        VM_INSTANCE_COLUMNS
+    ))
+    .bind(user.id)
+    .fetch_all(&**pool)
+    .await
+    .map_err(|e| AppError::internal("查询实例失败", e))?;
+
+    let invoices = sqlx::query_as::<_, Invoice>(&format!(
+        "SELECT {} FROM invoices WHERE user_id = $1 AND organization_id IS NULL ORDER BY created_at",
+        INVOICE_COLUMNS
+    ))
+    .bind(user.id)
+    .fetch_all(&**pool)
+    .await
+    .map_err(|e| AppError::internal("查询账单失败", e))?;
+
+    let payments = sqlx::query_as::<_, PaymentRecord>(&format!(
+        "SELECT {} FROM payment_records WHERE user_id = $1 AND organization_id IS NULL ORDER BY created_at",
+        PAYMENT_COLUMNS
+    ))
+    .bind(user.id)
+    .fetch_all(&**pool)
+    .await
+    .map_err(|e| AppError::internal("查询支付记录失败", e))?;
+
+    let tickets = sqlx::query_as::<_, Ticket>("SELECT * FROM tickets WHERE user_id = $1 ORDER BY created_at")
+        .bind(user.id)
+        .fetch_all(&**pool)
+        .await
+        .map_err(|e| AppError::internal("查询工单失败", e))?;
+
+    let organizations = sqlx::query_as::<_, Membership>(
+        r#"
+        SELECT o.id, o.name, m.role, m.joined_at
+        FROM organization_members m
+        JOIN organizations o ON o.id = m.organization_id
+        WHERE m.user_id = $1
+        ORDER BY m.joined_at
+        "#
+    )
+    .bind(user.id)
+    .fetch_all(&**pool)
+    .await
+    .map_err(|e| AppError::internal("查询组织失败", e))?;
+
+    let ticket_messages = sqlx::query_as::<_, TicketMessage>(
+        r#"
+        SELECT m.* FROM ticket_messages m
+        JOIN tickets t ON t.id = m.ticket_id
+        WHERE t.user_id = $1
+        ORDER BY m.created_at
+        "#
+    )
+    .bind(user.id)
+    .fetch_all(&**pool)
+    .await
+    .map_err(|e| AppError::internal("查询工单回复失败", e))?;
+
+    let exported_at = Utc::now();
+    let filename = format!("openvirt-export-{}-{}.json", profile.username, exported_at.format("%Y%m%d"));
+    info!("用户 {} 导出了个人数据", user.username);
+    Ok(HttpResponse::Ok()
+        .insert_header((header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)))
+        .json(json!({
+            "exported_at": exported_at,
+            "profile": profile,
+            "instances": instances,
+            "invoices": invoices,
+            "payments": payments,
+            "tickets": tickets,
+            "ticket_messages": ticket_messages,
+            "organizations": organizations
+        })))
+}
+
+/// 注销账户。需没有未删除的个人云主机、没有未付账单、余额为零且不是任何组织的唯一所有者，注销后个人信息清除且无法恢复
+pub async fn delete_account(
+    pool: web::Data<PgPool>,
+    storage: web::Data<dyn ObjectStorage>,
+    revocations: web::Data<RevocationStore>,
+    user: AuthUser,
+    locale: Locale,
+    body: ValidatedJson<DeleteAccountRequest>,
+) -> Result<HttpResponse, AppError> {
+    let (_, claims) = user.session().ok_or_else(|| AppError::forbidden("API 密钥不能执行此操作"))?;
+
+    let (stored, avatar_url) = sqlx::query_as::<_, (String, Option<String>)>(
+        "SELECT password_hash, avatar_url FROM users WHERE id = $1"
+    )
+    .bind(user.id)
+    .fetch_one(&**pool)
+    .await
+    .map_err(|e| AppError::internal("查询用户失败", e))?;
+    if !password::verify_password(&body.password, &stored).await.valid {
+        return Err(AppError::bad_request("当前密码错误"));
+    }
+
+    let mut tx = pool.begin().await.map_err(|e| AppError::internal("开启事务失败", e))?;
+
+    if let Some(blocker) = account_service::deletion_blocker(&mut tx, user.id)
+        .await
+        .map_err(|e| AppError::internal("查询账户状态失败", e))?
+    {
+        return Err(AppError::conflict(blocker.message()));
+    }
+    account_service::anonymize(&mut tx, user.id)
+        .await
+        .map_err(|e| AppError::internal("注销账户失败", e))?;
+
+    tx.commit().await.map_err(|e| AppError::internal("提交事务失败", e))?;
+    revocations.forget_user(user.id);
+
+    // 会话已删除，当前访问令牌也立即作废
+    if let Err(e) = revocations.revoke(&pool, claims).await {
+        error!("吊销令牌失败: {}", e);
+    }
+    discard_avatar(&**storage, user.id, avatar_url.as_deref()).await;
+
+    info!("用户 {} 已注销账户", user.username);
+    Ok(HttpResponse::Ok().json(json!({"message": i18n::translate(locale, "账户已注销")})))
+}
@@ -0,0 +1,192 @@
+use std::collections::BTreeMap;
+
+use actix_web::{web, HttpResponse};
+use chrono::Utc;
+use log::info;
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::middleware::auth::AuthUser;
+use crate::middleware::rbac::{authorize_resource, OwnedResource};
+use crate::models::monitoring::{MetricBucket, MetricPoint, StatsQuery, ALL_METRICS};
+use crate::models::organization::{OrgPermission, OrgRole, TransferInstanceRequest};
+use crate::services::{identity_service, organization_service};
+use crate::utils::error::AppError;
+use crate::utils::i18n;
+use crate::utils::locale::Locale;
+
+pub async fn get_instance_stats(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+    instance_id: web::Path<Uuid>,
+    query: web::Query<StatsQuery>,
+) -> Result<HttpResponse, AppError> {
+    let instance_id = instance_id.into_inner();
+
+    authorize_resource(&pool, &user, OwnedResource::VmInstance, instance_id).await?;
+
+    let metrics: Vec<String> = match &query.metrics {
+        Some(list) => {
+            let requested: Vec<String> = list
+                .split(',')
+                .map(|m| m.trim().to_string())
+                .filter(|m| !m.is_empty())
+                .collect();
+            if let Some(unknown) = requested.iter().find(|m| !ALL_METRICS.contains(&m.as_str())) {
+                return Err(AppError::bad_request(format!("不支持的监控指标: {}", unknown)));
+            }
+            requested
+        }
+        None => ALL_METRICS.iter().map(|m| m.to_string()).collect(),
+    };
+
+    let range = query.range;
+    let end = Utc::now();
+    let start = end - range.window();
+    let bucket_seconds = range.bucket_seconds();
+
+    // 按桶宽对原始数据做降采样，每个桶返回平均值和最大值
+    let buckets = sqlx::query_as::<_, MetricBucket>(
+        r#"
+        SELECT metric_type,
+               to_timestamp(floor(extract(epoch FROM timestamp) / $3::BIGINT) * $3::BIGINT) AS bucket,
+               AVG(value)::FLOAT8 AS avg_value,
+               MAX(value)::FLOAT8 AS max_value
+        FROM monitoring_data
+        WHERE vm_instance_id = $1
+          AND timestamp >= $2
+          AND metric_type = ANY($4)
+        GROUP BY metric_type, bucket
+        ORDER BY metric_type, bucket
+        "#
+    )
+    .bind(instance_id)
+    .bind(start)
+    .bind(bucket_seconds)
+    .bind(&metrics)
+    .fetch_all(&**pool)
+    .await
+    .map_err(|e| AppError::internal("查询监控数据失败", e))?;
+
+    // 没有数据的指标也返回空数组，方便前端固定图表
+    let mut series: BTreeMap<String, Vec<MetricPoint>> = metrics
+        .iter()
+        .map(|m| (m.clone(), Vec::new()))
+        .collect();
+    for bucket in buckets {
+        series.entry(bucket.metric_type).or_default().push(MetricPoint {
+            timestamp: bucket.bucket,
+            avg: bucket.avg_value,
+            max: bucket.max_value,
+        });
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
+        "instance_id": instance_id,
+        "range": range,
+        "interval_seconds": bucket_seconds,
+        "start": start.to_rfc3339(),
+        "end": end.to_rfc3339(),
+        "series": series
+    })))
+}
+
+/// 在个人账户和组织之间转移实例。
+/// 转出组织需要该组织的转移权限，组织实例转入个人账户只允许所有者操作，转入组织需要目标组织的运维权限；
+/// 转移后 user_id 记为操作人，失去访问权的用户在该实例上的告警规则一并删除
+pub async fn transfer_instance(
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+    locale: Locale,
+    instance_id: web::Path<Uuid>,
+    body: web::Json<TransferInstanceRequest>,
+) -> Result<HttpResponse, AppError> {
+    let instance_id = instance_id.into_inner();
+    let target = body.organization_id;
+
+    let mut tx = pool.begin().await.map_err(|e| AppError::internal("开启事务失败", e))?;
+
+    let (owner, source, status) = sqlx::query_as::<_, (Uuid, Option<Uuid>, String)>(
+        "SELECT user_id, organization_id, status FROM vm_instances WHERE id = $1 AND status <> 'deleted' FOR UPDATE"
+    )
+    .bind(instance_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| AppError::internal("查询实例失败", e))?
+    .ok_or_else(|| AppError::not_found("实例不存在"))?;
+
+    // 转出方：个人实例只能由本人转出，看不到实例的成员按不存在处理
+    match source {
+        None if owner != user.id => return Err(AppError::not_found("实例不存在")),
+        None => {}
+        Some(organization_id) => {
+            let role = organization_service::member_role(&mut *tx, organization_id, user.id)
+                .await
+                .map_err(|e| AppError::internal("查询组织成员失败", e))?;
+            // 组织付费的实例转入个人账户只能由所有者操作，管理员只能转给其他组织
+            match role {
+                Some(role) if target.is_none() && role != OrgRole::Owner && role.can(OrgPermission::ViewInstances) => {
+                    return Err(AppError::forbidden("只有组织所有者可以把实例转入个人账户"));
+                }
+                Some(role) if role.can(OrgPermission::TransferInstances) => {}
+                Some(role) if role.can(OrgPermission::ViewInstances) => return Err(AppError::forbidden("权限不足")),
+                _ => return Err(AppError::not_found("实例不存在")),
+            }
+        }
+    }
+    if source == target {
+        return Err(AppError::bad_request("实例已属于该账户"));
+    }
+    if let Some(organization_id) = target {
+        let role = organization_service::member_role(&mut *tx, organization_id, user.id)
+            .await
+            .map_err(|e| AppError::internal("查询组织成员失败", e))?
+            .ok_or_else(|| AppError::not_found("组织不存在"))?;
+        if !role.can(OrgPermission::ManageInstances) {
+            return Err(AppError::forbidden("权限不足"));
+        }
+    }
+    if matches!(status.as_str(), "creating" | "deleting") {
+        return Err(AppError::conflict("实例正在创建或删除，暂不能转移"));
+    }
+    // 转移后 user_id 记为操作人，与创建实例一样需满足实名要求
+    identity_service::ensure_may_create_instance(&mut *tx, user.id).await?;
+
+    sqlx::query("UPDATE vm_instances SET user_id = $2, organization_id = $3 WHERE id = $1")
+        .bind(instance_id)
+        .bind(user.id)
+        .bind(target)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| AppError::internal("转移实例失败", e))?;
+
+    sqlx::query(
+        r#"
+        DELETE FROM alert_rules r
+        WHERE r.vm_instance_id = $1
+          AND CASE WHEN $3::UUID IS NULL THEN r.user_id <> $2
+                   ELSE NOT EXISTS(
+                       SELECT 1 FROM organization_members m
+                       WHERE m.organization_id = $3 AND m.user_id = r.user_id AND m.role = ANY($4)
+                   )
+              END
+        "#
+    )
+    .bind(instance_id)
+    .bind(user.id)
+    .bind(target)
+    .bind(OrgRole::with_permission(OrgPermission::ViewInstances))
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| AppError::internal("转移实例失败", e))?;
+
+    tx.commit().await.map_err(|e| AppError::internal("提交事务失败", e))?;
+
+    info!("用户 {} 把实例 {} 从 {:?} 转移到 {:?}", user.username, instance_id, source, target);
+    Ok(HttpResponse::Ok().json(json!({
+        "message": i18n::translate(locale, "实例已转移"),
+        "instance_id": instance_id,
+        "organization_id": target
+    })))
+}
@@ -1,6 +1,7 @@
-use actix_web::{App, HttpServer, web};
+use std::sync::Arc;
+
+use actix_web::{App, HttpServer, middleware::from_fn, web};
 use dotenv::dotenv;
-use sqlx::migrate::MigrateError;
 
 mod routes;
 mod models;
@@ -8,8 +9,13 @@ mod handlers;
 mod middleware;
 mod utils;
 mod database;
+mod services;
 
 use database::{create_pool, DbPool};
+use services::email_service::EmailService;
+use services::revocation_service::RevocationStore;
+use services::webauthn_service::PasskeyService;
+use utils::jwt::JwtKeys;
 
 async fn run_migrations(pool: &DbPool) {
     if let Err(e) = database::migrate(pool).await {
@@ -26,12 +32,119 @@ async fn main() -> std::io::Result<()> {
 
     println!("Server started, listening on: {}", server_address);
 
+    // 不再回退到默认密钥，配置错误时直接拒绝启动
+    let jwt_keys = match JwtKeys::from_env() {
+        Ok(keys) => Arc::new(keys),
+        Err(e) => panic!("Failed to load JWT keys: {}", e),
+    };
+
+    utils::password::init();
+
+    let passkey_service = match PasskeyService::from_env() {
+        Ok(service) => Arc::new(service),
+        Err(e) => panic!("Invalid WebAuthn configuration: {}", e),
+    };
+
+    let identity_provider = match services::identity_service::provider_from_env() {
+        Ok(provider) => provider,
+        Err(e) => panic!("Invalid identity verification configuration: {}", e),
+    };
+
+    let object_storage = match services::storage_service::storage_from_env() {
+        Ok(storage) => storage,
+        Err(e) => panic!("Invalid storage configuration: {}", e),
+    };
+
+    // 敏感字段加密密钥可选，未配置时实名认证和两步验证接口返回 503
+    let field_cipher = match utils::crypto::FieldCipher::from_env() {
+        Ok(cipher) => Some(Arc::new(cipher)),
+        Err(e) => {
+            log::warn!("实名认证和两步验证不可用: {}", e);
+            None
+        }
+    };
+
+    // 邮件服务可选，未配置时发送验证码的接口返回 503
+    let email_service = match EmailService::from_env() {
+        Ok(service) => Some(Arc::new(service)),
+        Err(e) => {
+            log::warn!("邮件服务不可用: {}", e);
+            None
+        }
+    };
+
+    // RATE_LIMIT_ENABLED=false 时不注册限流器，中间件直接放行
+    let rate_limiter = if std::env::var("RATE_LIMIT_ENABLED").map(|v| v != "false").unwrap_or(true) {
+        match services::rate_limit_service::RateLimiter::from_env().await {
+            Ok(limiter) => {
+                let limiter = Arc::new(limiter);
+                services::rate_limit_service::spawn_rate_limit_cleanup(limiter.clone());
+                Some(limiter)
+            }
+            Err(e) => panic!("Failed to connect rate limit store: {}", e),
+        }
+    } else {
+        None
+    };
+
+    let captcha_verifier = services::captcha_service::CaptchaVerifier::from_env().map(Arc::new);
+    if captcha_verifier.is_none() {
+        log::warn!("未配置人机验证服务，多次登录失败后只返回需要验证的标记，不会校验 captcha_token");
+    }
+
     let db_pool = create_pool().await.expect("Failed to create database pool");
     run_migrations(&db_pool).await;
 
+    // 短信服务可选，未配置时发送短信验证码的接口返回 503
+    let sms_provider = match services::sms_service::provider_from_config(&db_pool).await {
+        Ok(provider) => {
+            log::info!("短信服务商: {}", provider.name());
+            Some(provider)
+        }
+        Err(e) => {
+            log::warn!("短信服务不可用: {}", e);
+            None
+        }
+    };
+
+    services::alert_service::spawn_alert_evaluator(db_pool.clone());
+    services::node_health_service::spawn_node_health_checker(db_pool.clone());
+    services::capacity_service::spawn_capacity_sync(db_pool.clone());
+    services::maintenance_service::spawn_maintenance(db_pool.clone());
+
+    let revocation_store = Arc::new(RevocationStore::new());
+    services::revocation_service::spawn_revocation_cleanup(db_pool.clone(), revocation_store.clone());
+
     HttpServer::new(move || {
-        App::new()
+        let mut app = App::new()
             .app_data(web::Data::new(db_pool.clone()))
+            .app_data(web::Data::from(revocation_store.clone()))
+            .app_data(web::Data::from(jwt_keys.clone()))
+            .app_data(web::Data::from(passkey_service.clone()))
+            .app_data(web::Data::from(identity_provider.clone()))
+            .app_data(web::Data::from(object_storage.clone()));
+        if let Some(email_service) = &email_service {
+            app = app.app_data(web::Data::from(email_service.clone()));
+        }
+        if let Some(field_cipher) = &field_cipher {
+            app = app.app_data(web::Data::from(field_cipher.clone()));
+        }
+        if let Some(rate_limiter) = &rate_limiter {
+            app = app.app_data(web::Data::from(rate_limiter.clone()));
+        }
+        if let Some(captcha_verifier) = &captcha_verifier {
+            app = app.app_data(web::Data::from(captcha_verifier.clone()));
+        }
+        if let Some(sms_provider) = &sms_provider {
+            app = app.app_data(web::Data::from(sms_provider.clone()));
+        }
+        // 本地存储的上传文件由本服务直接提供
+        if let Some((public_url, root)) = object_storage.local_mount() {
+            app = app.service(actix_files::Files::new(public_url, root));
+        }
+        app.wrap(from_fn(middleware::rate_limit::limit_requests))
+            .wrap(from_fn(middleware::request_id::assign_request_id))
+            .wrap(from_fn(middleware::metrics::track_requests))
             .configure(routes::config)
     })
     .bind(server_address)?
@@ -0,0 +1,113 @@
+use actix_web::dev::ServiceRequest;
+use actix_web::http::Method;
+use actix_web::{web, Error, HttpMessage};
+use log::{debug, error};
+
+use crate::database::DbPool;
+use crate::middleware::auth::{AuthUser, Credential};
+use crate::models::api_key::ApiKeyScope;
+use crate::services::api_key_service::{self, ApiKeyLookup};
+use crate::utils::error::AppError;
+use crate::utils::request::client_ip;
+
+// 实例开关机类操作，对应 /api/vm/instances/{id}/<action>
+const VM_POWER_ACTIONS: &[&str] = &["start", "stop", "restart"];
+
+fn is_vm_power_action(pattern: &str) -> bool {
+    pattern
+        .strip_prefix("/api/vm/instances/{id}/")
+        .is_some_and(|action| VM_POWER_ACTIONS.contains(&action))
+}
+
+/// 判断某个授权范围是否允许访问该路由
+fn scope_allows(scope: ApiKeyScope, method: &Method, pattern: &str) -> bool {
+    match scope {
+        ApiKeyScope::Read => method == Method::GET,
+        ApiKeyScope::VmPower => {
+            (method == Method::GET && pattern.starts_with("/api/vm/"))
+                || (method == Method::POST && is_vm_power_action(pattern))
+        }
+        ApiKeyScope::Billing => pattern.starts_with("/api/billing/"),
+    }
+}
+
+/// API 密钥不能管理登录会话、两步验证、密钥本身和账户设置
+fn is_account_route(pattern: &str) -> bool {
+    pattern.starts_with("/api/auth/") || pattern.starts_with("/api/user/")
+}
+
+pub(crate) async fn authenticate(req: ServiceRequest, key: &str) -> Result<ServiceRequest, (Error, ServiceRequest)> {
+    let Some(pool) = req.app_data::<web::Data<DbPool>>() else {
+        let e = AppError::internal("数据库连接未初始化", anyhow::anyhow!("缺少 DbPool"));
+        return Err((e.into(), req));
+    };
+
+    let ip_address = client_ip(req.request());
+    let principal = match api_key_service::authenticate(pool, key, &ip_address).await {
+        Ok(ApiKeyLookup::Valid(principal)) => principal,
+        Ok(ApiKeyLookup::Invalid) => return Err((AppError::unauthorized("无效的 API 密钥").into(), req)),
+        Ok(ApiKeyLookup::IpNotAllowed) => {
+            return Err((AppError::forbidden("来源 IP 不在 API 密钥允许范围内").into(), req));
+        }
+        Err(e) => {
+            error!("校验 API 密钥失败: {}", e);
+            return Err((AppError::unavailable("API 密钥校验失败").into(), req));
+        }
+    };
+
+    let pattern = req.match_pattern().unwrap_or_else(|| req.path().to_string());
+    debug!("API 密钥 {} 访问 {} {}", principal.key_id, req.method(), pattern);
+    let allowed = !is_account_route(&pattern)
+        && principal
+            .scopes
+            .iter()
+            .any(|scope| scope_allows(*scope, req.method(), &pattern));
+    if !allowed {
+        return Err((AppError::forbidden("API 密钥无权访问该接口").into(), req));
+    }
+
+    req.extensions_mut().insert(AuthUser {
+        id: principal.user_id,
+        username: principal.username,
+        role: principal.role,
+        credential: Credential::ApiKey,
+    });
+    Ok(req)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_scope_only_allows_get() {
+        assert!(scope_allows(ApiKeyScope::Read, &Method::GET, "/api/vm/instances"));
+        assert!(!scope_allows(ApiKeyScope::Read, &Method::POST, "/api/vm/instances/{id}/start"));
+        assert!(!scope_allows(ApiKeyScope::Read, &Method::DELETE, "/api/vm/instances/{id}"));
+    }
+
+    #[test]
+    fn vm_power_scope_allows_power_actions_only() {
+        assert!(scope_allows(ApiKeyScope::VmPower, &Method::GET, "/api/vm/instances/{id}"));
+        assert!(scope_allows(ApiKeyScope::VmPower, &Method::POST, "/api/vm/instances/{id}/start"));
+        assert!(scope_allows(ApiKeyScope::VmPower, &Method::POST, "/api/vm/instances/{id}/restart"));
+        assert!(!scope_allows(ApiKeyScope::VmPower, &Method::POST, "/api/vm/instances/{id}/reinstall"));
+        assert!(!scope_allows(ApiKeyScope::VmPower, &Method::DELETE, "/api/vm/instances/{id}"));
+        assert!(!scope_allows(ApiKeyScope::VmPower, &Method::GET, "/api/billing/orders"));
+    }
+
+    #[test]
+    fn billing_scope_is_limited_to_billing_routes() {
+        assert!(scope_allows(ApiKeyScope::Billing, &Method::GET, "/api/billing/orders"));
+        assert!(scope_allows(ApiKeyScope::Billing, &Method::POST, "/api/billing/recharge"));
+        assert!(!scope_allows(ApiKeyScope::Billing, &Method::GET, "/api/vm/instances"));
+    }
+
+    #[test]
+    fn account_routes_are_detected() {
+        assert!(is_account_route("/api/auth/sessions"));
+        assert!(is_account_route("/api/user/api-keys"));
+        assert!(!is_account_route("/api/vm/instances"));
+        assert!(!is_account_route("/api/billing/orders"));
+    }
+}
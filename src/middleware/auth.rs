@@ -0,0 +1,67 @@
+use std::future::{ready, Ready};
+
+use actix_web::dev::Payload;
+use actix_web::{Error, FromRequest, HttpMessage, HttpRequest};
+use uuid::Uuid;
+
+use crate::utils::error::AppError;
+use crate::utils::jwt::Claims;
+
+/// 请求所使用的凭据
+#[derive(Debug, Clone)]
+pub enum Credential {
+    /// 登录会话签发的访问令牌
+    Session { session_id: Uuid, claims: Claims },
+    /// 个人 API 密钥，只能访问授权范围内的接口
+    /// 授权范围已由认证中间件按路由校验
+    ApiKey,
+}
+
+/// 已通过 jwt_validator 认证的当前用户，处理器直接作为参数使用
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    pub id: Uuid,
+    pub username: String,
+    pub role: String,
+    pub credential: Credential,
+}
+
+impl AuthUser {
+    /// 会话管理类操作只能使用登录令牌
+    pub fn session(&self) -> Option<(Uuid, &Claims)> {
+        match &self.credential {
+            Credential::Session { session_id, claims } => Some((*session_id, claims)),
+            Credential::ApiKey => None,
+        }
+    }
+}
+
+impl TryFrom<Claims> for AuthUser {
+    type Error = uuid::Error;
+
+    fn try_from(claims: Claims) -> Result<Self, Self::Error> {
+        Ok(AuthUser {
+            id: Uuid::parse_str(&claims.sub)?,
+            username: claims.username.clone(),
+            role: claims.role.clone(),
+            credential: Credential::Session {
+                session_id: Uuid::parse_str(&claims.sid)?,
+                claims,
+            },
+        })
+    }
+}
+
+impl FromRequest for AuthUser {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let user = req
+            .extensions()
+            .get::<AuthUser>()
+            .cloned()
+            .ok_or_else(|| AppError::unauthorized("缺少授权令牌").into());
+        ready(user)
+    }
+}
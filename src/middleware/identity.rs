@@ -0,0 +1,50 @@
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::Method;
+use actix_web::middleware::Next;
+use actix_web::{web, Error, HttpMessage};
+
+use crate::database::DbPool;
+use crate::middleware::auth::AuthUser;
+use crate::services::identity_service::{self, InstanceGuardError};
+use crate::utils::error::AppError;
+
+const CREATE_INSTANCE_PATH: &str = "/api/vm/instances";
+
+fn creates_instance(req: &ServiceRequest) -> bool {
+    req.method() == Method::POST && req.path().trim_end_matches('/') == CREATE_INSTANCE_PATH
+}
+
+/// 路由守卫：system_configs.id_verify_required 开启时，未实名的用户不能创建实例，需放在 jwt_validator 之内。
+/// 创建实例的服务代码仍需自行调用 identity_service::ensure_may_create_instance，这里只是提前拒绝
+pub async fn require_identity_for_instances(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    if !creates_instance(&req) {
+        return Ok(next.call(req).await?.map_into_left_body());
+    }
+    // 缺少认证信息或数据库连接时拒绝，不能放行
+    let Some(user_id) = req.extensions().get::<AuthUser>().map(|user| user.id) else {
+        let e = AppError::unauthorized("缺少授权令牌");
+        return Ok(req.error_response(e).map_into_right_body());
+    };
+    let Some(pool) = req.app_data::<web::Data<DbPool>>() else {
+        let e = AppError::internal("数据库连接未初始化", anyhow::anyhow!("缺少 DbPool"));
+        return Ok(req.error_response(e).map_into_right_body());
+    };
+
+    match identity_service::ensure_may_create_instance(pool.get_ref(), user_id).await {
+        Ok(()) => Ok(next.call(req).await?.map_into_left_body()),
+        Err(e) => Ok(req.error_response(AppError::from(e)).map_into_right_body()),
+    }
+}
+
+impl From<InstanceGuardError> for AppError {
+    fn from(e: InstanceGuardError) -> Self {
+        match e {
+            InstanceGuardError::IdentityRequired => AppError::IdentityRequired("请先完成实名认证".into()),
+            InstanceGuardError::Database(e) => AppError::internal("查询实名认证状态失败", e),
+        }
+    }
+}
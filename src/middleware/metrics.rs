@@ -0,0 +1,34 @@
+use std::time::Instant;
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::Error;
+
+use crate::utils::metrics::{HTTP_REQUESTS_TOTAL, HTTP_REQUEST_DURATION};
+
+/// 按路由模板记录请求数和耗时，未匹配的路由统一记为 "unmatched" 以控制标签基数
+pub async fn track_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let method = req.method().to_string();
+    let started = Instant::now();
+
+    let res = next.call(req).await?;
+
+    let route = res
+        .request()
+        .match_pattern()
+        .unwrap_or_else(|| "unmatched".to_string());
+    let status = res.status().as_u16().to_string();
+
+    HTTP_REQUESTS_TOTAL
+        .with_label_values(&[&method, &route, &status])
+        .inc();
+    HTTP_REQUEST_DURATION
+        .with_label_values(&[&method, &route])
+        .observe(started.elapsed().as_secs_f64());
+
+    Ok(res)
+}
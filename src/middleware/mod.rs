@@ -1,15 +1,82 @@
-use actix_web::{dev::ServiceRequest, Error};
+use actix_web::{dev::ServiceRequest, web, Error, HttpMessage};
 use actix_web_httpauth::extractors::bearer::BearerAuth;
+use jsonwebtoken::errors::ErrorKind;
+use log::error;
 
+use crate::database::DbPool;
+use crate::middleware::auth::AuthUser;
+use crate::services::api_key_service::API_KEY_PREFIX;
+use crate::services::revocation_service::RevocationStore;
+use crate::utils::error::AppError;
+use crate::utils::jwt::JwtKeys;
 use crate::utils::validate_jwt;
 
+pub mod api_key;
+pub mod auth;
+pub mod identity;
+pub mod metrics;
+pub mod rate_limit;
+pub mod rbac;
+pub mod request_id;
+
+/// Bearer 认证，同时接受 JWT 访问令牌和个人 API 密钥
 pub async fn jwt_validator(
     req: ServiceRequest,
     credentials: BearerAuth,
 ) -> Result<ServiceRequest, (Error, ServiceRequest)> {
-    let secret = std::env::var("JWT_SECRET").expect("JWT_SECRET must be set");
-    match validate_jwt(credentials.token(), &secret) {
-        Ok(_) => Ok(req),
-        Err(e) => Err((actix_web::error::ErrorUnauthorized(e.to_string()), req))
+    if credentials.token().starts_with(API_KEY_PREFIX) {
+        return api_key::authenticate(req, credentials.token()).await;
+    }
+
+    let (Some(keys), Some(pool), Some(revocations)) = (
+        req.app_data::<web::Data<JwtKeys>>(),
+        req.app_data::<web::Data<DbPool>>(),
+        req.app_data::<web::Data<RevocationStore>>(),
+    ) else {
+        let e = AppError::internal("令牌校验组件未初始化", anyhow::anyhow!("缺少 JwtKeys、DbPool 或 RevocationStore"));
+        return Err((e.into(), req));
+    };
+
+    let claims = match validate_jwt(keys, credentials.token()) {
+        Ok(claims) => claims,
+        Err(e) => {
+            let message = match e.kind() {
+                ErrorKind::ExpiredSignature => "令牌已过期",
+                _ => "无效令牌",
+            };
+            return Err((AppError::unauthorized(message).into(), req));
+        }
+    };
+
+    // 查询失败时拒绝请求，避免已登出的令牌被放行
+    match revocations.is_revoked(pool, &claims).await {
+        Ok(false) => (),
+        Ok(true) => return Err((AppError::unauthorized("令牌已失效").into(), req)),
+        Err(e) => {
+            error!("查询令牌吊销状态失败: {}", e);
+            return Err((AppError::unavailable("令牌校验失败").into(), req));
+        }
     }
+
+    let mut user = match AuthUser::try_from(claims) {
+        Ok(user) => user,
+        Err(_) => return Err((AppError::unauthorized("无效令牌").into(), req)),
+    };
+
+    // 令牌中的角色只是签发时的快照，以数据库中的账户状态、角色和会话状态为准
+    let Some((session_id, _)) = user.session() else {
+        return Err((AppError::unauthorized("无效令牌").into(), req));
+    };
+    match revocations.session_role(pool, user.id, session_id).await {
+        Ok(Some(role)) => user.role = role,
+        Ok(None) => return Err((AppError::unauthorized("令牌已失效").into(), req)),
+        Err(e) => {
+            error!("查询会话状态失败: {}", e);
+            return Err((AppError::unavailable("令牌校验失败").into(), req));
+        }
+    }
+
+    // 将当前用户交给后续处理器使用
+    req.extensions_mut().insert(user);
+    Ok(req)
 }
@@ -0,0 +1,211 @@
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::{Error, HttpMessage};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::middleware::auth::AuthUser;
+use crate::models::organization::{OrgPermission, OrgRole};
+use crate::services::organization_service;
+use crate::utils::error::AppError;
+
+/// 与 users.role 的取值一一对应
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    User,
+    Support,
+    Admin,
+}
+
+/// 访问他人资源所需的权限，访问自己名下的资源不需要任何权限
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::enum_variant_names)]
+pub enum Permission {
+    ViewAllInstances,
+    ViewAllTickets,
+    ViewAllBilling,
+}
+
+// 客服处理工单时需要查看用户的实例，但不接触账务
+const SUPPORT_PERMISSIONS: &[Permission] = &[
+    Permission::ViewAllInstances,
+    Permission::ViewAllTickets,
+];
+
+const ADMIN_PERMISSIONS: &[Permission] = &[
+    Permission::ViewAllInstances,
+    Permission::ViewAllTickets,
+    Permission::ViewAllBilling,
+];
+
+impl Role {
+    pub fn parse(role: &str) -> Option<Role> {
+        match role {
+            "user" => Some(Role::User),
+            "support" => Some(Role::Support),
+            "admin" => Some(Role::Admin),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::User => "user",
+            Role::Support => "support",
+            Role::Admin => "admin",
+        }
+    }
+
+    /// 普通用户没有额外权限，只能访问自己名下的资源
+    pub fn permissions(&self) -> &'static [Permission] {
+        match self {
+            Role::User => &[],
+            Role::Support => SUPPORT_PERMISSIONS,
+            Role::Admin => ADMIN_PERMISSIONS,
+        }
+    }
+
+    pub fn is_staff(&self) -> bool {
+        matches!(self, Role::Support | Role::Admin)
+    }
+}
+
+impl AuthUser {
+    /// 无法识别的角色按普通用户处理
+    pub fn role(&self) -> Role {
+        Role::parse(&self.role).unwrap_or(Role::User)
+    }
+
+    pub fn can(&self, permission: Permission) -> bool {
+        self.role().permissions().contains(&permission)
+    }
+}
+
+fn request_role(req: &ServiceRequest) -> Option<Role> {
+    req.extensions()
+        .get::<AuthUser>()
+        .and_then(|user| Role::parse(&user.role))
+}
+
+/// 路由守卫：仅管理员，需放在 jwt_validator 之内
+pub async fn require_admin(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    match request_role(&req) {
+        Some(Role::Admin) => Ok(next.call(req).await?.map_into_left_body()),
+        _ => Ok(req.error_response(AppError::forbidden("权限不足")).map_into_right_body()),
+    }
+}
+
+/// 路由守卫：管理员或客服
+pub async fn require_staff(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    match request_role(&req) {
+        Some(role) if role.is_staff() => Ok(next.call(req).await?.map_into_left_body()),
+        _ => Ok(req.error_response(AppError::forbidden("权限不足")).map_into_right_body()),
+    }
+}
+
+/// 归属于某个用户的资源
+#[derive(Debug, Clone, Copy)]
+pub enum OwnedResource {
+    VmInstance,
+    Ticket,
+    Invoice,
+}
+
+impl OwnedResource {
+    fn ownership_query(&self) -> &'static str {
+        match self {
+            OwnedResource::VmInstance => {
+                "SELECT user_id, organization_id FROM vm_instances WHERE id = $1 AND status <> 'deleted'"
+            }
+            OwnedResource::Ticket => "SELECT user_id, NULL::UUID AS organization_id FROM tickets WHERE id = $1",
+            OwnedResource::Invoice => "SELECT user_id, organization_id FROM invoices WHERE id = $1",
+        }
+    }
+
+    /// 组织成员访问组织名下该类资源所需的权限，工单只属于个人
+    fn organization_permission(&self) -> Option<OrgPermission> {
+        match self {
+            OwnedResource::VmInstance => Some(OrgPermission::ViewInstances),
+            OwnedResource::Ticket => None,
+            OwnedResource::Invoice => Some(OrgPermission::ViewBilling),
+        }
+    }
+
+    /// 可以越过归属检查访问该类资源的权限
+    fn view_all_permission(&self) -> Permission {
+        match self {
+            OwnedResource::VmInstance => Permission::ViewAllInstances,
+            OwnedResource::Ticket => Permission::ViewAllTickets,
+            OwnedResource::Invoice => Permission::ViewAllBilling,
+        }
+    }
+
+    fn not_found_message(&self) -> &'static str {
+        match self {
+            OwnedResource::VmInstance => "实例不存在",
+            OwnedResource::Ticket => "工单不存在",
+            OwnedResource::Invoice => "账单不存在",
+        }
+    }
+}
+
+/// 校验当前用户能否访问资源：个人资源的本人、组织资源中有对应权限的成员，或拥有对应查看权限的员工。
+/// 无权访问时与不存在一样返回 404，避免泄露资源是否存在
+pub async fn authorize_resource(
+    pool: &PgPool,
+    user: &AuthUser,
+    resource: OwnedResource,
+    resource_id: Uuid,
+) -> Result<(), AppError> {
+    let owner = sqlx::query_as::<_, (Uuid, Option<Uuid>)>(resource.ownership_query())
+        .bind(resource_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| AppError::internal("查询资源归属失败", e))?;
+    let not_found = || AppError::not_found(resource.not_found_message());
+
+    let Some((owner, organization_id)) = owner else {
+        return Err(not_found());
+    };
+    if user.can(resource.view_all_permission()) {
+        return Ok(());
+    }
+    // 组织名下的资源只看成员权限，创建人离开组织后不能再访问
+    match (organization_id, resource.organization_permission()) {
+        (Some(organization_id), Some(permission)) => {
+            let role = organization_service::member_role(pool, organization_id, user.id)
+                .await
+                .map_err(|e| AppError::internal("查询组织成员失败", e))?;
+            if role.is_some_and(|role| role.can(permission)) { Ok(()) } else { Err(not_found()) }
+        }
+        _ if owner == user.id => Ok(()),
+        _ => Err(not_found()),
+    }
+}
+
+/// 校验当前用户在组织中拥有指定权限，返回其角色。
+/// 非成员返回 404，成员权限不足返回 403
+pub async fn authorize_organization(
+    pool: &PgPool,
+    user: &AuthUser,
+    organization_id: Uuid,
+    permission: Option<OrgPermission>,
+) -> Result<OrgRole, AppError> {
+    let role = organization_service::member_role(pool, organization_id, user.id)
+        .await
+        .map_err(|e| AppError::internal("查询组织成员失败", e))?
+        .ok_or_else(|| AppError::not_found("组织不存在"))?;
+    match permission {
+        Some(permission) if !role.can(permission) => Err(AppError::forbidden("权限不足")),
+        _ => Ok(role),
+    }
+}
@@ -0,0 +1,73 @@
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::Error;
+use log::error;
+use uuid::Uuid;
+
+use crate::utils::error::AppError;
+use crate::utils::locale::Locale;
+
+const X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+const MAX_REQUEST_ID_LENGTH: usize = 64;
+
+/// 沿用网关传入的 X-Request-Id，只接受字母、数字和 -_，避免日志注入
+fn incoming_request_id(req: &ServiceRequest) -> Option<String> {
+    let id = req.headers().get(X_REQUEST_ID)?.to_str().ok()?;
+    let valid = !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LENGTH
+        && id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+    valid.then(|| id.to_string())
+}
+
+/// 按请求语言重新生成错误响应并带上请求 ID，内部错误在这里记录日志以便按 ID 排查
+fn render_error(res: ServiceResponse<BoxBody>, locale: Locale, request_id: &str) -> ServiceResponse<BoxBody> {
+    let Some(error) = res.response().error() else {
+        return res;
+    };
+    let rendered = match error.as_error::<AppError>() {
+        Some(app_error) => {
+            if let AppError::Internal { .. } = app_error {
+                error!("[{}] {}", request_id, app_error);
+            }
+            app_error.render(locale, Some(request_id))
+        }
+        None => match AppError::from_status(res.status(), error.to_string()) {
+            Some(app_error) => app_error.render(locale, Some(request_id)),
+            None => return res,
+        },
+    };
+
+    // 保留原响应中的 WWW-Authenticate、RateLimit-* 等头
+    let (req, original) = res.into_parts();
+    let mut rendered = rendered;
+    for (name, value) in original.headers() {
+        if !rendered.headers().contains_key(name) {
+            rendered.headers_mut().append(name.clone(), value.clone());
+        }
+    }
+    ServiceResponse::new(req, rendered)
+}
+
+/// 为每个请求分配 ID，写入 X-Request-Id 响应头，并统一错误响应的格式
+pub async fn assign_request_id(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let request_id = incoming_request_id(&req).unwrap_or_else(|| Uuid::new_v4().simple().to_string());
+
+    // 内层中间件需用 req.error_response 返回错误，直接返回 Err 的不会经过这里
+    let res = next.call(req).await?.map_into_boxed_body();
+    // 请求处理完后认证信息已写入扩展，这时才能读取用户的语言偏好
+    let mut res = if res.response().error().is_some() {
+        let locale = Locale::resolve(res.request()).await;
+        render_error(res, locale, &request_id)
+    } else {
+        res
+    };
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        res.headers_mut().insert(X_REQUEST_ID, value);
+    }
+    Ok(res)
+}
@@ -0,0 +1,105 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::utils::validation::validate_not_blank;
+
+// alert_rules.metric_type 中除监控指标外的特殊规则：实例宕机
+pub const RULE_INSTANCE_DOWN: &str = "instance_down";
+
+pub const CHANNEL_EMAIL: &str = "email";
+pub const CHANNEL_WEBHOOK: &str = "webhook";
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct NotificationChannel {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub channel_type: String,
+    pub target: String,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreateChannelRequest {
+    #[validate(custom = "validate_not_blank", length(max = 100, message = "名称不能超过 100 个字符"))]
+    pub name: String,
+    pub channel_type: String,
+    #[validate(length(max = 500, message = "通知地址不能超过 500 个字符"))]
+    pub target: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AlertRule {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub vm_instance_id: Uuid,
+    pub name: String,
+    pub metric_type: String,
+    pub comparator: String,
+    pub threshold: Option<f64>,
+    pub duration_minutes: i32,
+    pub channel_ids: Vec<Uuid>,
+    pub enabled: bool,
+    pub state: String,
+    pub last_state_change: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl AlertRule {
+    /// 判断观测值是否满足告警条件
+    pub fn breaches(&self, value: f64) -> bool {
+        let threshold = match self.threshold {
+            Some(threshold) => threshold,
+            None => return false,
+        };
+        match self.comparator.as_str() {
+            "gt" => value > threshold,
+            "gte" => value >= threshold,
+            "lt" => value < threshold,
+            "lte" => value <= threshold,
+            _ => false,
+        }
+    }
+
+    /// 大于类比较取窗口内最小值、小于类比较取最大值，保证整个持续时间都越过阈值
+    pub fn uses_window_min(&self) -> bool {
+        matches!(self.comparator.as_str(), "gt" | "gte")
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct AlertRuleRequest {
+    pub vm_instance_id: Uuid,
+    #[validate(custom = "validate_not_blank", length(max = 100, message = "名称不能超过 100 个字符"))]
+    pub name: String,
+    pub metric_type: String,
+    pub comparator: Option<String>,
+    pub threshold: Option<f64>,
+    #[validate(range(min = 1, max = 1440, message = "持续时间必须在 1 到 1440 分钟之间"))]
+    pub duration_minutes: Option<i32>,
+    #[serde(default)]
+    pub channel_ids: Vec<Uuid>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AlertHistory {
+    pub id: Uuid,
+    pub rule_id: Uuid,
+    pub vm_instance_id: Uuid,
+    pub status: String,
+    pub value: Option<f64>,
+    pub message: String,
+    pub notified: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AlertHistoryQuery {
+    pub rule_id: Option<Uuid>,
+    pub limit: Option<i64>,
+}
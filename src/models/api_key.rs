@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+use crate::utils::validation::{validate_cidrs, validate_not_blank};
+
+/// API 密钥的授权范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ApiKeyScope {
+    /// 只读，可调用所有 GET 接口
+    #[serde(rename = "read")]
+    Read,
+    /// 查看实例并执行开关机、重启
+    #[serde(rename = "vm:power")]
+    VmPower,
+    /// 账单与支付相关接口
+    #[serde(rename = "billing")]
+    Billing,
+}
+
+impl ApiKeyScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiKeyScope::Read => "read",
+            ApiKeyScope::VmPower => "vm:power",
+            ApiKeyScope::Billing => "billing",
+        }
+    }
+
+    pub fn parse(scope: &str) -> Option<ApiKeyScope> {
+        match scope {
+            "read" => Some(ApiKeyScope::Read),
+            "vm:power" => Some(ApiKeyScope::VmPower),
+            "billing" => Some(ApiKeyScope::Billing),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ApiKeyInfo {
+    pub id: Uuid,
+    pub name: String,
+    pub key_prefix: String,
+    pub scopes: Vec<String>,
+    pub allowed_ips: Vec<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub last_used_ip: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+fn validate_expires_at(expires_at: &DateTime<Utc>) -> Result<(), ValidationError> {
+    if *expires_at > Utc::now() {
+        Ok(())
+    } else {
+        let mut error = ValidationError::new("expires_at");
+        error.message = Some("过期时间必须晚于当前时间".into());
+        Err(error)
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateApiKeyRequest {
+    #[validate(custom = "validate_not_blank", length(max = 100, message = "名称不能超过 100 个字符"))]
+    pub name: String,
+    #[validate(length(min = 1, message = "至少选择一个授权范围"))]
+    pub scopes: Vec<ApiKeyScope>,
+    /// IP 或 CIDR，为空表示不限制
+    #[serde(default)]
+    #[validate(custom = "validate_cidrs")]
+    pub allowed_ips: Vec<String>,
+    #[validate(custom = "validate_expires_at")]
+    pub expires_at: Option<DateTime<Utc>>,
+}
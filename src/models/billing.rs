@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// 查询时金额需转换为 FLOAT8
+pub const INVOICE_COLUMNS: &str = "id, user_id, organization_id, invoice_number, amount::FLOAT8 AS amount, status, \
+    payment_method, transaction_id, created_at, paid_at, due_date";
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Invoice {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub organization_id: Option<Uuid>,
+    pub invoice_number: String,
+    pub amount: f64,
+    pub status: String,
+    pub payment_method: Option<String>,
+    pub transaction_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub paid_at: Option<DateTime<Utc>>,
+    pub due_date: Option<DateTime<Utc>>,
+}
+
+pub const PAYMENT_COLUMNS: &str = "id, user_id, organization_id, invoice_id, amount::FLOAT8 AS amount, payment_method, \
+    transaction_id, status, created_at, completed_at, payment_account";
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct PaymentRecord {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub organization_id: Option<Uuid>,
+    pub invoice_id: Option<Uuid>,
+    pub amount: f64,
+    pub payment_method: String,
+    pub transaction_id: Option<String>,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub payment_account: Option<String>,
+}
@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::utils::validation::{validate_id_card, validate_not_blank};
+
+pub const IDENTITY_COLUMNS: &str = "id, user_id, real_name, id_card_masked, status, provider, provider_message, \
+    review_note, reviewed_at, created_at";
+
+/// 实名认证申请，列表中只返回脱敏后的证件号
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct IdentityVerification {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub real_name: String,
+    pub id_card_masked: String,
+    pub status: String,
+    pub provider: String,
+    pub provider_message: Option<String>,
+    pub review_note: Option<String>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct SubmitIdentityRequest {
+    #[validate(custom = "validate_not_blank", length(max = 50, message = "姓名不能超过 50 个字符"))]
+    pub real_name: String,
+    #[validate(custom = "validate_id_card")]
+    pub id_card: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IdentityQueueQuery {
+    /// 默认只列出待审核的申请
+    pub status: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ReviewIdentityRequest {
+    pub approved: bool,
+    #[validate(length(max = 500, message = "审核备注不能超过 500 个字符"))]
+    pub note: Option<String>,
+}
@@ -1,7 +1,12 @@
 pub mod user;
 pub mod pve_node;
 pub mod version;
-
-pub use user::{User, LoginRequest, UserSession, EmailVerification};
-pub use pve_node::{PveNode, PveNodeCreateRequest, PveNodeStatus};
-pub use version::Version;
+pub mod monitoring;
+pub mod alert;
+pub mod ticket;
+pub mod billing;
+pub mod webauthn;
+pub mod api_key;
+pub mod identity;
+pub mod vm_instance;
+pub mod organization;
@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Duration, Utc};
+
+// monitoring_data.metric_type 取值，与迁移中该列的 COMMENT 保持一致
+pub const METRIC_CPU: &str = "cpu";
+pub const METRIC_MEMORY: &str = "memory";
+pub const METRIC_DISK: &str = "disk";
+pub const METRIC_DISK_READ: &str = "disk_read";
+pub const METRIC_DISK_WRITE: &str = "disk_write";
+pub const METRIC_NET_IN: &str = "net_in";
+pub const METRIC_NET_OUT: &str = "net_out";
+
+pub const ALL_METRICS: [&str; 7] = [
+    METRIC_CPU,
+    METRIC_MEMORY,
+    METRIC_DISK,
+    METRIC_DISK_READ,
+    METRIC_DISK_WRITE,
+    METRIC_NET_IN,
+    METRIC_NET_OUT,
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatsRange {
+    #[default]
+    Hour,
+    Day,
+    Week,
+    Month,
+}
+
+impl StatsRange {
+    /// 查询时间窗口长度
+    pub fn window(&self) -> Duration {
+        match self {
+            StatsRange::Hour => Duration::hours(1),
+            StatsRange::Day => Duration::days(1),
+            StatsRange::Week => Duration::weeks(1),
+            StatsRange::Month => Duration::days(30),
+        }
+    }
+
+    /// 降采样桶宽（秒），每个时间窗口控制在 400 个点以内
+    pub fn bucket_seconds(&self) -> i64 {
+        match self {
+            StatsRange::Hour => 60,
+            StatsRange::Day => 5 * 60,
+            StatsRange::Week => 30 * 60,
+            StatsRange::Month => 2 * 60 * 60,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StatsQuery {
+    #[serde(default)]
+    pub range: StatsRange,
+    /// 逗号分隔的指标列表，如 "cpu,memory"，为空时返回全部指标
+    pub metrics: Option<String>,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct MetricBucket {
+    pub metric_type: String,
+    pub bucket: DateTime<Utc>,
+    pub avg_value: f64,
+    pub max_value: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MetricPoint {
+    pub timestamp: DateTime<Utc>,
+    pub avg: f64,
+    pub max: f64,
+}
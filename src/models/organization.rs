@@ -0,0 +1,188 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::utils::validation::validate_not_blank;
+
+/// 组织成员角色，与 organization_members.role 的取值一一对应
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OrgRole {
+    Owner,
+    Admin,
+    Operator,
+    Viewer,
+    Billing,
+}
+
+/// 成员在组织内的权限
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrgPermission {
+    ViewInstances,
+    /// 开关机、重启等日常运维
+    ManageInstances,
+    /// 把实例转给其他组织；转入个人账户另外要求所有者角色
+    TransferInstances,
+    ViewBilling,
+    ManageMembers,
+}
+
+const OWNER_PERMISSIONS: &[OrgPermission] = &[
+    OrgPermission::ViewInstances,
+    OrgPermission::ManageInstances,
+    OrgPermission::TransferInstances,
+    OrgPermission::ViewBilling,
+    OrgPermission::ManageMembers,
+];
+
+// 管理员与所有者的区别在于不能管理所有者和其他管理员
+const ADMIN_PERMISSIONS: &[OrgPermission] = OWNER_PERMISSIONS;
+
+const OPERATOR_PERMISSIONS: &[OrgPermission] = &[
+    OrgPermission::ViewInstances,
+    OrgPermission::ManageInstances,
+];
+
+const VIEWER_PERMISSIONS: &[OrgPermission] = &[OrgPermission::ViewInstances];
+
+// 财务只接触账单和余额，看不到实例
+const BILLING_PERMISSIONS: &[OrgPermission] = &[OrgPermission::ViewBilling];
+
+impl OrgRole {
+    pub const ALL: [OrgRole; 5] = [OrgRole::Owner, OrgRole::Admin, OrgRole::Operator, OrgRole::Viewer, OrgRole::Billing];
+
+    pub fn parse(role: &str) -> Option<OrgRole> {
+        match role {
+            "owner" => Some(OrgRole::Owner),
+            "admin" => Some(OrgRole::Admin),
+            "operator" => Some(OrgRole::Operator),
+            "viewer" => Some(OrgRole::Viewer),
+            "billing" => Some(OrgRole::Billing),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrgRole::Owner => "owner",
+            OrgRole::Admin => "admin",
+            OrgRole::Operator => "operator",
+            OrgRole::Viewer => "viewer",
+            OrgRole::Billing => "billing",
+        }
+    }
+
+    /// 邀请邮件中显示的角色名称
+    pub fn label(&self) -> &'static str {
+        match self {
+            OrgRole::Owner => "所有者",
+            OrgRole::Admin => "管理员",
+            OrgRole::Operator => "运维",
+            OrgRole::Viewer => "只读成员",
+            OrgRole::Billing => "财务",
+        }
+    }
+
+    pub fn permissions(&self) -> &'static [OrgPermission] {
+        match self {
+            OrgRole::Owner => OWNER_PERMISSIONS,
+            OrgRole::Admin => ADMIN_PERMISSIONS,
+            OrgRole::Operator => OPERATOR_PERMISSIONS,
+            OrgRole::Viewer => VIEWER_PERMISSIONS,
+            OrgRole::Billing => BILLING_PERMISSIONS,
+        }
+    }
+
+    pub fn can(&self, permission: OrgPermission) -> bool {
+        self.permissions().contains(&permission)
+    }
+
+    /// 拥有该权限的全部角色，用于在 SQL 中按角色筛选成员
+    pub fn with_permission(permission: OrgPermission) -> Vec<&'static str> {
+        OrgRole::ALL.iter().filter(|role| role.can(permission)).map(OrgRole::as_str).collect()
+    }
+
+    /// 能否邀请、调整或移除该角色的成员：所有者不受限，管理员只能管理运维、只读成员和财务
+    pub fn can_manage(&self, role: OrgRole) -> bool {
+        match self {
+            OrgRole::Owner => true,
+            OrgRole::Admin => matches!(role, OrgRole::Operator | OrgRole::Viewer | OrgRole::Billing),
+            _ => false,
+        }
+    }
+}
+
+// 余额只对有账务权限的成员返回，单独查询
+pub const ORGANIZATION_COLUMNS: &str = "id, name, created_by, created_at, updated_at";
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Organization {
+    pub id: Uuid,
+    pub name: String,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// 当前用户所在的组织及其角色
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Membership {
+    pub id: Uuid,
+    pub name: String,
+    pub role: String,
+    pub joined_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct OrganizationMember {
+    pub user_id: Uuid,
+    pub username: String,
+    pub email: Option<String>,
+    pub role: String,
+    pub joined_at: DateTime<Utc>,
+}
+
+pub const INVITATION_COLUMNS: &str = "id, organization_id, email, role, invited_by, expires_at, created_at";
+
+/// 待接受的邀请，不含令牌
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct OrganizationInvitation {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub email: String,
+    pub role: String,
+    pub invited_by: Option<Uuid>,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateOrganizationRequest {
+    #[validate(custom = "validate_not_blank", length(max = 100, message = "名称不能超过 100 个字符"))]
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct InviteMemberRequest {
+    #[validate(email(message = "邮箱格式不正确"))]
+    pub email: String,
+    pub role: OrgRole,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateMemberRequest {
+    pub role: OrgRole,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct AcceptInvitationRequest {
+    #[validate(length(min = 1, max = 128, message = "邀请链接无效"))]
+    pub token: String,
+}
+
+/// 转移实例归属，organization_id 为空表示转为操作人的个人实例
+#[derive(Debug, Deserialize)]
+pub struct TransferInstanceRequest {
+    pub organization_id: Option<Uuid>,
+}
@@ -2,6 +2,12 @@ use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
+// INET 列需转换为文本后再映射到 PveNode.ip_address
+pub const PVE_NODE_COLUMNS: &str = "id, name, hostname, host(ip_address) AS ip_address, port, username, \
+    password_encrypted, api_token, status, location, max_cpu, max_memory_gb, max_storage_gb, \
+    used_cpu, used_memory_gb, used_storage_gb, created_at, last_heartbeat, \
+    health_status, consecutive_failures, last_error";
+
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
 pub struct PveNode {
     pub id: Uuid,
@@ -10,7 +16,11 @@ pub struct PveNode {
     pub ip_address: String,
     pub port: i32,
     pub username: String,
+    // 目前节点访问统一使用 API 令牌，密码仅作登记
+    #[allow(dead_code)]
+    #[serde(skip_serializing)]
     pub password_encrypted: String,
+    #[serde(skip_serializing)]
     pub api_token: Option<String>,
     pub status: String,
     pub location: Option<String>,
@@ -22,8 +32,19 @@ pub struct PveNode {
     pub used_storage_gb: i32,
     pub created_at: DateTime<Utc>,
     pub last_heartbeat: Option<DateTime<Utc>>,
+    pub health_status: String,
+    pub consecutive_failures: i32,
+    pub last_error: Option<String>,
+}
+
+impl PveNode {
+    /// 调度器只会在启用且未被判定为不健康的节点上创建实例
+    pub fn is_schedulable(&self) -> bool {
+        self.status == "active" && self.health_status != "unhealthy"
+    }
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PveNodeCreateRequest {
     pub name: String,
@@ -46,3 +67,41 @@ pub struct PveNodeStatus {
     pub uptime: i64,
     pub load_average: [f64; 3],
 }
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct NodeCapacityReport {
+    pub id: Uuid,
+    pub pve_node_id: Uuid,
+    pub pve_cpu: i32,
+    pub pve_memory_gb: i32,
+    pub pve_storage_gb: i32,
+    pub db_cpu: i32,
+    pub db_memory_gb: i32,
+    pub db_storage_gb: i32,
+    pub storage_used_gb: i32,
+    pub storage_total_gb: i32,
+    pub untracked_vmids: Vec<i32>,
+    pub missing_vmids: Vec<i32>,
+    pub has_drift: bool,
+    pub overcommitted: bool,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 节点上单个虚拟机的资源分配（来自 /nodes/{node}/qemu）
+#[derive(Debug, Clone)]
+pub struct PveVmAllocation {
+    pub vmid: i32,
+    pub cpus: i32,
+    pub memory_bytes: u64,
+    pub disk_bytes: u64,
+    pub template: bool,
+}
+
+/// 节点存储使用情况（来自 /nodes/{node}/storage）
+#[derive(Debug, Clone)]
+pub struct PveStorageUsage {
+    pub shared: bool,
+    pub used_bytes: u64,
+    pub total_bytes: u64,
+}
@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Ticket {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub subject: String,
+    pub content: String,
+    pub status: String,
+    pub priority: String,
+    pub assigned_to: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct TicketMessage {
+    pub id: Uuid,
+    pub ticket_id: Uuid,
+    pub user_id: Uuid,
+    pub content: String,
+    pub is_staff: bool,
+    pub created_at: DateTime<Utc>,
+}
@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 use uuid::Uuid;
+use validator::Validate;
+
+use crate::utils::validation::{validate_avatar_url, validate_code, validate_password, validate_phone};
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
 pub struct User {
@@ -9,7 +12,6 @@ pub struct User {
     pub email: Option<String>,
     pub phone: Option<String>,
     pub password_hash: String,
-    pub salt: String,
     pub real_name: Option<String>,
     pub status: String,
     pub phone_verified: bool,
@@ -24,35 +26,264 @@ pub struct User {
     pub province: Option<String>,
     pub city: Option<String>,
     pub two_factor_enabled: bool,
+    /// 语言偏好，如 zh-CN、en，为空时按请求头选择
+    pub preferred_language: Option<String>,
+    /// 实名认证通过时间
+    pub id_verified_at: Option<DateTime<Utc>>,
+    /// 自助注销时间
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Validate)]
 pub struct LoginRequest {
+    #[validate(length(min = 1, max = 255, message = "请输入用户名、邮箱或手机号"))]
     pub username: String,
+    // 已有账户的密码可能不满足新策略，登录时只限制长度
+    #[validate(length(min = 1, max = 128, message = "请输入密码"))]
     pub password: String,
+    /// 多次失败后需要提交的人机验证令牌
+    pub captcha_token: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
 pub struct UserSession {
     pub id: Uuid,
     pub user_id: Uuid,
     pub token_hash: String,
+    pub family_id: Uuid,
     pub expires_at: DateTime<Utc>,
+    pub rotated_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
 }
 
+/// 会话列表中的一项，一个 family 对应一台登录设备
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
-pub struct EmailVerification {
+pub struct SessionInfo {
     pub id: Uuid,
-    pub email: String,
-    pub code: String,
-    pub purpose: String,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub signed_in_at: Option<DateTime<Utc>>,
+    pub last_active_at: Option<DateTime<Utc>>,
     pub expires_at: DateTime<Utc>,
-    pub used: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+/// 邮箱验证码的用途，与 email_verifications.purpose 对应
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerificationPurpose {
+    Register,
+    ResetPassword,
+    /// 更换绑定邮箱，验证码发往新邮箱，只能通过个人资料接口申请
+    ChangeEmail,
+}
+
+impl VerificationPurpose {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VerificationPurpose::Register => "register",
+            VerificationPurpose::ResetPassword => "reset_password",
+            VerificationPurpose::ChangeEmail => "change_email",
+        }
+    }
+}
+
+/// 短信验证码的用途，与 phone_verifications.purpose 对应
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PhoneCodePurpose {
+    Register,
+    Login,
+    ResetPassword,
+    /// 更换绑定手机号，验证码发往新号码，只能通过个人资料接口申请
+    ChangePhone,
+}
+
+impl PhoneCodePurpose {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PhoneCodePurpose::Register => "register",
+            PhoneCodePurpose::Login => "login",
+            PhoneCodePurpose::ResetPassword => "reset_password",
+            PhoneCodePurpose::ChangePhone => "change_phone",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct SendEmailCodeRequest {
+    #[validate(email(message = "邮箱格式不正确"))]
+    pub email: String,
+    pub purpose: VerificationPurpose,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct VerifyEmailRequest {
+    #[validate(email(message = "邮箱格式不正确"))]
+    pub email: String,
+    #[validate(custom = "validate_code")]
+    pub code: String,
+    pub purpose: VerificationPurpose,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct SendPhoneCodeRequest {
+    #[validate(custom = "validate_phone")]
+    pub phone: String,
+    pub purpose: PhoneCodePurpose,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct VerifyPhoneRequest {
+    #[validate(custom = "validate_phone")]
+    pub phone: String,
+    #[validate(custom = "validate_code")]
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct PhoneLoginRequest {
+    #[validate(custom = "validate_phone")]
+    pub phone: String,
+    #[validate(custom = "validate_code")]
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct PhoneResetPasswordRequest {
+    #[validate(custom = "validate_phone")]
+    pub phone: String,
+    #[validate(custom = "validate_code")]
+    pub code: String,
+    #[validate(custom = "validate_password")]
+    pub new_password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
 pub struct ResetPasswordRequest {
+    #[validate(email(message = "邮箱格式不正确"))]
     pub email: String,
+    #[validate(custom = "validate_code")]
     pub code: String,
+    #[validate(custom = "validate_password")]
     pub new_password: String,
 }
+
+/// 员工查看用户资料时返回的字段，不含密码和证件号
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UserSummary {
+    pub id: Uuid,
+    pub username: String,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub real_name: Option<String>,
+    pub status: String,
+    pub role: String,
+    pub balance: f64,
+    pub created_at: DateTime<Utc>,
+    pub last_login: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct TwoFactorCodeRequest {
+    #[validate(custom = "validate_code")]
+    pub code: String,
+}
+
+/// 登录第二步，验证码和恢复码二选一
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TwoFactorLoginRequest {
+    pub challenge_token: String,
+    pub code: Option<String>,
+    pub recovery_code: Option<String>,
+}
+
+/// 设置语言偏好，为空表示跟随请求头
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateLanguageRequest {
+    pub language: Option<String>,
+}
+
+/// 个人资料接口返回的字段
+pub const PROFILE_COLUMNS: &str = "id, username, email, phone, real_name, avatar_url, province, city, \
+    email_verified, phone_verified, two_factor_enabled, preferred_language, id_verified_at, created_at";
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UserProfile {
+    pub id: Uuid,
+    pub username: String,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub real_name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub province: Option<String>,
+    pub city: Option<String>,
+    pub email_verified: bool,
+    pub phone_verified: bool,
+    pub two_factor_enabled: bool,
+    pub preferred_language: Option<String>,
+    pub id_verified_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 修改个人资料，未提供的字段保持不变，空字符串表示清空
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct UpdateProfileRequest {
+    #[validate(length(max = 100, message = "姓名不能超过 100 个字符"))]
+    pub real_name: Option<String>,
+    #[validate(length(max = 500, message = "头像地址不能超过 500 个字符"), custom = "validate_avatar_url")]
+    pub avatar_url: Option<String>,
+    #[validate(length(max = 50, message = "省份不能超过 50 个字符"))]
+    pub province: Option<String>,
+    #[validate(length(max = 50, message = "城市不能超过 50 个字符"))]
+    pub city: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct ChangePasswordRequest {
+    #[validate(length(min = 1, max = 128, message = "请输入当前密码"))]
+    pub current_password: String,
+    #[validate(custom = "validate_password")]
+    pub new_password: String,
+}
+
+/// 更换邮箱第一步，向新邮箱发送验证码
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct ChangeEmailRequest {
+    #[validate(email(message = "邮箱格式不正确"))]
+    pub email: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct ConfirmEmailChangeRequest {
+    #[validate(email(message = "邮箱格式不正确"))]
+    pub email: String,
+    #[validate(custom = "validate_code")]
+    pub code: String,
+}
+
+/// 更换手机号第一步，向新号码发送验证码
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct ChangePhoneRequest {
+    #[validate(custom = "validate_phone")]
+    pub phone: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct ConfirmPhoneChangeRequest {
+    #[validate(custom = "validate_phone")]
+    pub phone: String,
+    #[validate(custom = "validate_code")]
+    pub code: String,
+}
+
+/// 注销账户需再次输入密码确认
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct DeleteAccountRequest {
+    #[validate(length(min = 1, max = 128, message = "请输入当前密码"))]
+    pub password: String,
+}
@@ -1,5 +1,6 @@
 use serde::{Serialize, Deserialize};
 
+#[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Version {
     pub version: String,
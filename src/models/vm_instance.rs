@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+// INET 列转换为文本；root_password 不对外返回
+pub const VM_INSTANCE_COLUMNS: &str = "id, user_id, organization_id, plan_id, pve_node_id, name, pve_vmid, status, \
+    os_template, cpu_cores, memory_gb, storage_gb, host(ip_address) AS ip_address, host(ipv6_address) AS ipv6_address, \
+    billing_type, expires_at, created_at, auto_renew";
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct VmInstance {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    /// 为空时是 user_id 的个人实例
+    pub organization_id: Option<Uuid>,
+    pub plan_id: Uuid,
+    pub pve_node_id: Uuid,
+    pub name: String,
+    pub pve_vmid: i32,
+    pub status: String,
+    pub os_template: Option<String>,
+    pub cpu_cores: i32,
+    pub memory_gb: i32,
+    pub storage_gb: i32,
+    pub ip_address: Option<String>,
+    pub ipv6_address: Option<String>,
+    pub billing_type: String,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub auto_renew: bool,
+}
@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use validator::Validate;
+use webauthn_rs::prelude::{PublicKeyCredential, RegisterPublicKeyCredential};
+
+use crate::utils::validation::validate_not_blank;
+
+/// 已注册的验证器，不含公钥等内部数据
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct PasskeyInfo {
+    pub id: Uuid,
+    pub name: String,
+    pub sign_count: i64,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct FinishPasskeyRegistrationRequest {
+    pub ceremony_id: Uuid,
+    #[validate(custom = "validate_not_blank", length(max = 100, message = "名称不能超过 100 个字符"))]
+    pub name: String,
+    pub credential: RegisterPublicKeyCredential,
+}
+
+/// 免密码登录传 username；作为登录第二步时传密码登录返回的 challenge_token
+#[derive(Debug, Deserialize)]
+pub struct StartPasskeyLoginRequest {
+    pub username: Option<String>,
+    pub challenge_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FinishPasskeyLoginRequest {
+    pub ceremony_id: Uuid,
+    pub credential: PublicKeyCredential,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct RenamePasskeyRequest {
+    #[validate(custom = "validate_not_blank", length(max = 100, message = "名称不能超过 100 个字符"))]
+    pub name: String,
+}
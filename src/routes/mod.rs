@@ -1,10 +1,13 @@
-use actix_web::http::StatusCode;
+use actix_web::middleware::from_fn;
 use actix_web::web;
-use actix_web::HttpResponse;
 use crate::handlers;
 use crate::middleware;
+use crate::utils::error::AppError;
 
 pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.route("/metrics", web::get().to(handlers::metrics::export));
+    cfg.route("/.well-known/jwks.json", web::get().to(handlers::auth::jwks));
+
     cfg.service(
         web::scope("/api")
             .route("/health", web::post().to(handlers::health_check))
@@ -13,13 +16,163 @@ pub fn config(cfg: &mut web::ServiceConfig) {
                 web::scope("/auth")
                     .route("/register", web::post().to(handlers::auth::register))
                     .route("/login", web::post().to(handlers::auth::login))
-                    .route("/logout", web::post().to(handlers::auth::logout))
+                    .route("/login/phone", web::post().to(handlers::auth::login_phone))
+                    .route("/login/2fa", web::post().to(handlers::auth::login_two_factor))
+                    .route("/login/passkey/start", web::post().to(handlers::auth::start_passkey_login))
+                    .route("/login/passkey/finish", web::post().to(handlers::auth::finish_passkey_login))
+                    .service(
+                        web::resource("/logout")
+                            .wrap(actix_web_httpauth::middleware::HttpAuthentication::bearer(
+                                middleware::jwt_validator,
+                            ))
+                            .route(web::post().to(handlers::auth::logout))
+                    )
                     .route("/refresh", web::post().to(handlers::auth::refresh))
+                    .route("/email/send-code", web::post().to(handlers::auth::send_email_code))
+                    .route("/phone/send-code", web::post().to(handlers::auth::send_phone_code))
+                    .route("/phone/verify", web::post().to(handlers::auth::verify_phone))
+                    .route("/phone/reset-password", web::post().to(handlers::auth::reset_password_by_phone))
                     .route("/verify", web::post().to(handlers::auth::verify))
                     .route("/reset-password", web::post().to(handlers::auth::reset_password))
+                    .service(
+                        web::scope("/sessions")
+                            .wrap(actix_web_httpauth::middleware::HttpAuthentication::bearer(
+                                middleware::jwt_validator,
+                            ))
+                            .route("", web::get().to(handlers::auth::list_sessions))
+                            .route("/revoke-others", web::post().to(handlers::auth::revoke_other_sessions))
+                            .route("/{id}", web::delete().to(handlers::auth::revoke_session))
+                    )
+                    .service(
+                        web::scope("/2fa")
+                            .wrap(actix_web_httpauth::middleware::HttpAuthentication::bearer(
+                                middleware::jwt_validator,
+                            ))
+                            .route("", web::get().to(handlers::two_factor::status))
+                            .route("/enroll", web::post().to(handlers::two_factor::enroll))
+                            .route("/confirm", web::post().to(handlers::two_factor::confirm))
+                    )
+                    .service(
+                        web::scope("/passkeys")
+                            .wrap(actix_web_httpauth::middleware::HttpAuthentication::bearer(
+                                middleware::jwt_validator,
+                            ))
+                            .route("", web::get().to(handlers::passkey::list))
+                            .route("/register/start", web::post().to(handlers::passkey::start_registration))
+                            .route("/register/finish", web::post().to(handlers::passkey::finish_registration))
+                            .route("/{id}", web::put().to(handlers::passkey::rename))
+                            .route("/{id}", web::delete().to(handlers::passkey::revoke))
+                    )
+                    .service(
+                        web::scope("/api-keys")
+                            .wrap(actix_web_httpauth::middleware::HttpAuthentication::bearer(
+                                middleware::jwt_validator,
+                            ))
+                            .route("", web::get().to(handlers::api_key::list))
+                            .route("", web::post().to(handlers::api_key::create))
+                            .route("/{id}", web::delete().to(handlers::api_key::revoke))
+                    )
+            )
+            .service(
+                web::scope("/user")
+                    .wrap(actix_web_httpauth::middleware::HttpAuthentication::bearer(
+                        middleware::jwt_validator,
+                    ))
+                    .route("/profile", web::get().to(handlers::user::get_profile))
+                    .route("/profile", web::put().to(handlers::user::update_profile))
+                    .route("/avatar", web::post().to(handlers::user::upload_avatar))
+                    .route("/password", web::put().to(handlers::user::change_password))
+                    .route("/email", web::post().to(handlers::user::request_email_change))
+                    .route("/email/confirm", web::post().to(handlers::user::confirm_email_change))
+                    .route("/phone", web::post().to(handlers::user::request_phone_change))
+                    .route("/phone/confirm", web::post().to(handlers::user::confirm_phone_change))
+                    .route("/export", web::get().to(handlers::user::export_data))
+                    .route("/account", web::delete().to(handlers::user::delete_account))
+                    .route("/language", web::put().to(handlers::user::update_language))
+                    .route("/identity", web::get().to(handlers::identity::status))
+                    .route("/identity", web::post().to(handlers::identity::submit))
             )
             .route("/version", web::get().to(handlers::get_version))
             .route("/nodes", web::get().to(handlers::get_nodes))
+            .service(
+                web::scope("/vm")
+                    .wrap(from_fn(middleware::identity::require_identity_for_instances))
+                    .wrap(actix_web_httpauth::middleware::HttpAuthentication::bearer(
+                        middleware::jwt_validator,
+                    ))
+                    .route("/instances/{id}/stats", web::get().to(handlers::vm::get_instance_stats))
+                    .route("/instances/{id}/transfer", web::post().to(handlers::vm::transfer_instance))
+            )
+            .service(
+                web::scope("/organizations")
+                    .wrap(actix_web_httpauth::middleware::HttpAuthentication::bearer(
+                        middleware::jwt_validator,
+                    ))
+                    .route("", web::get().to(handlers::organization::list))
+                    .route("", web::post().to(handlers::organization::create))
+                    .route("/invitations/accept", web::post().to(handlers::organization::accept_invitation))
+                    .route("/{id}", web::get().to(handlers::organization::get))
+                    .route("/{id}/members", web::get().to(handlers::organization::list_members))
+                    .route("/{id}/members/{user_id}", web::put().to(handlers::organization::update_member))
+                    .route("/{id}/members/{user_id}", web::delete().to(handlers::organization::remove_member))
+                    .route("/{id}/invitations", web::get().to(handlers::organization::list_invitations))
+                    .route("/{id}/invitations", web::post().to(handlers::organization::invite))
+                    .route("/{id}/invitations/{invitation_id}", web::delete().to(handlers::organization::revoke_invitation))
+                    .route("/{id}/instances", web::get().to(handlers::organization::list_instances))
+                    .route("/{id}/invoices", web::get().to(handlers::organization::list_invoices))
+            )
+            .service(
+                web::scope("/alerts")
+                    .wrap(actix_web_httpauth::middleware::HttpAuthentication::bearer(
+                        middleware::jwt_validator,
+                    ))
+                    .route("/channels", web::get().to(handlers::alert::list_channels))
+                    .route("/channels", web::post().to(handlers::alert::create_channel))
+                    .route("/channels/{id}", web::delete().to(handlers::alert::delete_channel))
+                    .route("/rules", web::get().to(handlers::alert::list_rules))
+                    .route("/rules", web::post().to(handlers::alert::create_rule))
+                    .route("/rules/{id}", web::put().to(handlers::alert::update_rule))
+                    .route("/rules/{id}", web::delete().to(handlers::alert::delete_rule))
+                    .route("/history", web::get().to(handlers::alert::list_history))
+            )
+            .service(
+                web::scope("/tickets")
+                    .wrap(actix_web_httpauth::middleware::HttpAuthentication::bearer(
+                        middleware::jwt_validator,
+                    ))
+                    .route("/{id}", web::get().to(handlers::ticket::get_ticket))
+            )
+            .service(
+                web::scope("/billing")
+                    .wrap(actix_web_httpauth::middleware::HttpAuthentication::bearer(
+                        middleware::jwt_validator,
+                    ))
+                    .route("/invoices/{id}", web::get().to(handlers::billing::get_invoice))
+            )
+            // 角色守卫依赖令牌声明，需注册在 jwt_validator 之前（后注册的先执行）
+            .service(
+                web::scope("/staff")
+                    .wrap(from_fn(middleware::rbac::require_staff))
+                    .wrap(actix_web_httpauth::middleware::HttpAuthentication::bearer(
+                        middleware::jwt_validator,
+                    ))
+                    .route("/users/{id}", web::get().to(handlers::staff::get_user))
+            )
+            .service(
+                web::scope("/admin")
+                    .wrap(from_fn(middleware::rbac::require_admin))
+                    .wrap(actix_web_httpauth::middleware::HttpAuthentication::bearer(
+                        middleware::jwt_validator,
+                    ))
+                    .route("/nodes/health", web::get().to(handlers::admin::node_health))
+                    .route("/nodes/capacity", web::get().to(handlers::admin::capacity_report))
+                    .route("/nodes/capacity/sync", web::post().to(handlers::admin::sync_capacity))
+                    .route("/users/{id}/role", web::put().to(handlers::admin::change_user_role))
+                    .route("/users/{id}/2fa/reset", web::post().to(handlers::admin::reset_two_factor))
+                    .route("/identity-verifications", web::get().to(handlers::admin::list_identity_verifications))
+                    .route("/identity-verifications/{id}", web::get().to(handlers::admin::get_identity_verification))
+                    .route("/identity-verifications/{id}/review", web::post().to(handlers::admin::review_identity_verification))
+            )
             .service(
                 web::scope("/protected")
                     .wrap(actix_web_httpauth::middleware::HttpAuthentication::bearer(
@@ -28,11 +181,7 @@ pub fn config(cfg: &mut web::ServiceConfig) {
             )
             // 404 处理
             .default_service(web::route().to(|| async {
-                HttpResponse::NotFound()
-                    .json(serde_json::json!({
-                        "code": StatusCode::NOT_FOUND.as_u16(),
-                        "message": "Resource not found"
-                    }))
+                Err::<actix_web::HttpResponse, _>(AppError::not_found("接口不存在"))
             }))
     );
 }
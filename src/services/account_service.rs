@@ -0,0 +1,179 @@
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::services::{organization_service, two_factor_service};
+
+/// 注销前需要先处理的事项
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DeletionBlocker {
+    /// 员工账号需由管理员处理，不能自助注销
+    StaffAccount,
+    ActiveInstances(i64),
+    UnpaidInvoices(i64),
+    NonZeroBalance(f64),
+    /// 唯一所有者退出后组织将无人管理
+    SoleOrganizationOwner(i64),
+}
+
+impl DeletionBlocker {
+    pub fn message(&self) -> String {
+        match self {
+            DeletionBlocker::StaffAccount => "员工账号不能自助注销".to_string(),
+            DeletionBlocker::ActiveInstances(count) => format!("还有 {} 台云主机未删除，请先删除全部云主机", count),
+            DeletionBlocker::UnpaidInvoices(count) => format!("还有 {} 张账单未支付，请先处理", count),
+            DeletionBlocker::NonZeroBalance(balance) => format!("账户余额为 {:.2} 元，需为零才能注销", balance),
+            DeletionBlocker::SoleOrganizationOwner(count) => format!("您是 {} 个组织的唯一所有者，请先转让所有权", count),
+        }
+    }
+}
+
+/// 判断能否注销所需的账户现状
+struct AccountStanding {
+    role: String,
+    balance: f64,
+    instances: i64,
+    unpaid: i64,
+    sole_owned: i64,
+}
+
+impl AccountStanding {
+    /// 按处理顺序返回第一个未满足的条件
+    fn blocker(&self) -> Option<DeletionBlocker> {
+        if self.role != "user" {
+            Some(DeletionBlocker::StaffAccount)
+        } else if self.instances > 0 {
+            Some(DeletionBlocker::ActiveInstances(self.instances))
+        } else if self.unpaid > 0 {
+            Some(DeletionBlocker::UnpaidInvoices(self.unpaid))
+        } else if self.balance != 0.0 {
+            Some(DeletionBlocker::NonZeroBalance(self.balance))
+        } else if self.sole_owned > 0 {
+            Some(DeletionBlocker::SoleOrganizationOwner(self.sole_owned))
+        } else {
+            None
+        }
+    }
+}
+
+/// 锁定用户行并检查能否注销，需在注销的同一事务中调用，避免检查后又产生余额或实例。
+/// 组织名下的实例和账单归组织所有，不影响个人注销
+pub async fn deletion_blocker(conn: &mut PgConnection, user_id: Uuid) -> Result<Option<DeletionBlocker>, sqlx::Error> {
+    let (role, balance, instances, unpaid) = sqlx::query_as::<_, (String, f64, i64, i64)>(
+        r#"
+        SELECT role,
+               COALESCE(balance, 0)::FLOAT8,
+               (SELECT COUNT(*) FROM vm_instances WHERE user_id = $1 AND organization_id IS NULL AND status <> 'deleted'),
+               (SELECT COUNT(*) FROM invoices WHERE user_id = $1 AND organization_id IS NULL AND status = 'pending')
+        FROM users WHERE id = $1
+        FOR UPDATE
+        "#
+    )
+    .bind(user_id)
+    .fetch_one(&mut *conn)
+    .await?;
+    let sole_owned = organization_service::sole_owned_count(&mut *conn, user_id).await?;
+
+    Ok(AccountStanding { role, balance, instances, unpaid, sole_owned }.blocker())
+}
+
+/// 清除个人信息并把账户标记为已注销。
+/// 账户行本身保留，账单、支付记录和已删除的实例仍关联到该 ID，按财务法规留存；
+/// 登录凭据、会话、通知渠道、实名资料以及关联邮箱和手机号的验证码、组织邀请全部删除，
+/// 用户名改为占位值以释放原用户名、邮箱和手机号
+pub async fn anonymize(conn: &mut PgConnection, user_id: Uuid) -> Result<(), sqlx::Error> {
+    two_factor_service::reset(&mut *conn, user_id).await?;
+
+    for statement in [
+        "DELETE FROM user_sessions WHERE user_id = $1",
+        "DELETE FROM api_keys WHERE user_id = $1",
+        "DELETE FROM webauthn_credentials WHERE user_id = $1",
+        "DELETE FROM webauthn_ceremonies WHERE user_id = $1",
+        "DELETE FROM alert_rules WHERE user_id = $1",
+        "DELETE FROM notification_channels WHERE user_id = $1",
+        "DELETE FROM identity_verifications WHERE user_id = $1",
+        "DELETE FROM organization_members WHERE user_id = $1",
+        // 验证码和邀请记录按邮箱、手机号关联，需在清空用户资料之前删除
+        "DELETE FROM email_verifications WHERE LOWER(email) = (SELECT LOWER(email) FROM users WHERE id = $1)",
+        "DELETE FROM phone_verifications WHERE phone = (SELECT phone FROM users WHERE id = $1)",
+        "DELETE FROM organization_invitations WHERE accepted_by = $1 OR LOWER(email) = (SELECT LOWER(email) FROM users WHERE id = $1)",
+        "DELETE FROM login_throttles WHERE scope = 'account' AND key = $1::TEXT",
+    ] {
+        sqlx::query(statement).bind(user_id).execute(&mut *conn).await?;
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE users SET
+            username = 'deleted_' || REPLACE(id::TEXT, '-', ''),
+            email = NULL,
+            phone = NULL,
+            password_hash = '!',
+            real_name = NULL,
+            id_card = NULL,
+            id_verified_at = NULL,
+            avatar_url = NULL,
+            province = NULL,
+            city = NULL,
+            last_login_ip = NULL,
+            preferred_language = NULL,
+            email_verified = FALSE,
+            phone_verified = FALSE,
+            status = 'deleted',
+            deleted_at = NOW(),
+            updated_at = NOW()
+        WHERE id = $1
+        "#
+    )
+    .bind(user_id)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clean() -> AccountStanding {
+        AccountStanding { role: "user".into(), balance: 0.0, instances: 0, unpaid: 0, sole_owned: 0 }
+    }
+
+    #[test]
+    fn clean_account_may_be_deleted() {
+        assert_eq!(clean().blocker(), None);
+    }
+
+    #[test]
+    fn staff_accounts_cannot_self_delete() {
+        for role in ["admin", "support"] {
+            let standing = AccountStanding { role: role.into(), ..clean() };
+            assert_eq!(standing.blocker(), Some(DeletionBlocker::StaffAccount));
+        }
+    }
+
+    #[test]
+    fn each_outstanding_item_blocks_deletion() {
+        assert_eq!(AccountStanding { instances: 2, ..clean() }.blocker(), Some(DeletionBlocker::ActiveInstances(2)));
+        assert_eq!(AccountStanding { unpaid: 1, ..clean() }.blocker(), Some(DeletionBlocker::UnpaidInvoices(1)));
+        assert_eq!(AccountStanding { balance: 12.5, ..clean() }.blocker(), Some(DeletionBlocker::NonZeroBalance(12.5)));
+        assert_eq!(AccountStanding { balance: -3.0, ..clean() }.blocker(), Some(DeletionBlocker::NonZeroBalance(-3.0)));
+        assert_eq!(
+            AccountStanding { sole_owned: 1, ..clean() }.blocker(),
+            Some(DeletionBlocker::SoleOrganizationOwner(1))
+        );
+    }
+
+    #[test]
+    fn blockers_are_reported_in_handling_order() {
+        let standing = AccountStanding { instances: 1, unpaid: 1, balance: 5.0, sole_owned: 1, ..clean() };
+        assert_eq!(standing.blocker(), Some(DeletionBlocker::ActiveInstances(1)));
+        let standing = AccountStanding { unpaid: 1, balance: 5.0, sole_owned: 1, ..clean() };
+        assert_eq!(standing.blocker(), Some(DeletionBlocker::UnpaidInvoices(1)));
+    }
+
+    #[test]
+    fn blocker_messages_include_counts() {
+        assert_eq!(DeletionBlocker::NonZeroBalance(12.5).message(), "账户余额为 12.50 元，需为零才能注销");
+        assert_eq!(DeletionBlocker::ActiveInstances(2).message(), "还有 2 台云主机未删除，请先删除全部云主机");
+    }
+}
@@ -0,0 +1,346 @@
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration as StdDuration;
+
+use chrono::{Duration, Utc};
+use log::{error, info, warn};
+use reqwest::redirect::Policy;
+use reqwest::{Client as ReqwestClient, Url};
+use serde_json::json;
+
+use crate::database::DbPool;
+use crate::models::alert::{AlertRule, NotificationChannel, CHANNEL_EMAIL, CHANNEL_WEBHOOK, RULE_INSTANCE_DOWN};
+use crate::models::monitoring::METRIC_CPU;
+use crate::services::email_service::EmailService;
+use crate::utils::metrics::{JOB_LAST_RUN, JOB_QUEUE_DEPTH};
+
+const JOB_NAME: &str = "alert_evaluator";
+const EVALUATION_INTERVAL: StdDuration = StdDuration::from_secs(60);
+
+struct Evaluation {
+    firing: bool,
+    value: Option<f64>,
+}
+
+/// 只允许公网地址，防止通过 webhook 访问本机、内网、PVE 或云厂商元数据服务
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            !(v4.is_loopback()
+                || v4.is_private()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_documentation()
+                || v4.is_multicast()
+                || a == 0
+                // 100.64.0.0/10 运营商级 NAT
+                || (a == 100 && (64..128).contains(&b)))
+        }
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(v4));
+            }
+            let first = v6.segments()[0];
+            !(v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                // fc00::/7 唯一本地地址、fe80::/10 链路本地地址
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+/// 解析 webhook 地址并确认全部解析结果都是公网地址，返回解析结果供发送时固定使用，
+/// 避免校验后 DNS 记录被改为内网地址
+pub async fn resolve_webhook_target(target: &str) -> Result<(Url, Vec<SocketAddr>), String> {
+    let url = Url::parse(target).map_err(|e| e.to_string())?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("不支持的协议 {}", url.scheme()));
+    }
+    let host = url.host_str().ok_or("缺少主机名")?;
+    let port = url.port_or_known_default().ok_or("缺少端口")?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|e| e.to_string())?
+        .collect();
+    if addrs.is_empty() {
+        return Err(format!("无法解析 {}", host));
+    }
+    if let Some(addr) = addrs.iter().find(|addr| !is_public_ip(addr.ip())) {
+        return Err(format!("{} 不是公网地址", addr.ip()));
+    }
+    Ok((url, addrs))
+}
+
+struct AlertNotifier {
+    email: Option<EmailService>,
+}
+
+impl AlertNotifier {
+    fn new() -> Self {
+        let email = match EmailService::from_env() {
+            Ok(service) => Some(service),
+            Err(e) => {
+                warn!("邮件告警渠道不可用: {}", e);
+                None
+            }
+        };
+        AlertNotifier { email }
+    }
+
+    async fn notify(&self, channel: &NotificationChannel, subject: &str, payload: &serde_json::Value) -> Result<(), String> {
+        match channel.channel_type.as_str() {
+            CHANNEL_EMAIL => {
+                let email = self.email.as_ref().ok_or("邮件服务未配置")?;
+                let body = payload["message"].as_str().unwrap_or(subject);
+                email.send(&channel.target, subject, body).await.map_err(|e| e.to_string())
+            }
+            CHANNEL_WEBHOOK => {
+                // 每次发送都重新校验，并把域名固定到校验过的地址、不跟随重定向
+                let (url, addrs) = resolve_webhook_target(&channel.target).await?;
+                let mut builder = ReqwestClient::builder()
+                    .timeout(StdDuration::from_secs(10))
+                    .redirect(Policy::none());
+                if let Some(domain) = url.domain() {
+                    builder = builder.resolve_to_addrs(domain, &addrs);
+                }
+                let http = builder.build().map_err(|e| e.to_string())?;
+                let resp = http
+                    .post(url)
+                    .json(payload)
+                    .send()
+                    .await
+                    .map_err(|e| e.to_string())?;
+                if resp.status().is_success() {
+                    Ok(())
+                } else {
+                    Err(resp.status().to_string())
+                }
+            }
+            other => Err(format!("未知通知渠道类型: {}", other)),
+        }
+    }
+}
+
+/// 启动告警规则评估后台任务
+pub fn spawn_alert_evaluator(pool: DbPool) {
+    tokio::spawn(async move {
+        let notifier = AlertNotifier::new();
+        let mut ticker = tokio::time::interval(EVALUATION_INTERVAL);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = evaluate_all(&pool, &notifier).await {
+                error!("告警规则评估失败: {}", e);
+            }
+        }
+    });
+}
+
+async fn evaluate_all(pool: &DbPool, notifier: &AlertNotifier) -> Result<(), sqlx::Error> {
+    let rules = sqlx::query_as::<_, AlertRule>("SELECT * FROM alert_rules WHERE enabled = true")
+        .fetch_all(pool)
+        .await?;
+
+    let queue_depth = JOB_QUEUE_DEPTH.with_label_values(&[JOB_NAME]);
+    queue_depth.set(rules.len() as i64);
+    for rule in rules {
+        if let Err(e) = evaluate_rule(pool, notifier, &rule).await {
+            error!("告警规则 {} 评估失败: {}", rule.id, e);
+        }
+        queue_depth.dec();
+    }
+    JOB_LAST_RUN.with_label_values(&[JOB_NAME]).set(Utc::now().timestamp());
+    Ok(())
+}
+
+async fn evaluate_rule(pool: &DbPool, notifier: &AlertNotifier, rule: &AlertRule) -> Result<(), sqlx::Error> {
+    let since = Utc::now() - Duration::minutes(rule.duration_minutes as i64);
+
+    let evaluation = if rule.metric_type == RULE_INSTANCE_DOWN {
+        // 运行中的实例在整个持续时间内没有上报任何数据即视为宕机
+        let (running, samples): (bool, i64) = sqlx::query_as(
+            r#"
+            SELECT vm.status = 'running',
+                   (SELECT COUNT(*) FROM monitoring_data md
+                    WHERE md.vm_instance_id = vm.id AND md.metric_type = $2 AND md.timestamp >= $3)
+            FROM vm_instances vm WHERE vm.id = $1
+            "#
+        )
+        .bind(rule.vm_instance_id)
+        .bind(METRIC_CPU)
+        .bind(since)
+        .fetch_one(pool)
+        .await?;
+        Evaluation { firing: running && samples == 0, value: None }
+    } else {
+        let (samples, min_value, max_value): (i64, Option<f64>, Option<f64>) = sqlx::query_as(
+            r#"
+            SELECT COUNT(*), MIN(value)::FLOAT8, MAX(value)::FLOAT8
+            FROM monitoring_data
+            WHERE vm_instance_id = $1 AND metric_type = $2 AND timestamp >= $3
+            "#
+        )
+        .bind(rule.vm_instance_id)
+        .bind(&rule.metric_type)
+        .bind(since)
+        .fetch_one(pool)
+        .await?;
+        // 没有数据时保持当前状态，由 instance_down 规则负责宕机告警
+        if samples == 0 {
+            return Ok(());
+        }
+        let value = if rule.uses_window_min() { min_value } else { max_value };
+        Evaluation {
+            firing: value.map(|v| rule.breaches(v)).unwrap_or(false),
+            value,
+        }
+    };
+
+    // 只在状态切换时通知，持续告警期间不重复发送
+    let status = match (rule.state.as_str(), evaluation.firing) {
+        ("ok", true) => "firing",
+        ("firing", false) => "resolved",
+        _ => return Ok(()),
+    };
+    let new_state = if evaluation.firing { "firing" } else { "ok" };
+
+    sqlx::query("UPDATE alert_rules SET state = $1, last_state_change = NOW() WHERE id = $2")
+        .bind(new_state)
+        .bind(rule.id)
+        .execute(pool)
+        .await?;
+
+    let message = describe(rule, status, evaluation.value);
+    info!("告警规则 {} 状态变更为 {}: {}", rule.id, status, message);
+
+    let channels = sqlx::query_as::<_, NotificationChannel>(
+        "SELECT * FROM notification_channels WHERE id = ANY($1) AND user_id = $2 AND enabled = true"
+    )
+    .bind(&rule.channel_ids)
+    .bind(rule.user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let subject = if status == "firing" {
+        format!("[告警] {}", rule.name)
+    } else {
+        format!("[恢复] {}", rule.name)
+    };
+    let payload = json!({
+        "rule_id": rule.id,
+        "rule_name": rule.name,
+        "vm_instance_id": rule.vm_instance_id,
+        "metric_type": rule.metric_type,
+        "status": status,
+        "value": evaluation.value,
+        "threshold": rule.threshold,
+        "message": message,
+        "timestamp": Utc::now().to_rfc3339()
+    });
+
+    let mut notified = !channels.is_empty();
+    for channel in &channels {
+        if let Err(e) = notifier.notify(channel, &subject, &payload).await {
+            warn!("告警通知发送失败 (渠道 {}): {}", channel.id, e);
+            notified = false;
+        }
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO alert_history (rule_id, vm_instance_id, status, value, message, notified)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#
+    )
+    .bind(rule.id)
+    .bind(rule.vm_instance_id)
+    .bind(status)
+    .bind(evaluation.value)
+    .bind(&message)
+    .bind(notified)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+fn describe(rule: &AlertRule, status: &str, value: Option<f64>) -> String {
+    if rule.metric_type == RULE_INSTANCE_DOWN {
+        return if status == "firing" {
+            format!("实例已超过 {} 分钟没有上报监控数据", rule.duration_minutes)
+        } else {
+            "实例已恢复上报监控数据".to_string()
+        };
+    }
+
+    let operator = match rule.comparator.as_str() {
+        "gt" => ">",
+        "gte" => ">=",
+        "lt" => "<",
+        _ => "<=",
+    };
+    let value = value.map(|v| format!("{:.2}", v)).unwrap_or_else(|| "-".to_string());
+    let threshold = rule.threshold.unwrap_or_default();
+    if status == "firing" {
+        format!(
+            "{} 持续 {} 分钟 {} {}，当前值 {}",
+            rule.metric_type, rule.duration_minutes, operator, threshold, value
+        )
+    } else {
+        format!("{} 已恢复正常，当前值 {}", rule.metric_type, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn public(ip: &str) -> bool {
+        is_public_ip(ip.parse().unwrap())
+    }
+
+    #[test]
+    fn public_addresses_are_allowed() {
+        assert!(public("8.8.8.8"));
+        assert!(public("1.1.1.1"));
+        assert!(public("2606:4700:4700::1111"));
+    }
+
+    #[test]
+    fn private_and_special_ipv4_addresses_are_rejected() {
+        for ip in [
+            "127.0.0.1", "10.1.2.3", "172.16.0.1", "192.168.1.1", "169.254.169.254", "0.0.0.0", "0.1.2.3",
+            "255.255.255.255", "192.0.2.1", "224.0.0.1", "100.64.0.1", "100.127.255.254",
+        ] {
+            assert!(!public(ip), "{} 应视为内网地址", ip);
+        }
+        assert!(public("100.128.0.1"));
+    }
+
+    #[test]
+    fn private_and_special_ipv6_addresses_are_rejected() {
+        for ip in ["::1", "::", "ff02::1", "fc00::1", "fd12:3456::1", "fe80::1", "::ffff:127.0.0.1", "::ffff:10.0.0.1"] {
+            assert!(!public(ip), "{} 应视为内网地址", ip);
+        }
+        assert!(public("::ffff:8.8.8.8"));
+    }
+
+    #[tokio::test]
+    async fn webhook_target_rejects_internal_hosts_and_other_schemes() {
+        assert!(resolve_webhook_target("http://127.0.0.1:8080/hook").await.is_err());
+        assert!(resolve_webhook_target("http://[::1]/hook").await.is_err());
+        assert!(resolve_webhook_target("http://169.254.169.254/latest/meta-data").await.is_err());
+        assert!(resolve_webhook_target("file:///etc/passwd").await.is_err());
+        assert!(resolve_webhook_target("not a url").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn webhook_target_pins_resolved_public_address() {
+        let (url, addrs) = resolve_webhook_target("https://8.8.8.8/hook").await.unwrap();
+        assert_eq!(url.path(), "/hook");
+        assert_eq!(addrs, vec!["8.8.8.8:443".parse().unwrap()]);
+    }
+}
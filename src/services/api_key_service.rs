@@ -0,0 +1,204 @@
+use std::net::IpAddr;
+
+use uuid::Uuid;
+
+use crate::database::DbPool;
+use crate::models::api_key::{ApiKeyInfo, ApiKeyScope, CreateApiKeyRequest};
+use crate::utils::crypto::{random_token, sha256_hex};
+
+/// 密钥明文前缀，认证中间件据此区分 API 密钥和 JWT
+pub const API_KEY_PREFIX: &str = "ovk_";
+const KEY_BYTES: usize = 24;
+const DISPLAY_PREFIX_LEN: usize = 12;
+// 最近使用时间的更新间隔，避免每个请求都写库
+const LAST_USED_UPDATE_SECS: i64 = 60;
+
+const API_KEY_COLUMNS: &str = "id, name, key_prefix, scopes, allowed_ips::TEXT[] AS allowed_ips, expires_at, \
+    last_used_at, host(last_used_ip) AS last_used_ip, created_at";
+
+/// 新创建的密钥，明文只返回这一次
+pub struct CreatedApiKey {
+    pub key: String,
+    pub info: ApiKeyInfo,
+}
+
+/// 通过 API 密钥认证的用户
+pub struct ApiKeyPrincipal {
+    pub key_id: Uuid,
+    pub user_id: Uuid,
+    pub username: String,
+    pub role: String,
+    pub scopes: Vec<ApiKeyScope>,
+}
+
+pub enum ApiKeyLookup {
+    Valid(ApiKeyPrincipal),
+    Invalid,
+    IpNotAllowed,
+}
+
+#[derive(sqlx::FromRow)]
+struct ApiKeyRow {
+    id: Uuid,
+    user_id: Uuid,
+    username: String,
+    role: String,
+    scopes: Vec<String>,
+    ip_allowed: bool,
+}
+
+/// 校验 IP 或 CIDR 格式，如 203.0.113.7、10.0.0.0/8、2001:db8::/32
+/// 网段必须写网络地址，10.0.0.5/8 这类带主机位的写法 Postgres CIDR 不接受
+pub fn is_valid_cidr(value: &str) -> bool {
+    let (addr, prefix) = match value.split_once('/') {
+        Some((addr, prefix)) => (addr, Some(prefix)),
+        None => (value, None),
+    };
+    let Ok(addr) = addr.parse::<IpAddr>() else {
+        return false;
+    };
+    let Some(prefix) = prefix else {
+        return true;
+    };
+    let Ok(prefix) = prefix.parse::<u32>() else {
+        return false;
+    };
+    match addr {
+        IpAddr::V4(v4) => prefix <= 32 && u32::from(v4).checked_shl(prefix).unwrap_or(0) == 0,
+        IpAddr::V6(v6) => prefix <= 128 && u128::from(v6).checked_shl(prefix).unwrap_or(0) == 0,
+    }
+}
+
+pub async fn create(pool: &DbPool, user_id: Uuid, request: &CreateApiKeyRequest) -> Result<CreatedApiKey, sqlx::Error> {
+    let key = format!("{}{}", API_KEY_PREFIX, random_token(KEY_BYTES));
+    let scopes: Vec<&str> = request.scopes.iter().map(|s| s.as_str()).collect();
+
+    let info = sqlx::query_as::<_, ApiKeyInfo>(&format!(
+        r#"
+        INSERT INTO api_keys (user_id, name, key_prefix, key_hash, scopes, allowed_ips, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6::CIDR[], $7)
+        RETURNING {}
+        "#,
+        API_KEY_COLUMNS
+    ))
+    .bind(user_id)
+    .bind(request.name.trim())
+    .bind(&key[..DISPLAY_PREFIX_LEN])
+    .bind(sha256_hex(&key))
+    .bind(&scopes)
+    .bind(&request.allowed_ips)
+    .bind(request.expires_at)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(CreatedApiKey { key, info })
+}
+
+pub async fn list(pool: &DbPool, user_id: Uuid) -> Result<Vec<ApiKeyInfo>, sqlx::Error> {
+    sqlx::query_as::<_, ApiKeyInfo>(&format!(
+        "SELECT {} FROM api_keys WHERE user_id = $1 AND revoked_at IS NULL ORDER BY created_at DESC",
+        API_KEY_COLUMNS
+    ))
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn revoke(pool: &DbPool, user_id: Uuid, key_id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE api_keys SET revoked_at = NOW() WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL"
+    )
+    .bind(key_id)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// 校验密钥是否有效、未过期、来源 IP 是否在允许列表内，并记录使用情况
+pub async fn authenticate(pool: &DbPool, key: &str, ip_address: &str) -> Result<ApiKeyLookup, sqlx::Error> {
+    let row = sqlx::query_as::<_, ApiKeyRow>(
+        r#"
+        SELECT k.id, k.user_id, u.username, u.role, k.scopes,
+               (cardinality(k.allowed_ips) = 0 OR $2::INET <<= ANY(k.allowed_ips)) AS ip_allowed
+        FROM api_keys k
+        JOIN users u ON u.id = k.user_id
+        WHERE k.key_hash = $1
+          AND k.revoked_at IS NULL
+          AND (k.expires_at IS NULL OR k.expires_at > NOW())
+          AND u.status = 'active'
+        "#
+    )
+    .bind(sha256_hex(key))
+    .bind(ip_address)
+    .fetch_optional(pool)
+    .await?;
+
+    let Some(row) = row else {
+        return Ok(ApiKeyLookup::Invalid);
+    };
+    if !row.ip_allowed {
+        return Ok(ApiKeyLookup::IpNotAllowed);
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE api_keys SET last_used_at = NOW(), last_used_ip = $2::INET
+        WHERE id = $1 AND (last_used_at IS NULL OR last_used_at < NOW() - make_interval(secs => $3))
+        "#
+    )
+    .bind(row.id)
+    .bind(ip_address)
+    .bind(LAST_USED_UPDATE_SECS as f64)
+    .execute(pool)
+    .await?;
+
+    Ok(ApiKeyLookup::Valid(ApiKeyPrincipal {
+        key_id: row.id,
+        user_id: row.user_id,
+        username: row.username,
+        role: row.role,
+        scopes: row.scopes.iter().filter_map(|s| ApiKeyScope::parse(s)).collect(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_valid_cidr_accepts_addresses_and_networks() {
+        assert!(is_valid_cidr("203.0.113.7"));
+        assert!(is_valid_cidr("10.0.0.0/8"));
+        assert!(is_valid_cidr("0.0.0.0/0"));
+        assert!(is_valid_cidr("2001:db8::/32"));
+        assert!(is_valid_cidr("::1"));
+    }
+
+    #[test]
+    fn is_valid_cidr_checks_prefix_bounds() {
+        assert!(is_valid_cidr("192.168.0.1/32"));
+        assert!(!is_valid_cidr("192.168.0.1/33"));
+        assert!(is_valid_cidr("2001:db8::1/128"));
+        assert!(!is_valid_cidr("2001:db8::1/129"));
+        assert!(!is_valid_cidr("10.0.0.0/-1"));
+        assert!(!is_valid_cidr("10.0.0.0/"));
+    }
+
+    #[test]
+    fn is_valid_cidr_rejects_host_bits() {
+        assert!(!is_valid_cidr("10.0.0.5/8"));
+        assert!(!is_valid_cidr("192.168.1.1/24"));
+        assert!(is_valid_cidr("192.168.1.0/24"));
+        assert!(!is_valid_cidr("2001:db8::1/32"));
+        assert!(is_valid_cidr("2001:db8::/32"));
+    }
+
+    #[test]
+    fn is_valid_cidr_rejects_malformed_input() {
+        assert!(!is_valid_cidr(""));
+        assert!(!is_valid_cidr("example.com"));
+        assert!(!is_valid_cidr("256.0.0.1"));
+        assert!(!is_valid_cidr("10.0.0/8"));
+    }
+}
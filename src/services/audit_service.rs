@@ -0,0 +1,42 @@
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+pub const ACTION_ROLE_CHANGED: &str = "user.role_changed";
+pub const ACTION_TWO_FACTOR_RESET: &str = "user.two_factor_reset";
+pub const ACTION_IDENTITY_VIEWED: &str = "identity.viewed";
+pub const ACTION_IDENTITY_REVIEWED: &str = "identity.reviewed";
+
+pub const TARGET_USER: &str = "user";
+pub const TARGET_IDENTITY_VERIFICATION: &str = "identity_verification";
+
+/// 一条审计记录，由执行操作的员工产生
+pub struct AuditEntry<'a> {
+    pub actor_id: Uuid,
+    pub action: &'a str,
+    pub target_type: &'a str,
+    pub target_id: Uuid,
+    pub details: serde_json::Value,
+    pub ip_address: &'a str,
+}
+
+/// 写入审计记录，可以放在业务操作的同一事务中执行
+pub async fn record<'e, E>(executor: E, entry: AuditEntry<'_>) -> Result<(), sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    sqlx::query(
+        r#"
+        INSERT INTO audit_logs (actor_id, action, target_type, target_id, details, ip_address)
+        VALUES ($1, $2, $3, $4, $5, $6::INET)
+        "#
+    )
+    .bind(entry.actor_id)
+    .bind(entry.action)
+    .bind(entry.target_type)
+    .bind(entry.target_id)
+    .bind(entry.details)
+    .bind(entry.ip_address)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
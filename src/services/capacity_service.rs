@@ -0,0 +1,198 @@
+use std::collections::HashSet;
+use std::time::Duration as StdDuration;
+
+use chrono::Utc;
+use log::{error, warn};
+
+use crate::database::DbPool;
+use crate::models::pve_node::{NodeCapacityReport, PveNode, PveStorageUsage, PveVmAllocation, PVE_NODE_COLUMNS};
+use crate::services::pve_service::ProxmoxClient;
+use crate::utils::metrics::{JOB_LAST_RUN, JOB_QUEUE_DEPTH};
+
+const JOB_NAME: &str = "capacity_sync";
+const GIB: u64 = 1024 * 1024 * 1024;
+
+fn to_gb(bytes: u64) -> i32 {
+    bytes.div_ceil(GIB) as i32
+}
+
+/// 平台 vm_instances 中记录的节点资源合计
+#[derive(sqlx::FromRow)]
+struct RecordedAllocation {
+    cpu: i64,
+    memory_gb: i64,
+    storage_gb: i64,
+    vmids: Vec<i32>,
+}
+
+struct NodeSnapshot {
+    vms: Vec<PveVmAllocation>,
+    storages: Vec<PveStorageUsage>,
+}
+
+/// 启动节点资源对账后台任务
+pub fn spawn_capacity_sync(pool: DbPool) {
+    let interval = std::env::var("NODE_CAPACITY_SYNC_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(300);
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(StdDuration::from_secs(interval));
+        loop {
+            ticker.tick().await;
+            if let Err(e) = sync_all(&pool).await {
+                error!("节点资源对账失败: {}", e);
+            }
+        }
+    });
+}
+
+/// 对所有启用节点执行一次对账，返回生成的报告
+pub async fn sync_all(pool: &DbPool) -> Result<Vec<NodeCapacityReport>, sqlx::Error> {
+    let nodes = sqlx::query_as::<_, PveNode>(&format!(
+        "SELECT {} FROM pve_nodes WHERE status <> 'disabled'",
+        PVE_NODE_COLUMNS
+    ))
+    .fetch_all(pool)
+    .await?;
+
+    let queue_depth = JOB_QUEUE_DEPTH.with_label_values(&[JOB_NAME]);
+    queue_depth.set(nodes.len() as i64);
+    let mut reports = Vec::with_capacity(nodes.len());
+    for node in &nodes {
+        match sync_node(pool, node).await {
+            Ok(report) => reports.push(report),
+            Err(e) => error!("节点 {} 资源对账失败: {}", node.name, e),
+        }
+        queue_depth.dec();
+    }
+
+    sqlx::query("DELETE FROM node_capacity_reports WHERE created_at < NOW() - INTERVAL '30 days'")
+        .execute(pool)
+        .await?;
+
+    JOB_LAST_RUN.with_label_values(&[JOB_NAME]).set(Utc::now().timestamp());
+    Ok(reports)
+}
+
+async fn fetch_snapshot(node: &PveNode) -> Result<NodeSnapshot, String> {
+    let client = ProxmoxClient::for_node(node)?;
+    Ok(NodeSnapshot {
+        vms: client.list_vms(&node.name).await?,
+        storages: client.list_storage(&node.name).await?,
+    })
+}
+
+async fn sync_node(pool: &DbPool, node: &PveNode) -> Result<NodeCapacityReport, sqlx::Error> {
+    let recorded = sqlx::query_as::<_, RecordedAllocation>(
+        r#"
+        SELECT COALESCE(SUM(cpu_cores), 0)::BIGINT AS cpu,
+               COALESCE(SUM(memory_gb), 0)::BIGINT AS memory_gb,
+               COALESCE(SUM(storage_gb), 0)::BIGINT AS storage_gb,
+               COALESCE(array_agg(pve_vmid) FILTER (WHERE pve_vmid IS NOT NULL), '{}') AS vmids
+        FROM vm_instances
+        WHERE pve_node_id = $1 AND status NOT IN ('deleted')
+        "#
+    )
+    .bind(node.id)
+    .fetch_one(pool)
+    .await?;
+
+    let snapshot = match fetch_snapshot(node).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            // 无法获取 PVE 数据时只记录平台侧数据和错误，不覆盖节点用量
+            warn!("获取节点 {} 资源数据失败: {}", node.name, e);
+            return sqlx::query_as::<_, NodeCapacityReport>(
+                r#"
+                INSERT INTO node_capacity_reports (pve_node_id, db_cpu, db_memory_gb, db_storage_gb, error)
+                VALUES ($1, $2, $3, $4, $5)
+                RETURNING *
+                "#
+            )
+            .bind(node.id)
+            .bind(recorded.cpu as i32)
+            .bind(recorded.memory_gb as i32)
+            .bind(recorded.storage_gb as i32)
+            .bind(e)
+            .fetch_one(pool)
+            .await;
+        }
+    };
+
+    // 模板不占用 CPU 和内存，但仍占用磁盘
+    let running_vms = snapshot.vms.iter().filter(|vm| !vm.template);
+    let pve_cpu: i32 = running_vms.clone().map(|vm| vm.cpus).sum();
+    let pve_memory_gb = to_gb(running_vms.map(|vm| vm.memory_bytes).sum());
+    let pve_storage_gb = to_gb(snapshot.vms.iter().map(|vm| vm.disk_bytes).sum());
+
+    // 共享存储会在多个节点重复出现，只统计本地存储
+    let local_storage = snapshot.storages.iter().filter(|s| !s.shared);
+    let storage_used_gb = to_gb(local_storage.clone().map(|s| s.used_bytes).sum());
+    let storage_total_gb = to_gb(local_storage.map(|s| s.total_bytes).sum());
+
+    let pve_vmids: HashSet<i32> = snapshot.vms.iter().filter(|vm| !vm.template).map(|vm| vm.vmid).collect();
+    let db_vmids: HashSet<i32> = recorded.vmids.iter().copied().collect();
+    let mut untracked_vmids: Vec<i32> = pve_vmids.difference(&db_vmids).copied().collect();
+    let mut missing_vmids: Vec<i32> = db_vmids.difference(&pve_vmids).copied().collect();
+    untracked_vmids.sort_unstable();
+    missing_vmids.sort_unstable();
+
+    let has_drift = pve_cpu as i64 != recorded.cpu
+        || pve_memory_gb as i64 != recorded.memory_gb
+        || pve_storage_gb as i64 != recorded.storage_gb
+        || !untracked_vmids.is_empty()
+        || !missing_vmids.is_empty();
+    let overcommitted = pve_cpu > node.max_cpu
+        || pve_memory_gb > node.max_memory_gb
+        || pve_storage_gb > node.max_storage_gb;
+
+    if has_drift {
+        warn!(
+            "节点 {} 资源偏差: PVE cpu={} mem={}G disk={}G, 平台 cpu={} mem={}G disk={}G, 未登记 {:?}, 缺失 {:?}",
+            node.name, pve_cpu, pve_memory_gb, pve_storage_gb,
+            recorded.cpu, recorded.memory_gb, recorded.storage_gb,
+            untracked_vmids, missing_vmids
+        );
+    }
+    if overcommitted {
+        warn!("节点 {} 已超售", node.name);
+    }
+
+    // 以 PVE 实际分配量为准更新节点用量，保证调度不会超售
+    sqlx::query(
+        "UPDATE pve_nodes SET used_cpu = $2, used_memory_gb = $3, used_storage_gb = $4 WHERE id = $1"
+    )
+    .bind(node.id)
+    .bind(pve_cpu)
+    .bind(pve_memory_gb)
+    .bind(pve_storage_gb)
+    .execute(pool)
+    .await?;
+
+    sqlx::query_as::<_, NodeCapacityReport>(
+        r#"
+        INSERT INTO node_capacity_reports
+            (pve_node_id, pve_cpu, pve_memory_gb, pve_storage_gb, db_cpu, db_memory_gb, db_storage_gb,
+             storage_used_gb, storage_total_gb, untracked_vmids, missing_vmids, has_drift, overcommitted)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+        RETURNING *
+        "#
+    )
+    .bind(node.id)
+    .bind(pve_cpu)
+    .bind(pve_memory_gb)
+    .bind(pve_storage_gb)
+    .bind(recorded.cpu as i32)
+    .bind(recorded.memory_gb as i32)
+    .bind(recorded.storage_gb as i32)
+    .bind(storage_used_gb)
+    .bind(storage_total_gb)
+    .bind(&untracked_vmids)
+    .bind(&missing_vmids)
+    .bind(has_drift)
+    .bind(overcommitted)
+    .fetch_one(pool)
+    .await
+}
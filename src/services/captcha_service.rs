@@ -0,0 +1,41 @@
+use std::time::Duration as StdDuration;
+
+use reqwest::Client as ReqwestClient;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct VerifyResponse {
+    success: bool,
+}
+
+/// 人机验证，兼容 hCaptcha / reCAPTCHA / Turnstile 的 siteverify 接口
+pub struct CaptchaVerifier {
+    http: ReqwestClient,
+    verify_url: String,
+    secret: String,
+}
+
+impl CaptchaVerifier {
+    /// 未配置 CAPTCHA_VERIFY_URL 和 CAPTCHA_SECRET 时返回 None，登录接口只返回需要验证的标记
+    pub fn from_env() -> Option<Self> {
+        let verify_url = std::env::var("CAPTCHA_VERIFY_URL").ok()?;
+        let secret = std::env::var("CAPTCHA_SECRET").ok()?;
+        let http = ReqwestClient::builder()
+            .timeout(StdDuration::from_secs(10))
+            .build()
+            .expect("Failed to build captcha client");
+        Some(CaptchaVerifier { http, verify_url, secret })
+    }
+
+    pub async fn verify(&self, token: &str, ip_address: &str) -> Result<bool, reqwest::Error> {
+        let response: VerifyResponse = self
+            .http
+            .post(&self.verify_url)
+            .form(&[("secret", self.secret.as_str()), ("response", token), ("remoteip", ip_address)])
+            .send()
+            .await?
+            .json()
+            .await?;
+        Ok(response.success)
+    }
+}
@@ -0,0 +1,109 @@
+use lettre::message::{header::ContentType, Mailbox};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Address, AsyncFileTransport, AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use thiserror::Error;
+
+use crate::services::email_template::EmailTemplate;
+use crate::utils::locale::Locale;
+
+const DEFAULT_FILE_DIR: &str = "./mail";
+const DEFAULT_FROM: &str = "OpenVirt <noreply@localhost>";
+
+#[derive(Debug, Error)]
+pub enum EmailError {
+    #[error("邮件服务未配置: {0}")]
+    NotConfigured(String),
+    #[error("无效的邮箱地址: {0}")]
+    Address(#[from] lettre::address::AddressError),
+    #[error("邮件构建失败: {0}")]
+    Build(#[from] lettre::error::Error),
+    #[error("邮件发送失败: {0}")]
+    Transport(#[from] lettre::transport::smtp::Error),
+    #[error("邮件写入失败: {0}")]
+    File(#[from] lettre::transport::file::Error),
+}
+
+/// 邮件投递方式，开发和测试环境可以写入本地目录代替真实发送
+#[derive(Clone)]
+enum Transport {
+    Smtp(AsyncSmtpTransport<Tokio1Executor>),
+    File(AsyncFileTransport<Tokio1Executor>),
+}
+
+#[derive(Clone)]
+pub struct EmailService {
+    transport: Transport,
+    from: Mailbox,
+}
+
+/// 校验邮箱地址格式
+pub fn is_valid_address(email: &str) -> bool {
+    email.parse::<Address>().is_ok()
+}
+
+fn smtp_transport() -> Result<AsyncSmtpTransport<Tokio1Executor>, EmailError> {
+    let host = std::env::var("SMTP_HOST")
+        .map_err(|_| EmailError::NotConfigured("SMTP_HOST".to_string()))?;
+
+    let mut builder = AsyncSmtpTransport::<Tokio1Executor>::relay(&host)?;
+    if let Ok(port) = std::env::var("SMTP_PORT") {
+        let port = port
+            .parse::<u16>()
+            .map_err(|_| EmailError::NotConfigured("SMTP_PORT".to_string()))?;
+        builder = builder.port(port);
+    }
+    if let (Ok(username), Ok(password)) = (std::env::var("SMTP_USERNAME"), std::env::var("SMTP_PASSWORD")) {
+        builder = builder.credentials(Credentials::new(username, password));
+    }
+    Ok(builder.build())
+}
+
+impl EmailService {
+    /// EMAIL_TRANSPORT 为 smtp（默认）时从 SMTP_HOST / SMTP_PORT / SMTP_USERNAME / SMTP_PASSWORD / SMTP_FROM 读取配置，
+    /// 为 file 时把邮件保存为 EMAIL_FILE_DIR 下的 .eml 文件
+    pub fn from_env() -> Result<Self, EmailError> {
+        let transport = match std::env::var("EMAIL_TRANSPORT").as_deref() {
+            Ok("smtp") | Err(_) => Transport::Smtp(smtp_transport()?),
+            Ok("file") => {
+                let dir = std::env::var("EMAIL_FILE_DIR").unwrap_or_else(|_| DEFAULT_FILE_DIR.to_string());
+                std::fs::create_dir_all(&dir)
+                    .map_err(|e| EmailError::NotConfigured(format!("EMAIL_FILE_DIR: {}", e)))?;
+                Transport::File(AsyncFileTransport::new(dir))
+            }
+            Ok(other) => return Err(EmailError::NotConfigured(format!("EMAIL_TRANSPORT={}", other))),
+        };
+
+        let from = match (&transport, std::env::var("SMTP_FROM")) {
+            (_, Ok(from)) => from.parse::<Mailbox>()?,
+            (Transport::File(_), Err(_)) => DEFAULT_FROM.parse::<Mailbox>()?,
+            (Transport::Smtp(_), Err(_)) => return Err(EmailError::NotConfigured("SMTP_FROM".to_string())),
+        };
+
+        Ok(EmailService { transport, from })
+    }
+
+    pub async fn send(&self, to: &str, subject: &str, body: &str) -> Result<(), EmailError> {
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to.parse::<Mailbox>()?)
+            .subject(subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(body.to_string())?;
+
+        match &self.transport {
+            Transport::Smtp(transport) => {
+                transport.send(message).await?;
+            }
+            Transport::File(transport) => {
+                transport.send(message).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 按收件人语言渲染模板后发送
+    pub async fn send_template(&self, to: &str, template: &EmailTemplate<'_>, locale: Locale) -> Result<(), EmailError> {
+        let rendered = template.render(locale);
+        self.send(to, &rendered.subject, &rendered.body).await
+    }
+}
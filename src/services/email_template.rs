@@ -0,0 +1,88 @@
+use chrono::{DateTime, FixedOffset, Utc};
+
+use crate::models::organization::OrgRole;
+use crate::models::user::VerificationPurpose;
+use crate::utils::i18n;
+use crate::utils::locale::Locale;
+
+/// 渲染后的邮件标题和纯文本正文
+pub struct RenderedEmail {
+    pub subject: String,
+    pub body: String,
+}
+
+/// 系统发出的邮件
+pub enum EmailTemplate<'a> {
+    VerificationCode {
+        code: &'a str,
+        purpose: VerificationPurpose,
+        ttl_minutes: i64,
+    },
+    /// 多次密码错误导致账户被临时锁定
+    AccountLocked {
+        username: &'a str,
+        ip_address: &'a str,
+        locked_until: DateTime<Utc>,
+    },
+    /// 邀请加入组织
+    OrganizationInvitation {
+        organization: &'a str,
+        inviter: &'a str,
+        role: OrgRole,
+        url: &'a str,
+        ttl_days: i64,
+    },
+}
+
+impl EmailTemplate<'_> {
+    /// 标题和正文取自消息目录，中文原文即模板
+    pub fn render(&self, locale: Locale) -> RenderedEmail {
+        match self {
+            EmailTemplate::VerificationCode { code, purpose, ttl_minutes } => {
+                let (subject, action) = match purpose {
+                    VerificationPurpose::Register => ("【OpenVirt】邮箱验证码", "验证邮箱"),
+                    VerificationPurpose::ResetPassword => ("【OpenVirt】重置密码验证码", "重置密码"),
+                    VerificationPurpose::ChangeEmail => ("【OpenVirt】更换邮箱验证码", "更换绑定邮箱"),
+                };
+                let action = i18n::translate(locale, action);
+                RenderedEmail {
+                    subject: i18n::translate(locale, subject).into_owned(),
+                    body: i18n::format_message(
+                        locale,
+                        "您好，\n\n您正在{}，验证码为：{}\n\n验证码 {} 分钟内有效，请勿泄露给他人。如非本人操作，请忽略本邮件。\n\nOpenVirt",
+                        &[&action, code, ttl_minutes],
+                    ),
+                }
+            }
+            EmailTemplate::AccountLocked { username, ip_address, locked_until } => {
+                // 中文邮件按北京时间显示，英文邮件按 UTC
+                let locked_until = match locale {
+                    Locale::ZhCn => {
+                        let beijing = FixedOffset::east_opt(8 * 3600).expect("valid offset");
+                        locked_until.with_timezone(&beijing).format("%Y-%m-%d %H:%M").to_string()
+                    }
+                    Locale::En => locked_until.format("%Y-%m-%d %H:%M").to_string(),
+                };
+                RenderedEmail {
+                    subject: i18n::translate(locale, "【OpenVirt】账户已临时锁定").into_owned(),
+                    body: i18n::format_message(
+                        locale,
+                        "{}，您好：\n\n您的账户连续多次密码错误（最近一次来自 {}），为保护账户安全已临时锁定，将于北京时间 {} 自动解锁。\n\n如非本人操作，建议解锁后立即修改密码并开启两步验证。\n\nOpenVirt",
+                        &[username, ip_address, &locked_until],
+                    ),
+                }
+            }
+            EmailTemplate::OrganizationInvitation { organization, inviter, role, url, ttl_days } => {
+                let role = i18n::translate(locale, role.label());
+                RenderedEmail {
+                    subject: i18n::format_message(locale, "【OpenVirt】{} 邀请您加入组织 {}", &[inviter, organization]),
+                    body: i18n::format_message(
+                        locale,
+                        "您好，\n\n{} 邀请您以{}身份加入 OpenVirt 组织“{}”。请登录后打开以下链接接受邀请：\n\n{}\n\n链接 {} 天内有效。如不认识邀请人，请忽略本邮件。\n\nOpenVirt",
+                        &[inviter, &role, organization, url, ttl_days],
+                    ),
+                }
+            }
+        }
+    }
+}
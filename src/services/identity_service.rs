@@ -0,0 +1,261 @@
+use std::sync::Arc;
+
+use chrono::{NaiveDate, Utc};
+use futures::future::BoxFuture;
+use log::info;
+use sqlx::PgExecutor;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::database::DbPool;
+
+pub const STATUS_PENDING: &str = "pending";
+pub const STATUS_APPROVED: &str = "approved";
+pub const STATUS_REJECTED: &str = "rejected";
+
+/// 身份证号前 17 位的加权系数和对应的校验码（GB 11643-1999）
+const CHECKSUM_WEIGHTS: [u32; 17] = [7, 9, 10, 5, 8, 4, 2, 1, 6, 3, 7, 9, 10, 5, 8, 4, 2];
+const CHECKSUM_CODES: &[u8; 11] = b"10X98765432";
+
+#[derive(Debug, Error)]
+pub enum IdentityError {
+    #[error("实名核验服务未配置: {0}")]
+    NotConfigured(String),
+}
+
+#[derive(Debug, Error)]
+pub enum InstanceGuardError {
+    #[error("请先完成实名认证")]
+    IdentityRequired,
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+}
+
+/// 核验服务商对姓名和证件号是否一致的判断
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdentityCheck {
+    Matched,
+    Mismatched,
+    /// 服务商无法确定，转人工审核
+    ManualReview,
+}
+
+/// 实名核验服务商（身份证二要素核验）
+pub trait IdentityProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn verify<'a>(&'a self, real_name: &'a str, id_card: &'a str) -> BoxFuture<'a, Result<IdentityCheck, IdentityError>>;
+}
+
+/// 不接入第三方，全部申请进入管理员审核队列
+pub struct ManualReview;
+
+impl IdentityProvider for ManualReview {
+    fn name(&self) -> &'static str {
+        "manual"
+    }
+
+    fn verify<'a>(&'a self, _real_name: &'a str, _id_card: &'a str) -> BoxFuture<'a, Result<IdentityCheck, IdentityError>> {
+        Box::pin(async { Ok(IdentityCheck::ManualReview) })
+    }
+}
+
+/// 本地开发用，姓名以“测试不符”开头时返回不一致，其余视为一致；只能在 APP_ENV=development 或 test 时启用
+pub struct MockIdentity;
+
+const MOCK_MISMATCH_PREFIX: &str = "测试不符";
+
+impl IdentityProvider for MockIdentity {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn verify<'a>(&'a self, real_name: &'a str, _id_card: &'a str) -> BoxFuture<'a, Result<IdentityCheck, IdentityError>> {
+        Box::pin(async move {
+            let check = if real_name.starts_with(MOCK_MISMATCH_PREFIX) {
+                IdentityCheck::Mismatched
+            } else {
+                IdentityCheck::Matched
+            };
+            info!("[实名核验模拟] 核验结果: {:?}", check);
+            Ok(check)
+        })
+    }
+}
+
+/// 模拟核验会放行所有请求，生产环境不允许启用
+fn mock_allowed() -> bool {
+    std::env::var("APP_ENV").is_ok_and(|env| env == "development" || env == "test")
+}
+
+/// 按 ID_VERIFY_PROVIDER 环境变量选择服务商，未设置时转人工审核
+pub fn provider_from_env() -> Result<Arc<dyn IdentityProvider>, IdentityError> {
+    let name = std::env::var("ID_VERIFY_PROVIDER").unwrap_or_else(|_| "manual".to_string());
+    match name.as_str() {
+        "manual" => Ok(Arc::new(ManualReview)),
+        "mock" if mock_allowed() => Ok(Arc::new(MockIdentity)),
+        "mock" => Err(IdentityError::NotConfigured(
+            "模拟实名核验只能在 APP_ENV=development 或 test 时使用".to_string(),
+        )),
+        other => Err(IdentityError::NotConfigured(format!("未知的实名核验服务商 {}", other))),
+    }
+}
+
+/// 校验 18 位居民身份证号的出生日期和校验码，末位 x 统一为大写，格式不正确时返回 None
+pub fn normalize_id_card(id_card: &str) -> Option<String> {
+    let id_card = id_card.trim().to_ascii_uppercase();
+    let bytes = id_card.as_bytes();
+    if bytes.len() != 18 || !bytes[..17].iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+
+    let birth_date = NaiveDate::parse_from_str(&id_card[6..14], "%Y%m%d").ok()?;
+    if birth_date > Utc::now().date_naive() {
+        return None;
+    }
+
+    let sum: u32 = bytes[..17]
+        .iter()
+        .zip(CHECKSUM_WEIGHTS)
+        .map(|(digit, weight)| u32::from(digit - b'0') * weight)
+        .sum();
+    (bytes[17] == CHECKSUM_CODES[(sum % 11) as usize]).then_some(id_card)
+}
+
+/// 只保留前 4 位和后 4 位
+pub fn mask_id_card(id_card: &str) -> String {
+    match (id_card.get(..4), id_card.get(id_card.len().saturating_sub(4)..)) {
+        (Some(head), Some(tail)) if id_card.len() > 8 => format!("{}{}{}", head, "*".repeat(id_card.len() - 8), tail),
+        _ => "*".repeat(id_card.len()),
+    }
+}
+
+/// system_configs.id_verify_required，未配置时不要求实名
+pub async fn verification_required(pool: &DbPool) -> Result<bool, sqlx::Error> {
+    let value = sqlx::query_scalar::<_, serde_json::Value>("SELECT value FROM system_configs WHERE key = 'id_verify_required'")
+        .fetch_optional(pool)
+        .await?;
+    Ok(match value {
+        Some(serde_json::Value::Bool(required)) => required,
+        Some(serde_json::Value::String(required)) => required == "true",
+        _ => false,
+    })
+}
+
+pub async fn is_verified(pool: &DbPool, user_id: Uuid) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM users WHERE id = $1 AND id_verified_at IS NOT NULL)")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+}
+
+/// 证件是否已被其他账号认证
+pub async fn id_card_taken(pool: &DbPool, id_card_hash: &str, user_id: Uuid) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM identity_verifications WHERE id_card_hash = $1 AND status = 'approved' AND user_id <> $2)"
+    )
+    .bind(id_card_hash)
+    .bind(user_id)
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn has_pending(pool: &DbPool, user_id: Uuid) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM identity_verifications WHERE user_id = $1 AND status = 'pending')")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+}
+
+/// 实名要求关闭或已完成实名认证时才能创建实例，判断规则与 verification_required、is_verified 一致，
+/// 合并为一条查询以便在调用方的事务中执行
+pub async fn may_create_instance<'e, E>(executor: E, user_id: Uuid) -> Result<bool, sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT NOT EXISTS(
+                   SELECT 1 FROM system_configs
+                   WHERE key = 'id_verify_required' AND value IN ('true'::JSONB, '"true"'::JSONB)
+               )
+            OR EXISTS(SELECT 1 FROM users WHERE id = $1 AND id_verified_at IS NOT NULL)
+        "#
+    )
+    .bind(user_id)
+    .fetch_one(executor)
+    .await
+}
+
+/// 用户获得实例前的必经检查：创建实例、把实例转入用户名下等写入 vm_instances.user_id 的代码都要先调用，
+/// 已开启事务时传入事务连接
+pub async fn ensure_may_create_instance<'e, E>(executor: E, user_id: Uuid) -> Result<(), InstanceGuardError>
+where
+    E: PgExecutor<'e>,
+{
+    if may_create_instance(executor, user_id).await? {
+        Ok(())
+    } else {
+        Err(InstanceGuardError::IdentityRequired)
+    }
+}
+
+/// 认证通过后把姓名和证件密文写入用户资料
+pub async fn mark_verified<'e, E>(executor: E, user_id: Uuid, real_name: &str, id_card_encrypted: &str) -> Result<(), sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    sqlx::query(
+        "UPDATE users SET real_name = $2, id_card = $3, id_verified_at = NOW(), updated_at = NOW() WHERE id = $1"
+    )
+    .bind(user_id)
+    .bind(real_name)
+    .bind(id_card_encrypted)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_id_card_accepts_valid_numbers() {
+        assert_eq!(normalize_id_card("440304199001011233").as_deref(), Some("440304199001011233"));
+        assert_eq!(normalize_id_card(" 440304199001011233 ").as_deref(), Some("440304199001011233"));
+    }
+
+    #[test]
+    fn normalize_id_card_uppercases_x_check_digit() {
+        assert_eq!(normalize_id_card("11010519491231002X").as_deref(), Some("11010519491231002X"));
+        assert_eq!(normalize_id_card("11010519491231002x").as_deref(), Some("11010519491231002X"));
+    }
+
+    #[test]
+    fn normalize_id_card_rejects_wrong_check_digit() {
+        assert_eq!(normalize_id_card("440304199001011234"), None);
+        assert_eq!(normalize_id_card("110105194912310021"), None);
+    }
+
+    #[test]
+    fn normalize_id_card_rejects_bad_format() {
+        assert_eq!(normalize_id_card(""), None);
+        assert_eq!(normalize_id_card("44030419900101123"), None);
+        assert_eq!(normalize_id_card("4403041990010112333"), None);
+        assert_eq!(normalize_id_card("4403041990010X1233"), None);
+    }
+
+    #[test]
+    fn normalize_id_card_rejects_invalid_birth_dates() {
+        // 校验码正确，但出生日期不存在或在未来
+        assert_eq!(normalize_id_card("440304199002301232"), None);
+        assert_eq!(normalize_id_card("310115209912310010"), None);
+    }
+
+    #[test]
+    fn mask_id_card_keeps_head_and_tail() {
+        assert_eq!(mask_id_card("440304199001011233"), "4403**********1233");
+        assert_eq!(mask_id_card("12345678"), "********");
+    }
+}
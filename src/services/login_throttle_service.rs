@@ -0,0 +1,243 @@
+use chrono::{DateTime, Utc};
+
+use crate::database::DbPool;
+
+// 超过该时长没有新的失败记录时计数重新开始
+const FAILURE_WINDOW_SECS: f64 = 3600.0;
+// 单个账户
+const ACCOUNT_DELAY_AFTER: i32 = 3;
+const ACCOUNT_CAPTCHA_AFTER: i32 = 3;
+const ACCOUNT_LOCK_AFTER: i32 = 10;
+const ACCOUNT_LOCK_MINUTES: i32 = 15;
+// 单个来源 IP，可能是多人共用的出口地址，阈值放宽
+const IP_DELAY_AFTER: i32 = 10;
+const IP_CAPTCHA_AFTER: i32 = 10;
+const IP_LOCK_AFTER: i32 = 50;
+const IP_LOCK_MINUTES: i32 = 60;
+// 递增等待时间的上限
+const MAX_DELAY_SECS: i64 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scope {
+    Account,
+    Ip,
+}
+
+impl Scope {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Scope::Account => "account",
+            Scope::Ip => "ip",
+        }
+    }
+
+    fn delay_after(&self) -> i32 {
+        match self {
+            Scope::Account => ACCOUNT_DELAY_AFTER,
+            Scope::Ip => IP_DELAY_AFTER,
+        }
+    }
+
+    fn captcha_after(&self) -> i32 {
+        match self {
+            Scope::Account => ACCOUNT_CAPTCHA_AFTER,
+            Scope::Ip => IP_CAPTCHA_AFTER,
+        }
+    }
+
+    fn lock_after(&self) -> i32 {
+        match self {
+            Scope::Account => ACCOUNT_LOCK_AFTER,
+            Scope::Ip => IP_LOCK_AFTER,
+        }
+    }
+
+    fn lock_minutes(&self) -> i32 {
+        match self {
+            Scope::Account => ACCOUNT_LOCK_MINUTES,
+            Scope::Ip => IP_LOCK_MINUTES,
+        }
+    }
+}
+
+/// 登录前的检查结果
+pub enum LoginGate {
+    Allowed { captcha_required: bool },
+    /// 连续失败后需要等待的秒数
+    Delayed(i64),
+    AccountLocked(DateTime<Utc>),
+    IpLocked(DateTime<Utc>),
+}
+
+/// 记录一次失败后的状态
+pub struct FailureOutcome {
+    pub captcha_required: bool,
+    /// 本次失败导致账户被锁定时为锁定截止时间
+    pub account_locked_until: Option<DateTime<Utc>>,
+}
+
+#[derive(sqlx::FromRow)]
+struct ThrottleRow {
+    scope: String,
+    failures: i32,
+    seconds_since_failure: f64,
+    locked_until: Option<DateTime<Utc>>,
+}
+
+/// 第 n 次失败之后的等待时间按 1、2、4… 秒递增
+fn delay_secs(scope: Scope, failures: i32) -> i64 {
+    let over = failures - scope.delay_after();
+    if over < 0 {
+        return 0;
+    }
+    1i64.checked_shl(over as u32).unwrap_or(MAX_DELAY_SECS).min(MAX_DELAY_SECS)
+}
+
+/// 校验密码之前调用，锁定或等待期内直接拒绝
+pub async fn check(pool: &DbPool, account_key: &str, ip_address: &str) -> Result<LoginGate, sqlx::Error> {
+    let rows = sqlx::query_as::<_, ThrottleRow>(
+        r#"
+        SELECT scope, failures, EXTRACT(EPOCH FROM NOW() - last_failure_at)::FLOAT8 AS seconds_since_failure,
+               CASE WHEN locked_until > NOW() THEN locked_until END AS locked_until
+        FROM login_throttles
+        WHERE ((scope = 'account' AND key = $1) OR (scope = 'ip' AND key = $2))
+          AND (last_failure_at > NOW() - make_interval(secs => $3) OR locked_until > NOW())
+        "#
+    )
+    .bind(account_key)
+    .bind(ip_address)
+    .bind(FAILURE_WINDOW_SECS)
+    .fetch_all(pool)
+    .await?;
+
+    let mut captcha_required = false;
+    let mut wait = 0i64;
+    for row in &rows {
+        let scope = if row.scope == Scope::Account.as_str() { Scope::Account } else { Scope::Ip };
+        if let Some(until) = row.locked_until {
+            return Ok(match scope {
+                Scope::Account => LoginGate::AccountLocked(until),
+                Scope::Ip => LoginGate::IpLocked(until),
+            });
+        }
+        captcha_required |= row.failures >= scope.captcha_after();
+        let remaining = delay_secs(scope, row.failures) - row.seconds_since_failure as i64;
+        wait = wait.max(remaining);
+    }
+
+    if wait > 0 {
+        return Ok(LoginGate::Delayed(wait));
+    }
+    Ok(LoginGate::Allowed { captcha_required })
+}
+
+/// 增加一次失败计数，达到阈值时加锁，返回 (失败次数, 新的锁定截止时间)
+async fn increment(pool: &DbPool, scope: Scope, key: &str) -> Result<(i32, Option<DateTime<Utc>>), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let failures = sqlx::query_scalar::<_, i32>(
+        r#"
+        INSERT INTO login_throttles (scope, key, failures, last_failure_at)
+        VALUES ($1, $2, 1, NOW())
+        ON CONFLICT (scope, key) DO UPDATE SET
+            failures = CASE
+                WHEN login_throttles.last_failure_at < NOW() - make_interval(secs => $3)
+                  OR login_throttles.locked_until <= NOW() THEN 1
+                ELSE login_throttles.failures + 1
+            END,
+            locked_until = CASE
+                WHEN login_throttles.locked_until <= NOW() THEN NULL
+                ELSE login_throttles.locked_until
+            END,
+            last_failure_at = NOW()
+        RETURNING failures
+        "#
+    )
+    .bind(scope.as_str())
+    .bind(key)
+    .bind(FAILURE_WINDOW_SECS)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let locked_until = if failures >= scope.lock_after() {
+        sqlx::query_scalar::<_, DateTime<Utc>>(
+            r#"
+            UPDATE login_throttles SET locked_until = NOW() + make_interval(mins => $3)
+            WHERE scope = $1 AND key = $2 AND locked_until IS NULL
+            RETURNING locked_until
+            "#
+        )
+        .bind(scope.as_str())
+        .bind(key)
+        .bind(scope.lock_minutes())
+        .fetch_optional(&mut *tx)
+        .await?
+    } else {
+        None
+    };
+
+    tx.commit().await?;
+    Ok((failures, locked_until))
+}
+
+/// 密码错误时同时累计账户和来源 IP 的失败次数
+pub async fn record_failure(pool: &DbPool, account_key: &str, ip_address: &str) -> Result<FailureOutcome, sqlx::Error> {
+    let (account_failures, account_locked_until) = increment(pool, Scope::Account, account_key).await?;
+    let (ip_failures, _) = increment(pool, Scope::Ip, ip_address).await?;
+
+    Ok(FailureOutcome {
+        captcha_required: account_failures >= Scope::Account.captcha_after()
+            || ip_failures >= Scope::Ip.captcha_after(),
+        account_locked_until,
+    })
+}
+
+/// 登录成功后清空账户计数。来源 IP 的计数不清空，避免用自己的账户重置对其他账户的猜测次数
+pub async fn clear_account(pool: &DbPool, account_key: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM login_throttles WHERE scope = 'account' AND key = $1")
+        .bind(account_key)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// 删除一天内没有新失败且未处于锁定期的记录，由定期维护任务调用
+pub async fn purge_stale(pool: &DbPool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "DELETE FROM login_throttles WHERE last_failure_at < NOW() - INTERVAL '1 day' AND (locked_until IS NULL OR locked_until < NOW())"
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delay_secs_is_zero_before_threshold() {
+        let after = Scope::Account.delay_after();
+        assert_eq!(delay_secs(Scope::Account, 0), 0);
+        assert_eq!(delay_secs(Scope::Account, after - 1), 0);
+    }
+
+    #[test]
+    fn delay_secs_doubles_after_threshold() {
+        for scope in [Scope::Account, Scope::Ip] {
+            let after = scope.delay_after();
+            assert_eq!(delay_secs(scope, after), 1);
+            assert_eq!(delay_secs(scope, after + 1), 2);
+            assert_eq!(delay_secs(scope, after + 2), 4);
+        }
+    }
+
+    #[test]
+    fn delay_secs_is_capped() {
+        let after = Scope::Ip.delay_after();
+        assert_eq!(delay_secs(Scope::Ip, after + 10), MAX_DELAY_SECS);
+        // 位移超出 i64 范围时同样取上限
+        assert_eq!(delay_secs(Scope::Ip, after + 64), MAX_DELAY_SECS);
+        assert_eq!(delay_secs(Scope::Ip, i32::MAX), MAX_DELAY_SECS);
+    }
+}
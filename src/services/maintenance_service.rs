@@ -0,0 +1,41 @@
+use std::time::Duration as StdDuration;
+
+use chrono::Utc;
+use futures::future::BoxFuture;
+use log::{error, info};
+
+use crate::database::DbPool;
+use crate::services::{login_throttle_service, session_service, two_factor_service, verification_service, webauthn_service};
+use crate::utils::metrics::JOB_LAST_RUN;
+
+const JOB_NAME: &str = "maintenance";
+const MAINTENANCE_INTERVAL: StdDuration = StdDuration::from_secs(600);
+
+type Purge = for<'a> fn(&'a DbPool) -> BoxFuture<'a, Result<u64, sqlx::Error>>;
+
+/// 各服务自己的过期数据清理，新的清理项在这里登记
+const PURGES: &[(&str, Purge)] = &[
+    ("过期会话", |pool| Box::pin(session_service::purge_expired(pool))),
+    ("过期登录挑战", |pool| Box::pin(two_factor_service::purge_expired_challenges(pool))),
+    ("过期 WebAuthn 流程", |pool| Box::pin(webauthn_service::purge_expired_ceremonies(pool))),
+    ("过期验证码", |pool| Box::pin(verification_service::purge_old_codes(pool))),
+    ("登录失败记录", |pool| Box::pin(login_throttle_service::purge_stale(pool))),
+];
+
+/// 启动定期清理过期数据的后台任务
+pub fn spawn_maintenance(pool: DbPool) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(MAINTENANCE_INTERVAL);
+        loop {
+            ticker.tick().await;
+            for (name, purge) in PURGES {
+                match purge(&pool).await {
+                    Ok(0) => (),
+                    Ok(count) => info!("已清理 {} 条{}", count, name),
+                    Err(e) => error!("清理{}失败: {}", name, e),
+                }
+            }
+            JOB_LAST_RUN.with_label_values(&[JOB_NAME]).set(Utc::now().timestamp());
+        }
+    });
+}
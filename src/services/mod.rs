@@ -0,0 +1,22 @@
+pub mod account_service;
+pub mod alert_service;
+pub mod api_key_service;
+pub mod audit_service;
+pub mod capacity_service;
+pub mod captcha_service;
+pub mod email_service;
+pub mod email_template;
+pub mod identity_service;
+pub mod login_throttle_service;
+pub mod maintenance_service;
+pub mod node_health_service;
+pub mod organization_service;
+pub mod pve_service;
+pub mod rate_limit_service;
+pub mod revocation_service;
+pub mod session_service;
+pub mod sms_service;
+pub mod storage_service;
+pub mod two_factor_service;
+pub mod verification_service;
+pub mod webauthn_service;
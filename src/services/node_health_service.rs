@@ -0,0 +1,227 @@
+use std::time::Duration as StdDuration;
+
+use chrono::Utc;
+use log::{error, info, warn};
+use serde_json::json;
+
+use crate::database::DbPool;
+use crate::models::pve_node::{PveNode, PveNodeStatus, PVE_NODE_COLUMNS};
+use crate::services::email_service::EmailService;
+use crate::services::pve_service::ProxmoxClient;
+use crate::utils::metrics::{JOB_LAST_RUN, JOB_QUEUE_DEPTH};
+
+const JOB_NAME: &str = "node_health";
+// 节点状态采样保留天数
+const STATUS_RETENTION_DAYS: i32 = 7;
+// 单个节点检查的总时限，避免一个无响应的节点拖住整轮检查
+const NODE_CHECK_TIMEOUT: StdDuration = StdDuration::from_secs(20);
+
+struct HealthConfig {
+    interval: StdDuration,
+    failure_threshold: i32,
+    admin_webhook: Option<String>,
+}
+
+impl HealthConfig {
+    fn from_env() -> Self {
+        let interval = std::env::var("NODE_HEALTH_INTERVAL_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(30);
+        let failure_threshold = std::env::var("NODE_HEALTH_FAILURE_THRESHOLD")
+            .ok()
+            .and_then(|v| v.parse::<i32>().ok())
+            .unwrap_or(3);
+        let admin_webhook = std::env::var("ADMIN_ALERT_WEBHOOK").ok().filter(|v| !v.is_empty());
+
+        HealthConfig {
+            interval: StdDuration::from_secs(interval),
+            failure_threshold,
+            admin_webhook,
+        }
+    }
+}
+
+struct HealthChecker {
+    pool: DbPool,
+    config: HealthConfig,
+    email: Option<EmailService>,
+    http: reqwest::Client,
+}
+
+/// 启动节点心跳检测后台任务
+pub fn spawn_node_health_checker(pool: DbPool) {
+    tokio::spawn(async move {
+        let checker = HealthChecker {
+            pool,
+            config: HealthConfig::from_env(),
+            email: EmailService::from_env().ok(),
+            http: reqwest::Client::builder()
+                .connect_timeout(StdDuration::from_secs(5))
+                .timeout(StdDuration::from_secs(10))
+                .build()
+                .expect("Failed to build admin webhook client"),
+        };
+        let mut ticker = tokio::time::interval(checker.config.interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = checker.check_all().await {
+                error!("节点健康检查失败: {}", e);
+            }
+        }
+    });
+}
+
+impl HealthChecker {
+    async fn check_all(&self) -> Result<(), sqlx::Error> {
+        let nodes = sqlx::query_as::<_, PveNode>(&format!(
+            "SELECT {} FROM pve_nodes WHERE status <> 'disabled'",
+            PVE_NODE_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        let queue_depth = JOB_QUEUE_DEPTH.with_label_values(&[JOB_NAME]);
+        queue_depth.set(nodes.len() as i64);
+        for node in nodes {
+            let result = match ProxmoxClient::for_node(&node) {
+                Ok(client) => tokio::time::timeout(NODE_CHECK_TIMEOUT, client.node_status(&node.name))
+                    .await
+                    .unwrap_or_else(|_| Err(format!("{} 秒内未响应", NODE_CHECK_TIMEOUT.as_secs()))),
+                Err(e) => Err(e),
+            };
+            let recorded = match result {
+                Ok(status) => self.record_success(&node, &status).await,
+                Err(e) => self.record_failure(&node, &e).await,
+            };
+            if let Err(e) = recorded {
+                error!("保存节点 {} 健康状态失败: {}", node.name, e);
+            }
+            queue_depth.dec();
+        }
+
+        sqlx::query("DELETE FROM pve_node_status WHERE recorded_at < NOW() - make_interval(days => $1)")
+            .bind(STATUS_RETENTION_DAYS)
+            .execute(&self.pool)
+            .await?;
+
+        JOB_LAST_RUN.with_label_values(&[JOB_NAME]).set(Utc::now().timestamp());
+        Ok(())
+    }
+
+    async fn record_success(&self, node: &PveNode, status: &PveNodeStatus) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO pve_node_status
+                (pve_node_id, cpu_usage, memory_usage, disk_usage, uptime, load_1, load_5, load_15)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#
+        )
+        .bind(node.id)
+        .bind(status.cpu_usage)
+        .bind(status.memory_usage)
+        .bind(status.disk_usage)
+        .bind(status.uptime)
+        .bind(status.load_average[0])
+        .bind(status.load_average[1])
+        .bind(status.load_average[2])
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            UPDATE pve_nodes
+            SET last_heartbeat = NOW(), consecutive_failures = 0, health_status = 'healthy', last_error = NULL
+            WHERE id = $1
+            "#
+        )
+        .bind(node.id)
+        .execute(&self.pool)
+        .await?;
+
+        if node.health_status == "unhealthy" {
+            info!("节点 {} 已恢复", node.name);
+            self.notify_admins(
+                node,
+                "recovered",
+                &format!("[恢复] 节点 {} 已恢复响应", node.name),
+                &format!(
+                    "节点 {} ({}) 已恢复响应，CPU {:.1}%，内存 {:.1}%，负载 {:.2}",
+                    node.name, node.ip_address, status.cpu_usage, status.memory_usage, status.load_average[0]
+                ),
+            )
+            .await;
+        }
+        Ok(())
+    }
+
+    async fn record_failure(&self, node: &PveNode, reason: &str) -> Result<(), sqlx::Error> {
+        warn!("节点 {} 心跳检测失败: {}", node.name, reason);
+        let failures = node.consecutive_failures + 1;
+        // 连续失败达到阈值后标记为不健康，调度器将跳过该节点
+        let went_dark = failures >= self.config.failure_threshold && node.health_status != "unhealthy";
+        let health_status = if failures >= self.config.failure_threshold {
+            "unhealthy"
+        } else {
+            node.health_status.as_str()
+        };
+
+        sqlx::query(
+            "UPDATE pve_nodes SET consecutive_failures = $2, health_status = $3, last_error = $4 WHERE id = $1"
+        )
+        .bind(node.id)
+        .bind(failures)
+        .bind(health_status)
+        .bind(reason)
+        .execute(&self.pool)
+        .await?;
+
+        if went_dark {
+            let last_seen = node
+                .last_heartbeat
+                .map(|t| t.to_rfc3339())
+                .unwrap_or_else(|| "从未".to_string());
+            self.notify_admins(
+                node,
+                "unhealthy",
+                &format!("[告警] 节点 {} 失去响应", node.name),
+                &format!(
+                    "节点 {} ({}) 连续 {} 次心跳检测失败，已停止调度。最后心跳: {}，错误: {}",
+                    node.name, node.ip_address, failures, last_seen, reason
+                ),
+            )
+            .await;
+        }
+        Ok(())
+    }
+
+    /// 通过邮件和可选的 Webhook 通知所有管理员
+    async fn notify_admins(&self, node: &PveNode, status: &str, subject: &str, body: &str) {
+        if let Some(email) = &self.email {
+            let recipients = sqlx::query_scalar::<_, String>(
+                "SELECT email FROM users WHERE role = 'admin' AND status = 'active' AND email IS NOT NULL"
+            )
+            .fetch_all(&self.pool)
+            .await
+            .unwrap_or_default();
+            for to in recipients {
+                if let Err(e) = email.send(&to, subject, body).await {
+                    warn!("发送节点告警邮件到 {} 失败: {}", to, e);
+                }
+            }
+        }
+
+        if let Some(url) = &self.config.admin_webhook {
+            let payload = json!({
+                "node_id": node.id,
+                "node_name": node.name,
+                "status": status,
+                "message": body,
+                "timestamp": Utc::now().to_rfc3339()
+            });
+            if let Err(e) = self.http.post(url).json(&payload).send().await {
+                warn!("发送节点告警 Webhook 失败: {}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{PgConnection, PgExecutor};
+use uuid::Uuid;
+
+use crate::database::DbPool;
+use crate::models::organization::{OrgRole, OrganizationInvitation, INVITATION_COLUMNS};
+use crate::utils::crypto::{random_token, sha256_hex};
+
+/// 邀请链接有效期
+pub const INVITATION_TTL_DAYS: i64 = 7;
+
+/// 邀请链接指向的前端页面，APP_BASE_URL 未设置时使用本地开发地址
+pub fn invitation_url(token: &str) -> String {
+    let base = std::env::var("APP_BASE_URL").unwrap_or_else(|_| "http://localhost:8081".to_string());
+    format!("{}/invitations/accept?token={}", base.trim_end_matches('/'), token)
+}
+
+/// 用户在组织中的角色，不是成员时返回 None
+pub async fn member_role<'e, E>(executor: E, organization_id: Uuid, user_id: Uuid) -> Result<Option<OrgRole>, sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    let role = sqlx::query_scalar::<_, String>(
+        "SELECT role FROM organization_members WHERE organization_id = $1 AND user_id = $2"
+    )
+    .bind(organization_id)
+    .bind(user_id)
+    .fetch_optional(executor)
+    .await?;
+    Ok(role.as_deref().and_then(OrgRole::parse))
+}
+
+/// 锁定组织的全部所有者行并计数，用于保证组织至少保留一名所有者
+pub async fn lock_owner_count(conn: &mut PgConnection, organization_id: Uuid) -> Result<usize, sqlx::Error> {
+    let owners = sqlx::query_scalar::<_, Uuid>(
+        "SELECT user_id FROM organization_members WHERE organization_id = $1 AND role = 'owner' FOR UPDATE"
+    )
+    .bind(organization_id)
+    .fetch_all(&mut *conn)
+    .await?;
+    Ok(owners.len())
+}
+
+/// 用户作为唯一所有者的组织数，这些组织需先转让才能注销账户
+pub async fn sole_owned_count<'e, E>(executor: E, user_id: Uuid) -> Result<i64, sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    sqlx::query_scalar::<_, i64>(
+        r#"
+        SELECT COUNT(*) FROM organization_members m
+        WHERE m.user_id = $1 AND m.role = 'owner'
+          AND NOT EXISTS (
+              SELECT 1 FROM organization_members o
+              WHERE o.organization_id = m.organization_id AND o.role = 'owner' AND o.user_id <> $1
+          )
+        "#
+    )
+    .bind(user_id)
+    .fetch_one(executor)
+    .await
+}
+
+/// 生成邀请，同一邮箱之前未接受的邀请随之作废。返回明文令牌，只用于发送邮件
+pub async fn create_invitation(
+    pool: &DbPool,
+    organization_id: Uuid,
+    email: &str,
+    role: OrgRole,
+    invited_by: Uuid,
+) -> Result<(String, OrganizationInvitation), sqlx::Error> {
+    let token = random_token(32);
+    let expires_at = Utc::now() + Duration::days(INVITATION_TTL_DAYS);
+
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "DELETE FROM organization_invitations WHERE organization_id = $1 AND LOWER(email) = LOWER($2) AND accepted_at IS NULL"
+    )
+    .bind(organization_id)
+    .bind(email)
+    .execute(&mut *tx)
+    .await?;
+
+    let invitation = sqlx::query_as::<_, OrganizationInvitation>(&format!(
+        r#"
+        INSERT INTO organization_invitations (organization_id, email, role, token_hash, invited_by, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING {}
+        "#,
+        INVITATION_COLUMNS
+    ))
+    .bind(organization_id)
+    .bind(email)
+    .bind(role.as_str())
+    .bind(sha256_hex(&token))
+    .bind(invited_by)
+    .bind(expires_at)
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok((token, invitation))
+}
+
+/// 邮件发送失败时删除刚生成的邀请
+pub async fn discard_invitation(pool: &DbPool, id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM organization_invitations WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub enum AcceptOutcome {
+    Joined { organization_id: Uuid, role: OrgRole },
+    AlreadyMember,
+    Invalid,
+}
+
+/// 用邀请令牌加入组织，邀请只能使用一次
+pub async fn accept_invitation(pool: &DbPool, token: &str, user_id: Uuid) -> Result<AcceptOutcome, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let invitation = sqlx::query_as::<_, (Uuid, Uuid, String, DateTime<Utc>)>(
+        r#"
+        SELECT id, organization_id, role, expires_at FROM organization_invitations
+        WHERE token_hash = $1 AND accepted_at IS NULL
+        FOR UPDATE
+        "#
+    )
+    .bind(sha256_hex(token))
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some((invitation_id, organization_id, role, expires_at)) = invitation else {
+        return Ok(AcceptOutcome::Invalid);
+    };
+    let Some(role) = OrgRole::parse(&role).filter(|_| expires_at > Utc::now()) else {
+        return Ok(AcceptOutcome::Invalid);
+    };
+
+    let inserted = sqlx::query(
+        r#"
+        INSERT INTO organization_members (organization_id, user_id, role)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (organization_id, user_id) DO NOTHING
+        "#
+    )
+    .bind(organization_id)
+    .bind(user_id)
+    .bind(role.as_str())
+    .execute(&mut *tx)
+    .await?
+    .rows_affected()
+        > 0;
+
+    sqlx::query("UPDATE organization_invitations SET accepted_by = $2, accepted_at = NOW() WHERE id = $1")
+        .bind(invitation_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(if inserted {
+        AcceptOutcome::Joined { organization_id, role }
+    } else {
+        AcceptOutcome::AlreadyMember
+    })
+}
@@ -0,0 +1,193 @@
+use std::net::IpAddr;
+use std::time::Duration;
+
+use reqwest::header::AUTHORIZATION;
+use reqwest::{Client as ReqwestClient, Method};
+use serde_json::Value;
+
+use crate::models::pve_node::{PveNode, PveNodeStatus, PveStorageUsage, PveVmAllocation};
+use crate::utils::metrics::{PROXMOX_REQUEST_DURATION, PROXMOX_REQUEST_ERRORS};
+
+pub struct ProxmoxClient {
+    client: ReqwestClient,
+    base_url: String,
+    auth_header: String,
+    node: String,
+}
+
+impl ProxmoxClient {
+    pub fn new() -> Self {
+        dotenv::dotenv().ok();
+        let url = std::env::var("PROXMOX_URL").unwrap();
+        let realm = std::env::var("PROXMOX_REALM").unwrap();
+        let username = std::env::var("PROXMOX_USERNAME").unwrap();
+        let token_name = std::env::var("PROXMOX_TOKEN_NAME").unwrap();
+        let token_secret = std::env::var("PROXMOX_TOKEN_SECRET").unwrap();
+
+        let base_url = url.trim_end_matches('/').trim_end_matches("/api2/json").to_string();
+        let auth_header = format!("PVEAPIToken={username}@{realm}!{token_name}={token_secret}");
+
+        // 监控指标按 PVE 主机区分
+        let node = reqwest::Url::parse(&base_url)
+            .ok()
+            .and_then(|u| u.host_str().map(|h| h.to_string()))
+            .unwrap_or_else(|| base_url.clone());
+
+        ProxmoxClient {
+            client: build_http_client(),
+            base_url,
+            auth_header,
+            node,
+        }
+    }
+
+    /// 使用 pve_nodes 中登记的地址和 API 令牌（格式 user@realm!tokenid=secret）访问单个节点
+    pub fn for_node(node: &PveNode) -> Result<Self, String> {
+        let api_token = node
+            .api_token
+            .as_deref()
+            .filter(|t| !t.is_empty())
+            .ok_or_else(|| format!("节点 {} 未配置 API 令牌", node.name))?;
+
+        Ok(ProxmoxClient {
+            client: build_http_client(),
+            base_url: node_base_url(&node.ip_address, node.port),
+            auth_header: format!("PVEAPIToken={}", api_token),
+            node: node.name.clone(),
+        })
+    }
+
+    pub async fn make_request(&self, endpoint: &str) -> Result<Value, String> {
+        self.request(Method::POST, endpoint).await
+    }
+
+    pub async fn get(&self, endpoint: &str) -> Result<Value, String> {
+        self.request(Method::GET, endpoint).await
+    }
+
+    /// 读取 /nodes/{node}/status 并换算为百分比
+    pub async fn node_status(&self, node_name: &str) -> Result<PveNodeStatus, String> {
+        let resp = self.get(&format!("nodes/{}/status", node_name)).await?;
+        let data = &resp["data"];
+
+        let percent = |used: &Value, total: &Value| match (used.as_f64(), total.as_f64()) {
+            (Some(used), Some(total)) if total > 0.0 => used / total * 100.0,
+            _ => 0.0,
+        };
+        // PVE 返回的 loadavg 是字符串数组
+        let load = |i: usize| {
+            data["loadavg"][i]
+                .as_str()
+                .and_then(|v| v.parse::<f64>().ok())
+                .unwrap_or(0.0)
+        };
+
+        Ok(PveNodeStatus {
+            cpu_usage: data["cpu"].as_f64().ok_or("节点状态缺少 cpu 字段")? * 100.0,
+            memory_usage: percent(&data["memory"]["used"], &data["memory"]["total"]),
+            disk_usage: percent(&data["rootfs"]["used"], &data["rootfs"]["total"]),
+            uptime: data["uptime"].as_i64().unwrap_or(0),
+            load_average: [load(0), load(1), load(2)],
+        })
+    }
+
+    /// 列出节点上的全部 QEMU 虚拟机及其分配的资源
+    pub async fn list_vms(&self, node_name: &str) -> Result<Vec<PveVmAllocation>, String> {
+        let resp = self.get(&format!("nodes/{}/qemu", node_name)).await?;
+        let vms = resp["data"].as_array().ok_or("虚拟机列表格式错误")?;
+
+        Ok(vms
+            .iter()
+            .filter_map(|vm| {
+                Some(PveVmAllocation {
+                    vmid: vm["vmid"].as_i64()? as i32,
+                    cpus: vm["cpus"].as_f64().unwrap_or(0.0).ceil() as i32,
+                    memory_bytes: vm["maxmem"].as_u64().unwrap_or(0),
+                    disk_bytes: vm["maxdisk"].as_u64().unwrap_or(0),
+                    template: vm["template"].as_u64().unwrap_or(0) == 1,
+                })
+            })
+            .collect())
+    }
+
+    /// 列出节点上已启用的存储及其使用量
+    pub async fn list_storage(&self, node_name: &str) -> Result<Vec<PveStorageUsage>, String> {
+        let resp = self.get(&format!("nodes/{}/storage?enabled=1", node_name)).await?;
+        let storages = resp["data"].as_array().ok_or("存储列表格式错误")?;
+
+        Ok(storages
+            .iter()
+            .filter(|s| s["active"].as_u64().unwrap_or(1) == 1)
+            .map(|s| {
+                PveStorageUsage {
+                    shared: s["shared"].as_u64().unwrap_or(0) == 1,
+                    used_bytes: s["used"].as_u64().unwrap_or(0),
+                    total_bytes: s["total"].as_u64().unwrap_or(0),
+                }
+            })
+            .collect())
+    }
+
+    async fn request(&self, method: Method, endpoint: &str) -> Result<Value, String> {
+        let api_url = format!("{}/api2/json/{}", self.base_url, endpoint.trim_start_matches('/'));
+
+        let timer = PROXMOX_REQUEST_DURATION
+            .with_label_values(&[&self.node])
+            .start_timer();
+        let result = match self.client.request(method, &api_url)
+            .header(AUTHORIZATION, &self.auth_header)
+            .send()
+            .await
+        {
+            Ok(resp) => {
+                if resp.status().is_success() {
+                    resp.json::<Value>().await.map_err(|e| e.to_string())
+                } else {
+                    Err(resp.status().to_string())
+                }
+            },
+            Err(e) => Err(e.to_string())
+        };
+        timer.observe_duration();
+
+        if result.is_err() {
+            PROXMOX_REQUEST_ERRORS.with_label_values(&[&self.node]).inc();
+        }
+        result
+    }
+}
+
+impl Default for ProxmoxClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// IPv6 地址在 URL 中需加方括号
+fn node_base_url(address: &str, port: i32) -> String {
+    match address.parse::<IpAddr>() {
+        Ok(IpAddr::V6(v6)) => format!("https://[{}]:{}", v6, port),
+        _ => format!("https://{}:{}", address, port),
+    }
+}
+
+fn build_http_client() -> ReqwestClient {
+    ReqwestClient::builder()
+        .danger_accept_invalid_certs(true)
+        .connect_timeout(Duration::from_secs(5))
+        .timeout(Duration::from_secs(15))
+        .build()
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn node_base_url_brackets_ipv6_hosts() {
+        assert_eq!(node_base_url("192.0.2.10", 8006), "https://192.0.2.10:8006");
+        assert_eq!(node_base_url("2001:db8::10", 8006), "https://[2001:db8::10]:8006");
+        assert_eq!(node_base_url("pve1.example.com", 443), "https://pve1.example.com:443");
+    }
+}
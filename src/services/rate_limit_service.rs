@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration as StdDuration, Instant, SystemTime, UNIX_EPOCH};
+
+use log::{error, info};
+use redis::aio::ConnectionManager;
+use redis::Script;
+
+const CLEANUP_INTERVAL: StdDuration = StdDuration::from_secs(60);
+const REDIS_KEY_PREFIX: &str = "openvirt:ratelimit:";
+
+// 令牌桶：桶中没有令牌时拒绝请求，令牌按固定速率补充到容量为止
+const TOKEN_BUCKET_SCRIPT: &str = r#"
+local capacity = tonumber(ARGV[1])
+local per_ms = tonumber(ARGV[2])
+local now = tonumber(ARGV[3])
+local state = redis.call('HMGET', KEYS[1], 'tokens', 'ts')
+local tokens = tonumber(state[1]) or capacity
+local ts = tonumber(state[2]) or now
+tokens = math.min(capacity, tokens + math.max(0, now - ts) * per_ms)
+local allowed = 0
+if tokens >= 1 then
+    tokens = tokens - 1
+    allowed = 1
+end
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'ts', now)
+redis.call('PEXPIRE', KEYS[1], math.ceil(capacity / per_ms) + 1000)
+return {allowed, tostring(tokens)}
+"#;
+
+/// 一类接口的限流参数
+#[derive(Debug, Clone, Copy)]
+pub struct Limit {
+    /// 规则名，用于区分存储的键
+    pub name: &'static str,
+    /// 桶容量，即允许的突发请求数
+    pub capacity: u32,
+    /// 每秒补充的令牌数
+    pub refill_per_sec: f64,
+}
+
+/// 单次请求的限流结果
+pub struct Decision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    /// 桶补满所需秒数
+    pub reset_secs: u64,
+    /// 被拒绝时距离下一个令牌的秒数
+    pub retry_after_secs: u64,
+}
+
+impl Decision {
+    fn from_tokens(limit: &Limit, allowed: bool, tokens: f64) -> Self {
+        let seconds_until = |target: f64| ((target - tokens).max(0.0) / limit.refill_per_sec).ceil() as u64;
+        Decision {
+            allowed,
+            limit: limit.capacity,
+            remaining: tokens.floor().max(0.0) as u32,
+            reset_secs: seconds_until(limit.capacity as f64),
+            retry_after_secs: if allowed { 0 } else { seconds_until(1.0).max(1) },
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+#[derive(Default)]
+struct MemoryStore {
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl MemoryStore {
+    fn take(&self, key: String, limit: &Limit) -> Decision {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets.entry(key).or_insert(Bucket {
+            tokens: limit.capacity as f64,
+            updated: now,
+        });
+
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * limit.refill_per_sec).min(limit.capacity as f64);
+        bucket.updated = now;
+
+        let allowed = bucket.tokens >= 1.0;
+        if allowed {
+            bucket.tokens -= 1.0;
+        }
+        Decision::from_tokens(limit, allowed, bucket.tokens)
+    }
+
+    /// 长时间未访问的桶早已补满，直接丢弃
+    fn prune(&self, idle: StdDuration) {
+        let now = Instant::now();
+        self.buckets
+            .lock()
+            .unwrap()
+            .retain(|_, bucket| now.duration_since(bucket.updated) < idle);
+    }
+}
+
+/// 多实例部署时共享计数
+struct RedisStore {
+    connection: ConnectionManager,
+    script: Script,
+}
+
+impl RedisStore {
+    async fn take(&self, key: String, limit: &Limit) -> Result<Decision, redis::RedisError> {
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        let (allowed, tokens): (i64, String) = self
+            .script
+            .key(format!("{}{}", REDIS_KEY_PREFIX, key))
+            .arg(limit.capacity)
+            .arg(limit.refill_per_sec / 1000.0)
+            .arg(now_ms)
+            .invoke_async(&mut self.connection.clone())
+            .await?;
+        Ok(Decision::from_tokens(limit, allowed == 1, tokens.parse().unwrap_or(0.0)))
+    }
+}
+
+enum Backend {
+    Memory(MemoryStore),
+    Redis(RedisStore),
+}
+
+pub struct RateLimiter {
+    backend: Backend,
+}
+
+impl RateLimiter {
+    /// 配置 RATE_LIMIT_REDIS_URL 时使用 Redis（或兼容协议的服务）保存令牌桶，否则保存在进程内
+    pub async fn from_env() -> Result<Self, redis::RedisError> {
+        let backend = match std::env::var("RATE_LIMIT_REDIS_URL") {
+            Ok(url) => {
+                let client = redis::Client::open(url)?;
+                let connection = ConnectionManager::new(client).await?;
+                info!("限流计数使用 Redis 存储");
+                Backend::Redis(RedisStore {
+                    connection,
+                    script: Script::new(TOKEN_BUCKET_SCRIPT),
+                })
+            }
+            Err(_) => Backend::Memory(MemoryStore::default()),
+        };
+        Ok(RateLimiter { backend })
+    }
+
+    /// 取一个令牌。Redis 不可用时放行，避免限流故障导致整站不可用
+    pub async fn check(&self, key: &str, limit: &Limit) -> Decision {
+        let key = format!("{}:{}", limit.name, key);
+        match &self.backend {
+            Backend::Memory(store) => store.take(key, limit),
+            Backend::Redis(store) => match store.take(key, limit).await {
+                Ok(decision) => decision,
+                Err(e) => {
+                    error!("限流计数失败: {}", e);
+                    Decision::from_tokens(limit, true, limit.capacity as f64)
+                }
+            },
+        }
+    }
+}
+
+/// 定期清理进程内的空闲令牌桶，Redis 中的键依靠过期时间自动清理
+pub fn spawn_rate_limit_cleanup(limiter: Arc<RateLimiter>) {
+    let Backend::Memory(_) = limiter.backend else {
+        return;
+    };
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(CLEANUP_INTERVAL);
+        loop {
+            ticker.tick().await;
+            if let Backend::Memory(store) = &limiter.backend {
+                store.prune(CLEANUP_INTERVAL * 10);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMIT: Limit = Limit { name: "test", capacity: 3, refill_per_sec: 1.0 };
+
+    /// 把桶的更新时间往前拨，模拟时间流逝
+    fn rewind(store: &MemoryStore, key: &str, secs: u64) {
+        let mut buckets = store.buckets.lock().unwrap();
+        let bucket = buckets.get_mut(key).unwrap();
+        bucket.updated -= StdDuration::from_secs(secs);
+    }
+
+    #[test]
+    fn allows_burst_up_to_capacity_then_rejects() {
+        let store = MemoryStore::default();
+        for expected in [2, 1, 0] {
+            let decision = store.take("k".into(), &LIMIT);
+            assert!(decision.allowed);
+            assert_eq!(decision.remaining, expected);
+        }
+
+        let rejected = store.take("k".into(), &LIMIT);
+        assert!(!rejected.allowed);
+        assert_eq!(rejected.remaining, 0);
+        assert_eq!(rejected.retry_after_secs, 1);
+        assert_eq!(rejected.reset_secs, 3);
+    }
+
+    #[test]
+    fn tokens_refill_over_time_up_to_capacity() {
+        let store = MemoryStore::default();
+        for _ in 0..3 {
+            store.take("k".into(), &LIMIT);
+        }
+        rewind(&store, "k", 2);
+        assert!(store.take("k".into(), &LIMIT).allowed);
+        assert!(store.take("k".into(), &LIMIT).allowed);
+        assert!(!store.take("k".into(), &LIMIT).allowed);
+
+        // 长时间空闲后最多补满到容量
+        rewind(&store, "k", 3600);
+        assert_eq!(store.take("k".into(), &LIMIT).remaining, 2);
+    }
+
+    #[test]
+    fn keys_are_counted_separately() {
+        let store = MemoryStore::default();
+        for _ in 0..3 {
+            store.take("a".into(), &LIMIT);
+        }
+        assert!(!store.take("a".into(), &LIMIT).allowed);
+        assert!(store.take("b".into(), &LIMIT).allowed);
+    }
+
+    #[test]
+    fn prune_drops_idle_buckets() {
+        let store = MemoryStore::default();
+        store.take("idle".into(), &LIMIT);
+        store.take("busy".into(), &LIMIT);
+        rewind(&store, "idle", 600);
+        store.prune(StdDuration::from_secs(300));
+        let buckets = store.buckets.lock().unwrap();
+        assert!(!buckets.contains_key("idle"));
+        assert!(buckets.contains_key("busy"));
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration as StdDuration;
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use log::{error, info};
+use uuid::Uuid;
+
+use crate::database::DbPool;
+use crate::utils::crypto::sha256_hex;
+use crate::services::session_service;
+use crate::utils::jwt::Claims;
+
+// 未吊销结果的缓存时间，多实例部署时其他实例的吊销最多延迟这么久生效
+const NEGATIVE_CACHE_TTL_SECS: i64 = 30;
+// 会话和账户状态的缓存时间，本实例内吊销会话时立即清除对应缓存
+const SESSION_CACHE_TTL_SECS: i64 = 30;
+const CLEANUP_INTERVAL: StdDuration = StdDuration::from_secs(600);
+
+#[derive(Clone, Copy)]
+struct CachedStatus {
+    revoked: bool,
+    valid_until: DateTime<Utc>,
+}
+
+#[derive(Clone)]
+struct CachedSession {
+    role: Option<String>,
+    valid_until: DateTime<Utc>,
+}
+
+/// 访问令牌吊销表和会话状态，数据库为准，内存缓存避免每个请求都查库
+#[derive(Default)]
+pub struct RevocationStore {
+    cache: RwLock<HashMap<String, CachedStatus>>,
+    sessions: RwLock<HashMap<(Uuid, Uuid), CachedSession>>,
+}
+
+fn token_expiry(claims: &Claims) -> DateTime<Utc> {
+    Utc.timestamp_opt(claims.exp as i64, 0).single().unwrap_or_else(Utc::now)
+}
+
+impl RevocationStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn is_revoked(&self, pool: &DbPool, claims: &Claims) -> Result<bool, sqlx::Error> {
+        let jti_hash = sha256_hex(&claims.jti);
+        let now = Utc::now();
+
+        let cached = self.cache.read().unwrap().get(&jti_hash).copied();
+        if let Some(status) = cached.filter(|status| status.valid_until > now) {
+            return Ok(status.revoked);
+        }
+
+        let revoked = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS(SELECT 1 FROM revoked_tokens WHERE jti_hash = $1)"
+        )
+        .bind(&jti_hash)
+        .fetch_one(pool)
+        .await?;
+
+        // 已吊销的结果一直有效到令牌过期
+        let valid_until = if revoked {
+            token_expiry(claims)
+        } else {
+            now + Duration::seconds(NEGATIVE_CACHE_TTL_SECS)
+        };
+        self.cache
+            .write()
+            .unwrap()
+            .insert(jti_hash, CachedStatus { revoked, valid_until });
+
+        Ok(revoked)
+    }
+
+    /// 吊销令牌，返回是否为首次吊销（并发刷新时只有一个请求能成功）
+    pub async fn revoke(&self, pool: &DbPool, claims: &Claims) -> Result<bool, sqlx::Error> {
+        let jti_hash = sha256_hex(&claims.jti);
+        let expires_at = token_expiry(claims);
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO revoked_tokens (jti_hash, expires_at)
+            VALUES ($1, $2)
+            ON CONFLICT (jti_hash) DO NOTHING
+            "#
+        )
+        .bind(&jti_hash)
+        .bind(expires_at)
+        .execute(pool)
+        .await?;
+
+        self.cache.write().unwrap().insert(
+            jti_hash,
+            CachedStatus { revoked: true, valid_until: expires_at },
+        );
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 访问令牌所属账户的当前角色，账户不可用或会话已吊销时为 None，结果短时缓存
+    pub async fn session_role(&self, pool: &DbPool, user_id: Uuid, session_id: Uuid) -> Result<Option<String>, sqlx::Error> {
+        let now = Utc::now();
+        let cached = self.sessions.read().unwrap().get(&(user_id, session_id)).cloned();
+        if let Some(session) = cached.filter(|session| session.valid_until > now) {
+            return Ok(session.role);
+        }
+
+        let role = session_service::active_session_role(pool, user_id, session_id).await?;
+        self.sessions.write().unwrap().insert(
+            (user_id, session_id),
+            CachedSession { role: role.clone(), valid_until: now + Duration::seconds(SESSION_CACHE_TTL_SECS) },
+        );
+        Ok(role)
+    }
+
+    /// 吊销会话、调整角色或注销账户后调用，使本实例立即按数据库重新判断
+    pub fn forget_user(&self, user_id: Uuid) {
+        self.sessions.write().unwrap().retain(|(cached_user, _), _| *cached_user != user_id);
+    }
+
+    fn prune_cache(&self) {
+        let now = Utc::now();
+        self.cache.write().unwrap().retain(|_, status| status.valid_until > now);
+        self.sessions.write().unwrap().retain(|_, session| session.valid_until > now);
+    }
+}
+
+/// 定期清理已过期的吊销记录和缓存
+pub fn spawn_revocation_cleanup(pool: DbPool, store: Arc<RevocationStore>) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(CLEANUP_INTERVAL);
+        loop {
+            ticker.tick().await;
+            store.prune_cache();
+            match sqlx::query("DELETE FROM revoked_tokens WHERE expires_at < NOW()")
+                .execute(&pool)
+                .await
+            {
+                Ok(result) if result.rows_affected() > 0 => {
+                    info!("已清理 {} 条过期的令牌吊销记录", result.rows_affected());
+                }
+                Ok(_) => (),
+                Err(e) => error!("清理令牌吊销记录失败: {}", e),
+            }
+        }
+    });
+}
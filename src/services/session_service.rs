@@ -0,0 +1,286 @@
+use chrono::{DateTime, Duration, Utc};
+use log::warn;
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::database::DbPool;
+use crate::models::user::{SessionInfo, UserSession};
+use crate::utils::crypto::{random_token, sha256_hex};
+
+const REFRESH_TOKEN_BYTES: usize = 32;
+
+pub fn access_token_ttl() -> Duration {
+    let minutes = std::env::var("ACCESS_TOKEN_TTL_MINUTES")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .unwrap_or(15);
+    Duration::minutes(minutes)
+}
+
+pub fn refresh_token_ttl() -> Duration {
+    let days = std::env::var("REFRESH_TOKEN_TTL_DAYS")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .unwrap_or(30);
+    Duration::days(days)
+}
+
+/// 新签发的刷新令牌，明文只在响应中出现一次
+pub struct IssuedRefreshToken {
+    pub token: String,
+    pub family_id: Uuid,
+    pub expires_at: DateTime<Utc>,
+}
+
+pub enum RotateOutcome {
+    Rotated {
+        user_id: Uuid,
+        refresh: IssuedRefreshToken,
+    },
+    Invalid,
+    /// 已轮换或已吊销的令牌被再次使用，整个 family 已被吊销
+    Reused,
+}
+
+/// 刷新令牌在轮换前的状态
+#[derive(Debug, PartialEq, Eq)]
+enum TokenState {
+    Usable,
+    Expired,
+    /// 已轮换或已吊销，再次出现说明令牌被重放
+    Replayed,
+}
+
+fn token_state(session: &UserSession, now: DateTime<Utc>) -> TokenState {
+    if session.rotated_at.is_some() || session.revoked_at.is_some() {
+        TokenState::Replayed
+    } else if session.expires_at <= now {
+        TokenState::Expired
+    } else {
+        TokenState::Usable
+    }
+}
+
+async fn insert_token(
+    pool: &DbPool,
+    user_id: Uuid,
+    family_id: Uuid,
+    ip_address: &str,
+    user_agent: Option<&str>,
+) -> Result<IssuedRefreshToken, sqlx::Error> {
+    let token = random_token(REFRESH_TOKEN_BYTES);
+    let expires_at = Utc::now() + refresh_token_ttl();
+
+    sqlx::query(
+        r#"
+        INSERT INTO user_sessions (user_id, token_hash, family_id, ip_address, user_agent, expires_at)
+        VALUES ($1, $2, $3, $4::INET, $5, $6)
+        "#
+    )
+    .bind(user_id)
+    .bind(sha256_hex(&token))
+    .bind(family_id)
+    .bind(ip_address)
+    .bind(user_agent)
+    .bind(expires_at)
+    .execute(pool)
+    .await?;
+
+    Ok(IssuedRefreshToken { token, family_id, expires_at })
+}
+
+/// 登录成功后创建新的会话 family
+pub async fn create_session(
+    pool: &DbPool,
+    user_id: Uuid,
+    ip_address: &str,
+    user_agent: Option<&str>,
+) -> Result<IssuedRefreshToken, sqlx::Error> {
+    insert_token(pool, user_id, Uuid::new_v4(), ip_address, user_agent).await
+}
+
+/// 用刷新令牌换取新令牌，旧令牌作废
+pub async fn rotate(
+    pool: &DbPool,
+    refresh_token: &str,
+    ip_address: &str,
+    user_agent: Option<&str>,
+) -> Result<RotateOutcome, sqlx::Error> {
+    let session = match sqlx::query_as::<_, UserSession>(
+        "SELECT id, user_id, token_hash, family_id, expires_at, rotated_at, revoked_at FROM user_sessions WHERE token_hash = $1"
+    )
+    .bind(sha256_hex(refresh_token))
+    .fetch_optional(pool)
+    .await?
+    {
+        Some(session) => session,
+        None => return Ok(RotateOutcome::Invalid),
+    };
+
+    match token_state(&session, Utc::now()) {
+        TokenState::Replayed => {
+            warn!("检测到刷新令牌重放，吊销会话 {} (用户 {})", session.family_id, session.user_id);
+            revoke_family(pool, session.family_id).await?;
+            return Ok(RotateOutcome::Reused);
+        }
+        TokenState::Expired => return Ok(RotateOutcome::Invalid),
+        TokenState::Usable => (),
+    }
+
+    // 条件更新保证并发请求中只有一个能完成轮换，其余视为重放
+    let claimed = sqlx::query(
+        "UPDATE user_sessions SET rotated_at = NOW() WHERE id = $1 AND rotated_at IS NULL AND revoked_at IS NULL"
+    )
+    .bind(session.id)
+    .execute(pool)
+    .await?;
+    if claimed.rows_affected() == 0 {
+        revoke_family(pool, session.family_id).await?;
+        return Ok(RotateOutcome::Reused);
+    }
+
+    let active = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM users WHERE id = $1 AND status = 'active')"
+    )
+    .bind(session.user_id)
+    .fetch_one(pool)
+    .await?;
+    if !active {
+        revoke_family(pool, session.family_id).await?;
+        return Ok(RotateOutcome::Invalid);
+    }
+
+    let refresh = insert_token(pool, session.user_id, session.family_id, ip_address, user_agent).await?;
+    Ok(RotateOutcome::Rotated { user_id: session.user_id, refresh })
+}
+
+pub async fn revoke_family(pool: &DbPool, family_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE user_sessions SET revoked_at = NOW() WHERE family_id = $1 AND revoked_at IS NULL")
+        .bind(family_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// 吊销用户的某个会话，返回是否存在该会话
+pub async fn revoke_user_session(pool: &DbPool, user_id: Uuid, family_id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE user_sessions SET revoked_at = NOW() WHERE user_id = $1 AND family_id = $2 AND revoked_at IS NULL"
+    )
+    .bind(user_id)
+    .bind(family_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// 吊销除当前会话外的全部会话，返回吊销的设备数
+pub async fn revoke_other_sessions(pool: &DbPool, user_id: Uuid, current_family: Uuid) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE user_sessions SET revoked_at = NOW()
+        WHERE user_id = $1 AND family_id <> $2 AND revoked_at IS NULL AND rotated_at IS NULL
+        "#
+    )
+    .bind(user_id)
+    .bind(current_family)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+pub async fn list_sessions(pool: &DbPool, user_id: Uuid) -> Result<Vec<SessionInfo>, sqlx::Error> {
+    sqlx::query_as::<_, SessionInfo>(
+        r#"
+        SELECT s.family_id AS id,
+               host(s.ip_address) AS ip_address,
+               s.user_agent,
+               (SELECT MIN(f.created_at) FROM user_sessions f WHERE f.family_id = s.family_id) AS signed_in_at,
+               s.created_at AS last_active_at,
+               s.expires_at
+        FROM user_sessions s
+        WHERE s.user_id = $1 AND s.rotated_at IS NULL AND s.revoked_at IS NULL AND s.expires_at > NOW()
+        ORDER BY s.created_at DESC
+        "#
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// 访问令牌所属账户的当前角色。账户已停用或注销、会话已被吊销时返回 None，
+/// 使封禁、注销、调整角色和吊销会话对尚未过期的访问令牌立即生效
+pub async fn active_session_role(pool: &DbPool, user_id: Uuid, family_id: Uuid) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT u.role FROM users u
+        WHERE u.id = $1 AND u.status = 'active'
+          AND EXISTS (
+              SELECT 1 FROM user_sessions s
+              WHERE s.family_id = $2 AND s.user_id = u.id AND s.rotated_at IS NULL AND s.revoked_at IS NULL
+          )
+        "#
+    )
+    .bind(user_id)
+    .bind(family_id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// 吊销用户的全部会话，如角色变更或重置密码后要求重新登录
+pub async fn revoke_all_sessions<'e, E>(executor: E, user_id: Uuid) -> Result<u64, sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    let result = sqlx::query("UPDATE user_sessions SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL")
+        .bind(user_id)
+        .execute(executor)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// 删除已过期的刷新令牌，由定期维护任务调用
+pub async fn purge_expired(pool: &DbPool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM user_sessions WHERE expires_at < NOW()").execute(pool).await?;
+    Ok(result.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(expires_in: Duration, rotated: bool, revoked: bool) -> UserSession {
+        let now = Utc::now();
+        UserSession {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            token_hash: sha256_hex("token"),
+            family_id: Uuid::new_v4(),
+            expires_at: now + expires_in,
+            rotated_at: rotated.then_some(now),
+            revoked_at: revoked.then_some(now),
+        }
+    }
+
+    #[test]
+    fn fresh_token_is_usable() {
+        assert_eq!(token_state(&session(Duration::days(1), false, false), Utc::now()), TokenState::Usable);
+    }
+
+    #[test]
+    fn rotated_or_revoked_token_is_a_replay() {
+        assert_eq!(token_state(&session(Duration::days(1), true, false), Utc::now()), TokenState::Replayed);
+        assert_eq!(token_state(&session(Duration::days(1), false, true), Utc::now()), TokenState::Replayed);
+    }
+
+    #[test]
+    fn replay_is_detected_even_after_expiry() {
+        // 过期的旧令牌被重放同样要吊销整个 family
+        assert_eq!(token_state(&session(Duration::days(-1), true, false), Utc::now()), TokenState::Replayed);
+    }
+
+    #[test]
+    fn expired_token_is_rejected() {
+        assert_eq!(token_state(&session(Duration::seconds(-1), false, false), Utc::now()), TokenState::Expired);
+    }
+}
@@ -0,0 +1,257 @@
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::Utc;
+use futures::future::BoxFuture;
+use hmac::{Hmac, Mac};
+use log::info;
+use reqwest::Client as ReqwestClient;
+use serde::Deserialize;
+use serde_json::json;
+use sha1::Sha1;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::database::DbPool;
+use crate::models::user::PhoneCodePurpose;
+
+const DEFAULT_ALIYUN_ENDPOINT: &str = "https://dysmsapi.aliyuncs.com/";
+const DEFAULT_ALIYUN_REGION: &str = "cn-hangzhou";
+
+#[derive(Debug, Error)]
+pub enum SmsError {
+    #[error("短信服务未配置: {0}")]
+    NotConfigured(String),
+    #[error("数据库错误: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("短信请求失败: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("短信服务商返回错误: {code} {message}")]
+    Provider { code: String, message: String },
+}
+
+/// 短信服务商，发送验证码短信
+pub trait SmsProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn send_code<'a>(
+        &'a self,
+        phone: &'a str,
+        code: &'a str,
+        purpose: PhoneCodePurpose,
+    ) -> BoxFuture<'a, Result<(), SmsError>>;
+}
+
+/// 规范化中国大陆手机号，去掉 +86 前缀，格式不正确时返回 None
+pub fn normalize_phone(phone: &str) -> Option<String> {
+    let phone = phone.trim();
+    let phone = phone
+        .strip_prefix("+86")
+        .or_else(|| phone.strip_prefix("0086"))
+        .unwrap_or(phone)
+        .trim_start_matches(['-', ' ']);
+    let bytes = phone.as_bytes();
+    let valid = bytes.len() == 11
+        && bytes[0] == b'1'
+        && (b'3'..=b'9').contains(&bytes[1])
+        && bytes.iter().all(u8::is_ascii_digit);
+    valid.then(|| phone.to_string())
+}
+
+/// 本地开发用，验证码直接写入日志
+pub struct MockSms;
+
+impl SmsProvider for MockSms {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn send_code<'a>(
+        &'a self,
+        phone: &'a str,
+        code: &'a str,
+        purpose: PhoneCodePurpose,
+    ) -> BoxFuture<'a, Result<(), SmsError>> {
+        Box::pin(async move {
+            info!("[短信模拟] 发送到 {} 的{}验证码: {}", phone, purpose.as_str(), code);
+            Ok(())
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AliyunResponse {
+    code: String,
+    message: Option<String>,
+}
+
+/// 阿里云短信服务（dysmsapi SendSms），也可以指向兼容该签名协议的网关
+pub struct AliyunSms {
+    http: ReqwestClient,
+    endpoint: String,
+    region: String,
+    access_key_id: String,
+    access_key_secret: String,
+    sign_name: String,
+    template_register: String,
+    template_login: String,
+    template_reset_password: String,
+    template_change_phone: String,
+}
+
+/// RFC 3986 编码，阿里云签名要求空格编码为 %20、波浪号不编码
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn required_env(name: &str) -> Result<String, SmsError> {
+    std::env::var(name).map_err(|_| SmsError::NotConfigured(name.to_string()))
+}
+
+impl AliyunSms {
+    /// 各用途的模板未单独配置时使用 ALIYUN_SMS_TEMPLATE_CODE
+    pub fn from_env() -> Result<Self, SmsError> {
+        let default_template = std::env::var("ALIYUN_SMS_TEMPLATE_CODE").ok();
+        let template = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .or_else(|| default_template.clone())
+                .ok_or_else(|| SmsError::NotConfigured(name.to_string()))
+        };
+
+        Ok(AliyunSms {
+            http: ReqwestClient::builder().timeout(StdDuration::from_secs(10)).build()?,
+            endpoint: std::env::var("ALIYUN_SMS_ENDPOINT").unwrap_or_else(|_| DEFAULT_ALIYUN_ENDPOINT.to_string()),
+            region: std::env::var("ALIYUN_SMS_REGION").unwrap_or_else(|_| DEFAULT_ALIYUN_REGION.to_string()),
+            access_key_id: required_env("ALIYUN_SMS_ACCESS_KEY_ID")?,
+            access_key_secret: required_env("ALIYUN_SMS_ACCESS_KEY_SECRET")?,
+            sign_name: required_env("ALIYUN_SMS_SIGN_NAME")?,
+            template_register: template("ALIYUN_SMS_TEMPLATE_REGISTER")?,
+            template_login: template("ALIYUN_SMS_TEMPLATE_LOGIN")?,
+            template_reset_password: template("ALIYUN_SMS_TEMPLATE_RESET_PASSWORD")?,
+            template_change_phone: template("ALIYUN_SMS_TEMPLATE_CHANGE_PHONE")?,
+        })
+    }
+
+    fn template_code(&self, purpose: PhoneCodePurpose) -> &str {
+        match purpose {
+            PhoneCodePurpose::Register => &self.template_register,
+            PhoneCodePurpose::Login => &self.template_login,
+            PhoneCodePurpose::ResetPassword => &self.template_reset_password,
+            PhoneCodePurpose::ChangePhone => &self.template_change_phone,
+        }
+    }
+
+    /// 按 RPC 风格签名（HMAC-SHA1）生成完整的查询参数
+    fn signed_query(&self, params: Vec<(&str, String)>) -> String {
+        let mut params = params;
+        params.sort_by(|a, b| a.0.cmp(b.0));
+        let canonical = params
+            .iter()
+            .map(|(k, v)| format!("{}={}", percent_encode(k), percent_encode(v)))
+            .collect::<Vec<_>>()
+            .join("&");
+        let string_to_sign = format!("GET&{}&{}", percent_encode("/"), percent_encode(&canonical));
+
+        let mut mac = Hmac::<Sha1>::new_from_slice(format!("{}&", self.access_key_secret).as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(string_to_sign.as_bytes());
+        let signature = STANDARD.encode(mac.finalize().into_bytes());
+
+        format!("Signature={}&{}", percent_encode(&signature), canonical)
+    }
+}
+
+impl SmsProvider for AliyunSms {
+    fn name(&self) -> &'static str {
+        "aliyun"
+    }
+
+    fn send_code<'a>(
+        &'a self,
+        phone: &'a str,
+        code: &'a str,
+        purpose: PhoneCodePurpose,
+    ) -> BoxFuture<'a, Result<(), SmsError>> {
+        Box::pin(async move {
+            let params = vec![
+                ("AccessKeyId", self.access_key_id.clone()),
+                ("Action", "SendSms".to_string()),
+                ("Format", "JSON".to_string()),
+                ("PhoneNumbers", phone.to_string()),
+                ("RegionId", self.region.clone()),
+                ("SignName", self.sign_name.clone()),
+                ("SignatureMethod", "HMAC-SHA1".to_string()),
+                ("SignatureNonce", Uuid::new_v4().to_string()),
+                ("SignatureVersion", "1.0".to_string()),
+                ("TemplateCode", self.template_code(purpose).to_string()),
+                ("TemplateParam", json!({"code": code}).to_string()),
+                ("Timestamp", Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string()),
+                ("Version", "2017-05-25".to_string()),
+            ];
+            let url = format!("{}?{}", self.endpoint, self.signed_query(params));
+
+            let response: AliyunResponse = self.http.get(url).send().await?.json().await?;
+            if response.code != "OK" {
+                return Err(SmsError::Provider {
+                    code: response.code,
+                    message: response.message.unwrap_or_default(),
+                });
+            }
+            Ok(())
+        })
+    }
+}
+
+/// 按 SMS_PROVIDER 环境变量选择服务商，未设置时使用 system_configs.sms_provider
+pub async fn provider_from_config(pool: &DbPool) -> Result<Arc<dyn SmsProvider>, SmsError> {
+    let name = match std::env::var("SMS_PROVIDER") {
+        Ok(name) => name,
+        Err(_) => sqlx::query_scalar::<_, serde_json::Value>("SELECT value FROM system_configs WHERE key = 'sms_provider'")
+            .fetch_optional(pool)
+            .await?
+            .and_then(|value| value.as_str().map(str::to_string))
+            .ok_or_else(|| SmsError::NotConfigured("sms_provider".to_string()))?,
+    };
+
+    match name.as_str() {
+        "aliyun" => Ok(Arc::new(AliyunSms::from_env()?)),
+        "mock" => Ok(Arc::new(MockSms)),
+        other => Err(SmsError::NotConfigured(format!("未知的短信服务商 {}", other))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_phone_strips_country_code() {
+        assert_eq!(normalize_phone("13800138000").as_deref(), Some("13800138000"));
+        assert_eq!(normalize_phone("+8613800138000").as_deref(), Some("13800138000"));
+        assert_eq!(normalize_phone("+86 13800138000").as_deref(), Some("13800138000"));
+        assert_eq!(normalize_phone("0086-13800138000").as_deref(), Some("13800138000"));
+        assert_eq!(normalize_phone(" 19912345678 ").as_deref(), Some("19912345678"));
+    }
+
+    #[test]
+    fn normalize_phone_rejects_invalid_numbers() {
+        assert_eq!(normalize_phone(""), None);
+        assert_eq!(normalize_phone("1380013800"), None);
+        assert_eq!(normalize_phone("138001380000"), None);
+        assert_eq!(normalize_phone("12800138000"), None);
+        assert_eq!(normalize_phone("23800138000"), None);
+        assert_eq!(normalize_phone("1380013800a"), None);
+        assert_eq!(normalize_phone("+1 13800138000"), None);
+    }
+}
@@ -0,0 +1,101 @@
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum StorageError {
+    #[error("文件存储未配置: {0}")]
+    NotConfigured(String),
+    #[error("非法的对象键: {0}")]
+    InvalidKey(String),
+    #[error("文件读写失败: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// 用户上传文件（头像等）的存储后端，按对象键保存，返回可公开访问的地址
+pub trait ObjectStorage: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn put<'a>(&'a self, key: &'a str, content_type: &'a str, bytes: Vec<u8>) -> BoxFuture<'a, Result<String, StorageError>>;
+
+    /// put 返回的地址对应的对象键，不属于本后端的地址返回 None
+    fn object_key<'a>(&self, url: &'a str) -> Option<&'a str>;
+
+    fn delete<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<(), StorageError>>;
+
+    /// 需要由本服务提供静态访问的目录：(URL 前缀, 本地目录)
+    fn local_mount(&self) -> Option<(&str, &Path)> {
+        None
+    }
+}
+
+/// 保存到本地磁盘，由本服务在 STORAGE_PUBLIC_URL 下提供访问
+pub struct LocalStorage {
+    root: PathBuf,
+    public_url: String,
+}
+
+impl LocalStorage {
+    pub fn from_env() -> Self {
+        let root = std::env::var("STORAGE_LOCAL_DIR").unwrap_or_else(|_| "./uploads".to_string());
+        let public_url = std::env::var("STORAGE_PUBLIC_URL").unwrap_or_else(|_| "/uploads".to_string());
+        LocalStorage {
+            root: PathBuf::from(root),
+            public_url: public_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// 对象键只允许普通的相对路径，防止写到存储目录之外
+    fn path_for(&self, key: &str) -> Result<PathBuf, StorageError> {
+        let relative = Path::new(key);
+        if key.is_empty() || !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+            return Err(StorageError::InvalidKey(key.to_string()));
+        }
+        Ok(self.root.join(relative))
+    }
+}
+
+impl ObjectStorage for LocalStorage {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    fn put<'a>(&'a self, key: &'a str, _content_type: &'a str, bytes: Vec<u8>) -> BoxFuture<'a, Result<String, StorageError>> {
+        Box::pin(async move {
+            let path = self.path_for(key)?;
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::write(&path, bytes).await?;
+            Ok(format!("{}/{}", self.public_url, key))
+        })
+    }
+
+    fn object_key<'a>(&self, url: &'a str) -> Option<&'a str> {
+        url.strip_prefix(&self.public_url).and_then(|rest| rest.strip_prefix('/'))
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<(), StorageError>> {
+        Box::pin(async move {
+            match tokio::fs::remove_file(self.path_for(key)?).await {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+                _ => Ok(()),
+            }
+        })
+    }
+
+    fn local_mount(&self) -> Option<(&str, &Path)> {
+        Some((&self.public_url, &self.root))
+    }
+}
+
+/// 按 STORAGE_BACKEND 环境变量选择存储后端，未设置时保存到本地磁盘
+pub fn storage_from_env() -> Result<Arc<dyn ObjectStorage>, StorageError> {
+    let name = std::env::var("STORAGE_BACKEND").unwrap_or_else(|_| "local".to_string());
+    match name.as_str() {
+        "local" => Ok(Arc::new(LocalStorage::from_env())),
+        other => Err(StorageError::NotConfigured(format!("未知的存储后端 {}", other))),
+    }
+}
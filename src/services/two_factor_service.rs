@@ -0,0 +1,299 @@
+use chrono::{DateTime, Duration, Utc};
+use log::error;
+use sqlx::PgConnection;
+use totp_rs::{Algorithm, Secret, TOTP};
+use uuid::Uuid;
+
+use crate::database::DbPool;
+use crate::utils::crypto::{random_bytes, random_token, sha256_hex, FieldCipher};
+
+const SECRET_BYTES: usize = 20;
+const TOTP_DIGITS: usize = 6;
+const TOTP_STEP_SECS: u64 = 30;
+// 允许前后各一个时间步的时钟偏差
+const TOTP_SKEW_STEPS: i64 = 1;
+const RECOVERY_CODE_COUNT: usize = 10;
+const CHALLENGE_TTL_MINUTES: i64 = 5;
+const MAX_CHALLENGE_ATTEMPTS: i32 = 5;
+
+/// 新生成的 TOTP 密钥，确认前不会生效
+pub struct Enrollment {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+pub enum ChallengeLookup {
+    Valid { id: Uuid, user_id: Uuid },
+    Invalid,
+    TooManyAttempts,
+}
+
+fn issuer() -> String {
+    std::env::var("TOTP_ISSUER").unwrap_or_else(|_| "OpenVirt".to_string())
+}
+
+fn build_totp(secret: Vec<u8>, account: &str) -> TOTP {
+    TOTP::new_unchecked(
+        Algorithm::SHA1,
+        TOTP_DIGITS,
+        0,
+        TOTP_STEP_SECS,
+        secret,
+        Some(issuer()),
+        account.to_owned(),
+    )
+}
+
+/// 返回验证码匹配的时间步，不匹配时为 None
+fn matching_step(secret: &str, code: &str) -> Option<i64> {
+    let bytes = Secret::Encoded(secret.to_owned()).to_bytes().ok()?;
+    let totp = build_totp(bytes, "");
+    let current = Utc::now().timestamp() / TOTP_STEP_SECS as i64;
+    (-TOTP_SKEW_STEPS..=TOTP_SKEW_STEPS)
+        .map(|offset| current + offset)
+        .find(|step| totp.check(code.trim(), (*step as u64) * TOTP_STEP_SECS))
+}
+
+/// 取出 base32 密钥：新密钥只有密文，迁移前绑定的旧密钥仍是明文
+fn stored_secret(cipher: &FieldCipher, user_id: Uuid, row: Option<(Option<String>, Option<String>)>) -> Option<String> {
+    match row? {
+        (_, Some(encrypted)) => match cipher.decrypt(&encrypted) {
+            Ok(secret) => Some(secret),
+            Err(e) => {
+                error!("解密用户 {} 的两步验证密钥失败: {}", user_id, e);
+                None
+            }
+        },
+        (plain, None) => plain,
+    }
+}
+
+fn normalize_recovery_code(code: &str) -> String {
+    code.trim().replace('-', "").to_lowercase()
+}
+
+/// 生成新的恢复码并替换旧的，返回明文（仅展示一次）
+async fn replace_recovery_codes(conn: &mut PgConnection, user_id: Uuid) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query("DELETE FROM two_factor_recovery_codes WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+
+    let mut codes = Vec::with_capacity(RECOVERY_CODE_COUNT);
+    for _ in 0..RECOVERY_CODE_COUNT {
+        let raw = random_token(5);
+        sqlx::query("INSERT INTO two_factor_recovery_codes (user_id, code_hash) VALUES ($1, $2)")
+            .bind(user_id)
+            .bind(sha256_hex(&raw))
+            .execute(&mut *conn)
+            .await?;
+        codes.push(format!("{}-{}", &raw[..5], &raw[5..]));
+    }
+    Ok(codes)
+}
+
+/// 开始绑定：生成新密钥，覆盖之前未确认的密钥。已启用时返回 None
+pub async fn start_enrollment(
+    pool: &DbPool,
+    cipher: &FieldCipher,
+    user_id: Uuid,
+    account: &str,
+) -> Result<Option<Enrollment>, sqlx::Error> {
+    let totp = build_totp(random_bytes(SECRET_BYTES), account);
+    let secret = totp.get_secret_base32();
+
+    let result = sqlx::query(
+        r#"
+        INSERT INTO user_two_factor (user_id, secret_encrypted)
+        VALUES ($1, $2)
+        ON CONFLICT (user_id) DO UPDATE
+        SET secret = NULL, secret_encrypted = EXCLUDED.secret_encrypted, last_used_step = NULL, created_at = NOW()
+        WHERE user_two_factor.confirmed_at IS NULL
+        "#
+    )
+    .bind(user_id)
+    .bind(cipher.encrypt(&secret))
+    .execute(pool)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Ok(None);
+    }
+
+    Ok(Some(Enrollment { otpauth_uri: totp.get_url(), secret }))
+}
+
+/// 用第一个验证码确认绑定，成功后启用两步验证并返回恢复码
+pub async fn confirm_enrollment(
+    pool: &DbPool,
+    cipher: &FieldCipher,
+    user_id: Uuid,
+    code: &str,
+) -> Result<Option<Vec<String>>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let row = sqlx::query_as::<_, (Option<String>, Option<String>)>(
+        "SELECT secret, secret_encrypted FROM user_two_factor WHERE user_id = $1 AND confirmed_at IS NULL FOR UPDATE"
+    )
+    .bind(user_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let secret = stored_secret(cipher, user_id, row);
+    let Some(step) = secret.as_deref().and_then(|secret| matching_step(secret, code)) else {
+        return Ok(None);
+    };
+
+    sqlx::query(
+        r#"
+        UPDATE user_two_factor SET confirmed_at = NOW(), last_used_step = $2,
+            secret_encrypted = COALESCE(secret_encrypted, $3), secret = NULL
+        WHERE user_id = $1
+        "#
+    )
+    .bind(user_id)
+    .bind(step)
+    .bind(secret.as_deref().map(|secret| cipher.encrypt(secret)))
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("UPDATE users SET two_factor_enabled = TRUE, updated_at = NOW() WHERE id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    let codes = replace_recovery_codes(&mut tx, user_id).await?;
+
+    tx.commit().await?;
+    Ok(Some(codes))
+}
+
+/// 校验 TOTP 验证码，同一时间步的验证码只能使用一次。旧的明文密钥在验证成功后改写为密文
+pub async fn verify_code(pool: &DbPool, cipher: &FieldCipher, user_id: Uuid, code: &str) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query_as::<_, (Option<String>, Option<String>)>(
+        "SELECT secret, secret_encrypted FROM user_two_factor WHERE user_id = $1 AND confirmed_at IS NOT NULL"
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+    let legacy = row.as_ref().is_some_and(|(_, encrypted)| encrypted.is_none());
+    let secret = stored_secret(cipher, user_id, row);
+    let Some(step) = secret.as_deref().and_then(|secret| matching_step(secret, code)) else {
+        return Ok(false);
+    };
+    let migrated = secret.filter(|_| legacy).map(|secret| cipher.encrypt(&secret));
+
+    let result = sqlx::query(
+        r#"
+        UPDATE user_two_factor SET last_used_step = $2,
+            secret_encrypted = COALESCE(secret_encrypted, $3), secret = NULL
+        WHERE user_id = $1 AND (last_used_step IS NULL OR last_used_step < $2)
+        "#
+    )
+    .bind(user_id)
+    .bind(step)
+    .bind(migrated)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// 使用恢复码，每个恢复码只能使用一次
+pub async fn use_recovery_code(pool: &DbPool, user_id: Uuid, code: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE two_factor_recovery_codes SET used_at = NOW()
+        WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+        "#
+    )
+    .bind(user_id)
+    .bind(sha256_hex(&normalize_recovery_code(code)))
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn remaining_recovery_codes(pool: &DbPool, user_id: Uuid) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM two_factor_recovery_codes WHERE user_id = $1 AND used_at IS NULL"
+    )
+    .bind(user_id)
+    .fetch_one(pool)
+    .await
+}
+
+/// 清除用户的两步验证配置，返回之前是否已启用
+pub async fn reset(conn: &mut PgConnection, user_id: Uuid) -> Result<bool, sqlx::Error> {
+    sqlx::query("DELETE FROM user_two_factor WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query("DELETE FROM two_factor_recovery_codes WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query("DELETE FROM login_challenges WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+    let result = sqlx::query(
+        "UPDATE users SET two_factor_enabled = FALSE, updated_at = NOW() WHERE id = $1 AND two_factor_enabled"
+    )
+    .bind(user_id)
+    .execute(&mut *conn)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// 密码验证通过后创建登录挑战，返回明文令牌和过期时间
+pub async fn create_challenge(pool: &DbPool, user_id: Uuid) -> Result<(String, DateTime<Utc>), sqlx::Error> {
+    let token = random_token(32);
+    let expires_at = Utc::now() + Duration::minutes(CHALLENGE_TTL_MINUTES);
+
+    sqlx::query("INSERT INTO login_challenges (user_id, token_hash, expires_at) VALUES ($1, $2, $3)")
+        .bind(user_id)
+        .bind(sha256_hex(&token))
+        .bind(expires_at)
+        .execute(pool)
+        .await?;
+    Ok((token, expires_at))
+}
+
+pub async fn find_challenge(pool: &DbPool, token: &str) -> Result<ChallengeLookup, sqlx::Error> {
+    let challenge = sqlx::query_as::<_, (Uuid, Uuid, i32)>(
+        r#"
+        SELECT id, user_id, attempts FROM login_challenges
+        WHERE token_hash = $1 AND consumed_at IS NULL AND expires_at > NOW()
+        "#
+    )
+    .bind(sha256_hex(token))
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(match challenge {
+        Some((_, _, attempts)) if attempts >= MAX_CHALLENGE_ATTEMPTS => ChallengeLookup::TooManyAttempts,
+        Some((id, user_id, _)) => ChallengeLookup::Valid { id, user_id },
+        None => ChallengeLookup::Invalid,
+    })
+}
+
+pub async fn record_failed_attempt(pool: &DbPool, challenge_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE login_challenges SET attempts = attempts + 1 WHERE id = $1")
+        .bind(challenge_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// 标记挑战已完成，并发请求中只有一个能成功
+pub async fn consume_challenge(pool: &DbPool, challenge_id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE login_challenges SET consumed_at = NOW() WHERE id = $1 AND consumed_at IS NULL"
+    )
+    .bind(challenge_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// 删除已过期的登录挑战，由定期维护任务调用
+pub async fn purge_expired_challenges(pool: &DbPool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM login_challenges WHERE expires_at < NOW()").execute(pool).await?;
+    Ok(result.rows_affected())
+}
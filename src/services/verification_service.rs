@@ -0,0 +1,294 @@
+use uuid::Uuid;
+
+use crate::database::DbPool;
+use crate::models::user::{PhoneCodePurpose, VerificationPurpose};
+use crate::utils::crypto::numeric_code;
+
+pub const CODE_TTL_MINUTES: i64 = 10;
+// 同一地址两次发送的最小间隔
+const RESEND_INTERVAL_SECS: i64 = 60;
+// 同一地址每小时最多发送次数
+const MAX_CODES_PER_HOUR: i64 = 10;
+// 同一 IP 每小时最多请求的短信数，防止批量轰炸不同号码
+const MAX_SMS_PER_IP_PER_HOUR: i64 = 20;
+// 每个验证码允许输错的次数
+const MAX_VERIFY_ATTEMPTS: i32 = 5;
+
+#[derive(Debug, thiserror::Error)]
+pub enum VerificationError {
+    #[error("数据库错误: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("发送过于频繁，请 {0} 秒后重试")]
+    TooFrequent(i64),
+    #[error("该地址发送次数过多，请稍后重试")]
+    HourlyLimitReached,
+    #[error("当前网络请求验证码次数过多，请稍后重试")]
+    IpLimitReached,
+}
+
+/// 验证码的发送渠道，对应不同的表
+#[derive(Clone, Copy)]
+enum Channel {
+    Email,
+    Phone,
+}
+
+impl Channel {
+    fn table(&self) -> &'static str {
+        match self {
+            Channel::Email => "email_verifications",
+            Channel::Phone => "phone_verifications",
+        }
+    }
+
+    /// 邮箱不区分大小写，手机号已规范化
+    fn address_match(&self) -> &'static str {
+        match self {
+            Channel::Email => "LOWER(email) = LOWER($1)",
+            Channel::Phone => "phone = $1",
+        }
+    }
+}
+
+/// 新生成的验证码
+pub struct IssuedCode {
+    pub id: Uuid,
+    pub code: String,
+}
+
+#[derive(sqlx::FromRow)]
+struct SendStats {
+    sent_last_hour: i64,
+    seconds_since_last: Option<f64>,
+}
+
+#[derive(sqlx::FromRow)]
+struct ActiveCode {
+    id: Uuid,
+    code: String,
+    attempts: i32,
+}
+
+async fn issue_code(
+    pool: &DbPool,
+    channel: Channel,
+    address: &str,
+    purpose: &str,
+    ip_address: Option<&str>,
+) -> Result<IssuedCode, VerificationError> {
+    let mut tx = pool.begin().await?;
+
+    // 按地址加事务锁，避免并发请求同时通过频率检查
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1 || ':' || LOWER($2)))")
+        .bind(channel.table())
+        .bind(address)
+        .execute(&mut *tx)
+        .await?;
+
+    let stats = sqlx::query_as::<_, SendStats>(&format!(
+        r#"
+        SELECT COUNT(*) AS sent_last_hour,
+               EXTRACT(EPOCH FROM NOW() - MAX(created_at))::FLOAT8 AS seconds_since_last
+        FROM {}
+        WHERE {} AND created_at > NOW() - INTERVAL '1 hour'
+        "#,
+        channel.table(),
+        channel.address_match()
+    ))
+    .bind(address)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if let Some(elapsed) = stats.seconds_since_last
+        && elapsed < RESEND_INTERVAL_SECS as f64
+    {
+        return Err(VerificationError::TooFrequent(RESEND_INTERVAL_SECS - elapsed as i64));
+    }
+    if stats.sent_last_hour >= MAX_CODES_PER_HOUR {
+        return Err(VerificationError::HourlyLimitReached);
+    }
+
+    if let Some(ip_address) = ip_address {
+        let sent_from_ip = sqlx::query_scalar::<_, i64>(&format!(
+            "SELECT COUNT(*) FROM {} WHERE ip_address = $1::INET AND created_at > NOW() - INTERVAL '1 hour'",
+            channel.table()
+        ))
+        .bind(ip_address)
+        .fetch_one(&mut *tx)
+        .await?;
+        if sent_from_ip >= MAX_SMS_PER_IP_PER_HOUR {
+            return Err(VerificationError::IpLimitReached);
+        }
+    }
+
+    sqlx::query(&format!(
+        "UPDATE {} SET used = true WHERE {} AND purpose = $2 AND used = false",
+        channel.table(),
+        channel.address_match()
+    ))
+    .bind(address)
+    .bind(purpose)
+    .execute(&mut *tx)
+    .await?;
+
+    let code = numeric_code();
+    let id = match (channel, ip_address) {
+        (Channel::Phone, Some(ip_address)) => sqlx::query_scalar::<_, Uuid>(
+            r#"
+            INSERT INTO phone_verifications (phone, code, purpose, ip_address, expires_at)
+            VALUES ($1, $2, $3, $5::INET, NOW() + make_interval(mins => $4))
+            RETURNING id
+            "#
+        )
+        .bind(address)
+        .bind(&code)
+        .bind(purpose)
+        .bind(CODE_TTL_MINUTES as i32)
+        .bind(ip_address),
+        _ => sqlx::query_scalar::<_, Uuid>(
+            r#"
+            INSERT INTO email_verifications (email, code, purpose, expires_at)
+            VALUES ($1, $2, $3, NOW() + make_interval(mins => $4))
+            RETURNING id
+            "#
+        )
+        .bind(address)
+        .bind(&code)
+        .bind(purpose)
+        .bind(CODE_TTL_MINUTES as i32),
+    }
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(IssuedCode { id, code })
+}
+
+/// 校验最新一条有效验证码，输错会累计次数，consume 为 true 时校验通过即作废
+async fn check_code(
+    pool: &DbPool,
+    channel: Channel,
+    address: &str,
+    purpose: &str,
+    code: &str,
+    consume: bool,
+) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let active = sqlx::query_as::<_, ActiveCode>(&format!(
+        r#"
+        SELECT id, code, attempts FROM {}
+        WHERE {} AND purpose = $2 AND used = false AND expires_at > NOW()
+        ORDER BY created_at DESC
+        LIMIT 1
+        FOR UPDATE
+        "#,
+        channel.table(),
+        channel.address_match()
+    ))
+    .bind(address)
+    .bind(purpose)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(active) = active else {
+        return Ok(false);
+    };
+    if active.attempts >= MAX_VERIFY_ATTEMPTS {
+        return Ok(false);
+    }
+
+    let matched = active.code == code.trim();
+    if !matched {
+        sqlx::query(&format!("UPDATE {} SET attempts = attempts + 1 WHERE id = $1", channel.table()))
+            .bind(active.id)
+            .execute(&mut *tx)
+            .await?;
+    } else if consume {
+        sqlx::query(&format!("UPDATE {} SET used = true WHERE id = $1", channel.table()))
+            .bind(active.id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+    Ok(matched)
+}
+
+/// 发送失败时删除验证码，不占用发送次数
+async fn discard_code(pool: &DbPool, channel: Channel, id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query(&format!("DELETE FROM {} WHERE id = $1", channel.table()))
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// 生成并保存邮箱验证码，同一地址同一用途之前未使用的验证码随之作废
+pub async fn issue_email_code(
+    pool: &DbPool,
+    email: &str,
+    purpose: VerificationPurpose,
+) -> Result<IssuedCode, VerificationError> {
+    issue_code(pool, Channel::Email, email, purpose.as_str(), None).await
+}
+
+pub async fn discard_email_code(pool: &DbPool, id: Uuid) -> Result<(), sqlx::Error> {
+    discard_code(pool, Channel::Email, id).await
+}
+
+/// 只校验不作废，用于重置密码前先确认验证码
+pub async fn check_email_code(
+    pool: &DbPool,
+    email: &str,
+    purpose: VerificationPurpose,
+    code: &str,
+) -> Result<bool, sqlx::Error> {
+    check_code(pool, Channel::Email, email, purpose.as_str(), code, false).await
+}
+
+/// 校验并作废邮箱验证码
+pub async fn consume_email_code(
+    pool: &DbPool,
+    email: &str,
+    purpose: VerificationPurpose,
+    code: &str,
+) -> Result<bool, sqlx::Error> {
+    check_code(pool, Channel::Email, email, purpose.as_str(), code, true).await
+}
+
+/// 生成并保存短信验证码，除号码外还按请求 IP 限制频率
+pub async fn issue_phone_code(
+    pool: &DbPool,
+    phone: &str,
+    purpose: PhoneCodePurpose,
+    ip_address: &str,
+) -> Result<IssuedCode, VerificationError> {
+    issue_code(pool, Channel::Phone, phone, purpose.as_str(), Some(ip_address)).await
+}
+
+pub async fn discard_phone_code(pool: &DbPool, id: Uuid) -> Result<(), sqlx::Error> {
+    discard_code(pool, Channel::Phone, id).await
+}
+
+/// 校验并作废短信验证码
+pub async fn consume_phone_code(
+    pool: &DbPool,
+    phone: &str,
+    purpose: PhoneCodePurpose,
+    code: &str,
+) -> Result<bool, sqlx::Error> {
+    check_code(pool, Channel::Phone, phone, purpose.as_str(), code, true).await
+}
+
+/// 删除一天前的邮箱和短信验证码，保留一天用于发送频率统计，由定期维护任务调用
+pub async fn purge_old_codes(pool: &DbPool) -> Result<u64, sqlx::Error> {
+    let mut purged = 0;
+    for statement in [
+        "DELETE FROM email_verifications WHERE created_at < NOW() - INTERVAL '1 day'",
+        "DELETE FROM phone_verifications WHERE created_at < NOW() - INTERVAL '1 day'",
+    ] {
+        purged += sqlx::query(statement).execute(pool).await?.rows_affected();
+    }
+    Ok(purged)
+}
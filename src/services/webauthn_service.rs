@@ -0,0 +1,277 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{Duration, Utc};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use uuid::Uuid;
+use webauthn_rs::prelude::{
+    CreationChallengeResponse, CredentialID, Passkey, PasskeyAuthentication, PasskeyRegistration,
+    PublicKeyCredential, RegisterPublicKeyCredential, RequestChallengeResponse, Url, Webauthn,
+    WebauthnBuilder, WebauthnError,
+};
+
+use crate::database::DbPool;
+use crate::models::webauthn::PasskeyInfo;
+
+const CEREMONY_TTL_MINUTES: i64 = 5;
+const PURPOSE_REGISTER: &str = "register";
+const PURPOSE_AUTHENTICATE: &str = "authenticate";
+
+#[derive(Debug, thiserror::Error)]
+pub enum PasskeyError {
+    #[error("数据库错误: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("WebAuthn 校验失败: {0}")]
+    Webauthn(#[from] WebauthnError),
+    #[error("流程状态无法解析: {0}")]
+    State(#[from] serde_json::Error),
+    #[error("流程不存在或已过期")]
+    CeremonyExpired,
+    #[error("凭据已被注册")]
+    AlreadyRegistered,
+    #[error("凭据不存在")]
+    UnknownCredential,
+}
+
+/// 通行密钥认证成功的结果
+pub struct VerifiedPasskey {
+    pub user_id: Uuid,
+    /// 作为登录第二步时对应的登录挑战
+    pub login_challenge_id: Option<Uuid>,
+}
+
+fn encode_credential_id(id: &CredentialID) -> String {
+    URL_SAFE_NO_PAD.encode(id.as_slice())
+}
+
+async fn user_passkeys(pool: &DbPool, user_id: Uuid) -> Result<Vec<Passkey>, PasskeyError> {
+    let rows = sqlx::query_scalar::<_, serde_json::Value>(
+        "SELECT passkey FROM webauthn_credentials WHERE user_id = $1"
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(serde_json::from_value)
+        .collect::<Result<Vec<Passkey>, _>>()?)
+}
+
+async fn save_ceremony<T: Serialize>(
+    pool: &DbPool,
+    user_id: Uuid,
+    purpose: &str,
+    login_challenge_id: Option<Uuid>,
+    state: &T,
+) -> Result<Uuid, PasskeyError> {
+    let id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO webauthn_ceremonies (user_id, purpose, login_challenge_id, state, expires_at)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id
+        "#
+    )
+    .bind(user_id)
+    .bind(purpose)
+    .bind(login_challenge_id)
+    .bind(serde_json::to_value(state)?)
+    .bind(Utc::now() + Duration::minutes(CEREMONY_TTL_MINUTES))
+    .fetch_one(pool)
+    .await?;
+    Ok(id)
+}
+
+/// 取出并删除流程状态，保证每个挑战只能提交一次
+async fn take_ceremony<T: DeserializeOwned>(
+    pool: &DbPool,
+    ceremony_id: Uuid,
+    purpose: &str,
+) -> Result<(Uuid, Option<Uuid>, T), PasskeyError> {
+    let row = sqlx::query_as::<_, (Uuid, Option<Uuid>, serde_json::Value)>(
+        r#"
+        DELETE FROM webauthn_ceremonies
+        WHERE id = $1 AND purpose = $2 AND expires_at > NOW()
+        RETURNING user_id, login_challenge_id, state
+        "#
+    )
+    .bind(ceremony_id)
+    .bind(purpose)
+    .fetch_optional(pool)
+    .await?;
+
+    let (user_id, login_challenge_id, state) = row.ok_or(PasskeyError::CeremonyExpired)?;
+    Ok((user_id, login_challenge_id, serde_json::from_value(state)?))
+}
+
+pub struct PasskeyService {
+    webauthn: Webauthn,
+}
+
+impl PasskeyService {
+    /// 依赖方 ID 必须是前端访问域名或其上级域名
+    pub fn from_env() -> Result<Self, WebauthnError> {
+        let rp_id = std::env::var("WEBAUTHN_RP_ID").unwrap_or_else(|_| "localhost".to_string());
+        let rp_origin = std::env::var("WEBAUTHN_RP_ORIGIN").unwrap_or_else(|_| "http://localhost:8081".to_string());
+        let rp_name = std::env::var("WEBAUTHN_RP_NAME").unwrap_or_else(|_| "OpenVirt".to_string());
+
+        let rp_origin = Url::parse(&rp_origin).map_err(|_| WebauthnError::Configuration)?;
+        let webauthn = WebauthnBuilder::new(&rp_id, &rp_origin)?
+            .rp_name(&rp_name)
+            .build()?;
+        Ok(Self { webauthn })
+    }
+
+    pub async fn start_registration(
+        &self,
+        pool: &DbPool,
+        user_id: Uuid,
+        username: &str,
+    ) -> Result<(Uuid, CreationChallengeResponse), PasskeyError> {
+        // 已注册的凭据不允许在同一个验证器上重复注册
+        let existing: Vec<CredentialID> = user_passkeys(pool, user_id)
+            .await?
+            .iter()
+            .map(|passkey| passkey.cred_id().clone())
+            .collect();
+        let exclude = (!existing.is_empty()).then_some(existing);
+
+        let (options, state) = self
+            .webauthn
+            .start_passkey_registration(user_id, username, username, exclude)?;
+        let ceremony_id = save_ceremony(pool, user_id, PURPOSE_REGISTER, None, &state).await?;
+        Ok((ceremony_id, options))
+    }
+
+    pub async fn finish_registration(
+        &self,
+        pool: &DbPool,
+        user_id: Uuid,
+        ceremony_id: Uuid,
+        name: &str,
+        credential: &RegisterPublicKeyCredential,
+    ) -> Result<PasskeyInfo, PasskeyError> {
+        let (owner, _, state) = take_ceremony::<PasskeyRegistration>(pool, ceremony_id, PURPOSE_REGISTER).await?;
+        if owner != user_id {
+            return Err(PasskeyError::CeremonyExpired);
+        }
+
+        let passkey = self.webauthn.finish_passkey_registration(credential, &state)?;
+        let result = sqlx::query_as::<_, PasskeyInfo>(
+            r#"
+            INSERT INTO webauthn_credentials (user_id, credential_id, name, passkey)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (credential_id) DO NOTHING
+            RETURNING id, name, sign_count, created_at, last_used_at
+            "#
+        )
+        .bind(user_id)
+        .bind(encode_credential_id(passkey.cred_id()))
+        .bind(name)
+        .bind(serde_json::to_value(&passkey)?)
+        .fetch_optional(pool)
+        .await?;
+        result.ok_or(PasskeyError::AlreadyRegistered)
+    }
+
+    /// 用户没有任何凭据时返回 None
+    pub async fn start_authentication(
+        &self,
+        pool: &DbPool,
+        user_id: Uuid,
+        login_challenge_id: Option<Uuid>,
+    ) -> Result<Option<(Uuid, RequestChallengeResponse)>, PasskeyError> {
+        let passkeys = user_passkeys(pool, user_id).await?;
+        if passkeys.is_empty() {
+            return Ok(None);
+        }
+
+        let (options, state) = self.webauthn.start_passkey_authentication(&passkeys)?;
+        let ceremony_id = save_ceremony(pool, user_id, PURPOSE_AUTHENTICATE, login_challenge_id, &state).await?;
+        Ok(Some((ceremony_id, options)))
+    }
+
+    /// 校验断言并更新签名计数器，计数器回退（疑似克隆）时校验失败
+    pub async fn finish_authentication(
+        &self,
+        pool: &DbPool,
+        ceremony_id: Uuid,
+        credential: &PublicKeyCredential,
+    ) -> Result<VerifiedPasskey, PasskeyError> {
+        let (user_id, login_challenge_id, state) =
+            take_ceremony::<PasskeyAuthentication>(pool, ceremony_id, PURPOSE_AUTHENTICATE).await?;
+        let result = self.webauthn.finish_passkey_authentication(credential, &state)?;
+
+        let credential_id = encode_credential_id(result.cred_id());
+        let stored = sqlx::query_as::<_, (Uuid, serde_json::Value)>(
+            "SELECT id, passkey FROM webauthn_credentials WHERE user_id = $1 AND credential_id = $2"
+        )
+        .bind(user_id)
+        .bind(&credential_id)
+        .fetch_optional(pool)
+        .await?;
+        let (id, passkey) = stored.ok_or(PasskeyError::UnknownCredential)?;
+
+        let mut passkey: Passkey = serde_json::from_value(passkey)?;
+        passkey.update_credential(&result);
+        sqlx::query(
+            r#"
+            UPDATE webauthn_credentials
+            SET passkey = $2, sign_count = $3, last_used_at = NOW()
+            WHERE id = $1
+            "#
+        )
+        .bind(id)
+        .bind(serde_json::to_value(&passkey)?)
+        .bind(i64::from(result.counter()))
+        .execute(pool)
+        .await?;
+
+        Ok(VerifiedPasskey { user_id, login_challenge_id })
+    }
+}
+
+pub async fn has_passkeys(pool: &DbPool, user_id: Uuid) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM webauthn_credentials WHERE user_id = $1)")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+}
+
+pub async fn list_passkeys(pool: &DbPool, user_id: Uuid) -> Result<Vec<PasskeyInfo>, sqlx::Error> {
+    sqlx::query_as::<_, PasskeyInfo>(
+        r#"
+        SELECT id, name, sign_count, created_at, last_used_at
+        FROM webauthn_credentials
+        WHERE user_id = $1
+        ORDER BY created_at
+        "#
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn rename_passkey(pool: &DbPool, user_id: Uuid, id: Uuid, name: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("UPDATE webauthn_credentials SET name = $3 WHERE id = $1 AND user_id = $2")
+        .bind(id)
+        .bind(user_id)
+        .bind(name)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn revoke_passkey(pool: &DbPool, user_id: Uuid, id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM webauthn_credentials WHERE id = $1 AND user_id = $2")
+        .bind(id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// 删除已过期的注册和登录流程，由定期维护任务调用
+pub async fn purge_expired_ceremonies(pool: &DbPool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM webauthn_ceremonies WHERE expires_at < NOW()").execute(pool).await?;
+    Ok(result.rows_affected())
+}
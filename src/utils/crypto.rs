@@ -0,0 +1,167 @@
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use rand::rngs::OsRng;
+use rand::{Rng, RngCore};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+const NONCE_LEN: usize = 12;
+// 由主密钥经 HKDF 派生的子密钥用途标签，加密与摘要使用互不相关的密钥
+const ENCRYPTION_KEY_INFO: &[u8] = b"openvirt field encryption v1";
+const FINGERPRINT_KEY_INFO: &[u8] = b"openvirt field fingerprint v1";
+
+/// 计算 SHA-256 并以十六进制输出，用于令牌等敏感标识的落库存储
+pub fn sha256_hex(input: &str) -> String {
+    hex::encode(Sha256::digest(input.as_bytes()))
+}
+
+/// 常量时间比较两个秘密值，先取摘要使耗时与长度无关
+pub fn secrets_equal(a: &str, b: &str) -> bool {
+    Sha256::digest(a.as_bytes()).ct_eq(&Sha256::digest(b.as_bytes())).into()
+}
+
+pub fn random_bytes(bytes: usize) -> Vec<u8> {
+    let mut buf = vec![0u8; bytes];
+    OsRng.fill_bytes(&mut buf);
+    buf
+}
+
+/// 生成指定字节数的随机令牌，十六进制输出
+pub fn random_token(bytes: usize) -> String {
+    hex::encode(random_bytes(bytes))
+}
+
+/// 生成 6 位数字验证码
+pub fn numeric_code() -> String {
+    format!("{:06}", OsRng.gen_range(0..1_000_000))
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CipherError {
+    #[error("环境变量 DATA_ENCRYPTION_KEY 未设置")]
+    MissingKey,
+    #[error("DATA_ENCRYPTION_KEY 应为 base64 编码的 32 字节密钥")]
+    InvalidKey,
+    #[error("密文无法解密")]
+    Decrypt,
+}
+
+/// 数据库中敏感字段的加密（AES-256-GCM），密文为 base64(nonce || ciphertext)
+pub struct FieldCipher {
+    cipher: Aes256Gcm,
+    fingerprint_key: [u8; 32],
+}
+
+impl FieldCipher {
+    pub fn from_env() -> Result<Self, CipherError> {
+        let encoded = std::env::var("DATA_ENCRYPTION_KEY")
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+            .ok_or(CipherError::MissingKey)?;
+        let key = STANDARD.decode(encoded.trim()).map_err(|_| CipherError::InvalidKey)?;
+        Self::new(&key)
+    }
+
+    /// 主密钥必须为 32 字节，加密密钥和摘要密钥分别由 HKDF 派生
+    pub fn new(master_key: &[u8]) -> Result<Self, CipherError> {
+        if master_key.len() != 32 {
+            return Err(CipherError::InvalidKey);
+        }
+        let hkdf = Hkdf::<Sha256>::new(None, master_key);
+        let mut encryption_key = [0u8; 32];
+        let mut fingerprint_key = [0u8; 32];
+        hkdf.expand(ENCRYPTION_KEY_INFO, &mut encryption_key)
+            .expect("32 bytes is a valid HKDF-SHA256 output length");
+        hkdf.expand(FINGERPRINT_KEY_INFO, &mut fingerprint_key)
+            .expect("32 bytes is a valid HKDF-SHA256 output length");
+        let cipher = Aes256Gcm::new_from_slice(&encryption_key).map_err(|_| CipherError::InvalidKey)?;
+        Ok(FieldCipher { cipher, fingerprint_key })
+    }
+
+    pub fn encrypt(&self, plaintext: &str) -> String {
+        let nonce = random_bytes(NONCE_LEN);
+        let ciphertext = self
+            .cipher
+            .encrypt(Nonce::from_slice(&nonce), plaintext.as_bytes())
+            .expect("AES-GCM encryption does not fail for in-memory buffers");
+        STANDARD.encode([nonce, ciphertext].concat())
+    }
+
+    pub fn decrypt(&self, encoded: &str) -> Result<String, CipherError> {
+        let bytes = STANDARD.decode(encoded).map_err(|_| CipherError::Decrypt)?;
+        if bytes.len() <= NONCE_LEN {
+            return Err(CipherError::Decrypt);
+        }
+        let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+        let plaintext = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| CipherError::Decrypt)?;
+        String::from_utf8(plaintext).map_err(|_| CipherError::Decrypt)
+    }
+
+    /// 带密钥的 HMAC 摘要，用于在不解密的情况下判断两个值是否相同
+    pub fn fingerprint(&self, value: &str) -> String {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&self.fingerprint_key).expect("HMAC accepts keys of any length");
+        mac.update(value.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cipher() -> FieldCipher {
+        FieldCipher::new(&[7u8; 32]).unwrap()
+    }
+
+    #[test]
+    fn encrypt_then_decrypt_round_trips() {
+        let cipher = cipher();
+        let encrypted = cipher.encrypt("110101199003077777");
+        assert_ne!(encrypted, "110101199003077777");
+        assert_eq!(cipher.decrypt(&encrypted).unwrap(), "110101199003077777");
+        // 每次加密使用新的 nonce
+        assert_ne!(cipher.encrypt("110101199003077777"), encrypted);
+    }
+
+    #[test]
+    fn decrypt_rejects_tampered_or_foreign_ciphertext() {
+        let cipher = cipher();
+        let mut bytes = STANDARD.decode(cipher.encrypt("secret")).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        assert!(cipher.decrypt(&STANDARD.encode(&bytes)).is_err());
+        assert!(cipher.decrypt("not base64!").is_err());
+        assert!(cipher.decrypt(&STANDARD.encode([0u8; NONCE_LEN])).is_err());
+
+        let other = FieldCipher::new(&[8u8; 32]).unwrap();
+        assert!(other.decrypt(&cipher.encrypt("secret")).is_err());
+    }
+
+    #[test]
+    fn fingerprint_is_stable_and_key_dependent() {
+        let cipher = cipher();
+        assert_eq!(cipher.fingerprint("value"), cipher.fingerprint("value"));
+        assert_ne!(cipher.fingerprint("value"), cipher.fingerprint("other"));
+        assert_ne!(FieldCipher::new(&[8u8; 32]).unwrap().fingerprint("value"), cipher.fingerprint("value"));
+    }
+
+    #[test]
+    fn fingerprint_key_differs_from_master_key() {
+        let master = [7u8; 32];
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&master).unwrap();
+        mac.update(b"value");
+        assert_ne!(cipher().fingerprint("value"), hex::encode(mac.finalize().into_bytes()));
+    }
+
+    #[test]
+    fn rejects_keys_of_wrong_length() {
+        assert!(FieldCipher::new(&[0u8; 16]).is_err());
+    }
+}
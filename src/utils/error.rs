@@ -0,0 +1,185 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
+
+use actix_web::http::header;
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
+use chrono::{DateTime, Utc};
+use serde_json::json;
+
+use crate::utils::i18n;
+use crate::utils::locale::Locale;
+
+/// APP_ENV=development 时在 500 响应中附带内部错误信息，便于本地调试
+static EXPOSE_DETAILS: LazyLock<bool> =
+    LazyLock::new(|| std::env::var("APP_ENV").is_ok_and(|env| env == "development"));
+
+/// 处理器统一返回的错误。code 是给客户端判断用的稳定标识，message 只用于展示
+#[derive(Debug, thiserror::Error)]
+pub enum AppError {
+    #[error("{0}")]
+    BadRequest(Cow<'static, str>),
+    /// 字段路径 -> 错误信息列表
+    #[error("请求参数校验失败")]
+    Validation(BTreeMap<String, Vec<String>>),
+    #[error("{0}")]
+    Unauthorized(Cow<'static, str>),
+    #[error("用户名或密码错误")]
+    InvalidCredentials { captcha_required: bool },
+    #[error("{0}")]
+    CaptchaRequired(Cow<'static, str>),
+    #[error("{0}")]
+    Forbidden(Cow<'static, str>),
+    /// 开启实名要求时未完成实名认证
+    #[error("{0}")]
+    IdentityRequired(Cow<'static, str>),
+    #[error("{0}")]
+    NotFound(Cow<'static, str>),
+    #[error("{0}")]
+    Conflict(Cow<'static, str>),
+    #[error("{message}")]
+    Locked { message: Cow<'static, str>, until: DateTime<Utc> },
+    #[error("{message}")]
+    TooManyRequests { message: Cow<'static, str>, retry_after: Option<i64> },
+    #[error("{0}")]
+    ServiceUnavailable(Cow<'static, str>),
+    /// context 只写入日志，响应中不会出现
+    #[error("{context}: {source}")]
+    Internal { context: Cow<'static, str>, source: anyhow::Error },
+}
+
+impl AppError {
+    pub fn bad_request(message: impl Into<Cow<'static, str>>) -> Self {
+        AppError::BadRequest(message.into())
+    }
+
+    pub fn unauthorized(message: impl Into<Cow<'static, str>>) -> Self {
+        AppError::Unauthorized(message.into())
+    }
+
+    pub fn forbidden(message: impl Into<Cow<'static, str>>) -> Self {
+        AppError::Forbidden(message.into())
+    }
+
+    pub fn not_found(message: impl Into<Cow<'static, str>>) -> Self {
+        AppError::NotFound(message.into())
+    }
+
+    pub fn conflict(message: impl Into<Cow<'static, str>>) -> Self {
+        AppError::Conflict(message.into())
+    }
+
+    pub fn too_many_requests(message: impl Into<Cow<'static, str>>, retry_after: Option<i64>) -> Self {
+        AppError::TooManyRequests { message: message.into(), retry_after }
+    }
+
+    pub fn unavailable(message: impl Into<Cow<'static, str>>) -> Self {
+        AppError::ServiceUnavailable(message.into())
+    }
+
+    /// 数据库等内部错误，context 描述失败的操作，如“查询用户失败”
+    pub fn internal(context: impl Into<Cow<'static, str>>, source: impl Into<anyhow::Error>) -> Self {
+        AppError::Internal { context: context.into(), source: source.into() }
+    }
+
+    /// 框架或第三方组件产生的错误按状态码套用统一格式，无法对应的返回 None
+    pub fn from_status(status: StatusCode, message: String) -> Option<Self> {
+        let error = match status {
+            StatusCode::BAD_REQUEST => AppError::BadRequest(message.into()),
+            // Bearer 认证中间件缺少请求头时只有状态码，没有可读的信息
+            StatusCode::UNAUTHORIZED => AppError::unauthorized("缺少或无效的授权令牌"),
+            StatusCode::FORBIDDEN => AppError::Forbidden(message.into()),
+            StatusCode::NOT_FOUND => AppError::NotFound(message.into()),
+            StatusCode::CONFLICT => AppError::Conflict(message.into()),
+            StatusCode::TOO_MANY_REQUESTS => AppError::too_many_requests(message, None),
+            StatusCode::SERVICE_UNAVAILABLE => AppError::ServiceUnavailable(message.into()),
+            _ => return None,
+        };
+        Some(error)
+    }
+
+    /// 稳定的错误码，客户端应以此判断错误类型
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::BadRequest(_) => "BAD_REQUEST",
+            AppError::Validation(_) => "VALIDATION_FAILED",
+            AppError::Unauthorized(_) => "UNAUTHORIZED",
+            AppError::InvalidCredentials { .. } => "INVALID_CREDENTIALS",
+            AppError::CaptchaRequired(_) => "CAPTCHA_REQUIRED",
+            AppError::Forbidden(_) => "FORBIDDEN",
+            AppError::IdentityRequired(_) => "IDENTITY_VERIFICATION_REQUIRED",
+            AppError::NotFound(_) => "NOT_FOUND",
+            AppError::Conflict(_) => "CONFLICT",
+            AppError::Locked { .. } => "ACCOUNT_LOCKED",
+            AppError::TooManyRequests { .. } => "RATE_LIMITED",
+            AppError::ServiceUnavailable(_) => "SERVICE_UNAVAILABLE",
+            AppError::Internal { .. } => "INTERNAL_ERROR",
+        }
+    }
+
+    /// 按语言翻译错误信息，内部错误只返回通用提示
+    pub fn message(&self, locale: Locale) -> String {
+        let message = match self {
+            AppError::Internal { .. } => "服务器内部错误，请稍后重试".to_string(),
+            error => error.to_string(),
+        };
+        i18n::translate(locale, &message).into_owned()
+    }
+
+    /// 生成错误响应：{"error": 信息, "code": 错误码, "request_id": 请求 ID, ...附加字段}
+    pub fn render(&self, locale: Locale, request_id: Option<&str>) -> HttpResponse {
+        let mut body = json!({
+            "error": self.message(locale),
+            "code": self.code(),
+        });
+        if let Some(request_id) = request_id {
+            body["request_id"] = json!(request_id);
+        }
+
+        let mut response = HttpResponse::build(self.status_code());
+        match self {
+            AppError::Validation(fields) => {
+                let fields: BTreeMap<_, Vec<_>> = fields
+                    .iter()
+                    .map(|(field, messages)| (field, messages.iter().map(|m| i18n::translate(locale, m)).collect()))
+                    .collect();
+                body["fields"] = json!(fields);
+            }
+            AppError::InvalidCredentials { captcha_required } => body["captcha_required"] = json!(captcha_required),
+            AppError::CaptchaRequired(_) => body["captcha_required"] = json!(true),
+            AppError::Locked { until, .. } => body["locked_until"] = json!(until.to_rfc3339()),
+            AppError::TooManyRequests { retry_after: Some(secs), .. } => {
+                response.insert_header((header::RETRY_AFTER, secs.to_string()));
+                body["retry_after"] = json!(secs);
+            }
+            AppError::Internal { context, source } if *EXPOSE_DETAILS => {
+                body["detail"] = json!(format!("{}: {:#}", context, source));
+            }
+            _ => (),
+        }
+        response.json(body)
+    }
+}
+
+impl ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::BadRequest(_) | AppError::CaptchaRequired(_) => StatusCode::BAD_REQUEST,
+            AppError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::Unauthorized(_) | AppError::InvalidCredentials { .. } => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) | AppError::IdentityRequired(_) => StatusCode::FORBIDDEN,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::Locked { .. } => StatusCode::LOCKED,
+            AppError::TooManyRequests { .. } => StatusCode::TOO_MANY_REQUESTS,
+            AppError::ServiceUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::Internal { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// 没有经过 request_id 中间件时使用默认语言，且不带请求 ID
+    fn error_response(&self) -> HttpResponse {
+        self.render(Locale::default(), None)
+    }
+}
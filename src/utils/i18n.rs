@@ -0,0 +1,316 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::LazyLock;
+
+use crate::utils::locale::Locale;
+
+const PLACEHOLDER: &str = "{}";
+
+/// 英文消息目录：中文原文 -> 英文译文。
+/// 中文原文即消息键，处理器和错误中直接写中文；{} 为按顺序代入的参数，译文中参数顺序需与原文一致
+const EN: &[(&str, &str)] = &[
+    // 通用
+    ("服务器内部错误，请稍后重试", "Internal server error, please try again later"),
+    ("请求参数校验失败", "Request validation failed"),
+    ("接口不存在", "Endpoint not found"),
+    ("请求过于频繁，请稍后重试", "Too many requests, please try again later"),
+    ("权限不足", "Permission denied"),
+    ("不能为空", "Must not be blank"),
+    ("名称不能超过 100 个字符", "Name must be at most 100 characters"),
+    ("语言设置已更新", "Language preference updated"),
+    ("不支持的语言", "Unsupported language"),
+    // 认证与令牌
+    ("缺少或无效的授权令牌", "Missing or invalid authorization token"),
+    ("缺少授权令牌", "Missing authorization token"),
+    ("无效令牌", "Invalid token"),
+    ("令牌已过期", "Token expired"),
+    ("令牌已失效", "Token has been revoked"),
+    ("令牌校验失败", "Token verification failed"),
+    ("刷新令牌已失效，请重新登录", "Refresh token is no longer valid, please sign in again"),
+    ("令牌刷新成功", "Token refreshed"),
+    ("API 密钥不能执行此操作", "API keys cannot perform this operation"),
+    // 注册与登录
+    ("用户名需为 3 到 32 位字母、数字或下划线，并以字母开头", "Username must be 3 to 32 letters, digits or underscores and start with a letter"),
+    ("邮箱格式不正确", "Invalid email address"),
+    ("手机号格式不正确", "Invalid phone number"),
+    ("密码长度需在 {} 到 {} 个字符之间", "Password must be between {} and {} characters"),
+    ("密码需包含字母、数字和符号中的至少两种", "Password must contain at least two of letters, digits and symbols"),
+    ("该密码已出现在泄露密码库中，请更换", "This password has appeared in a data breach, please choose another"),
+    ("用户名/邮箱/手机号已存在", "Username, email or phone number already exists"),
+    ("注册成功", "Registration successful"),
+    ("请输入用户名、邮箱或手机号", "Please enter your username, email or phone number"),
+    ("请输入密码", "Please enter your password"),
+    ("用户名或密码错误", "Invalid username or password"),
+    ("登录失败次数过多，请 {} 秒后重试", "Too many failed sign-in attempts, please try again in {} seconds"),
+    ("登录失败次数过多，账户已临时锁定", "Too many failed sign-in attempts, the account is temporarily locked"),
+    ("账户已停用，无法登录", "This account has been disabled and cannot sign in"),
+    ("当前网络登录失败次数过多，请稍后重试", "Too many failed sign-in attempts from your network, please try again later"),
+    ("请完成人机验证", "Please complete the captcha"),
+    ("人机验证服务不可用", "Captcha service is unavailable"),
+    ("人机验证失败", "Captcha verification failed"),
+    ("请完成两步验证", "Two-factor authentication required"),
+    ("登录成功", "Signed in successfully"),
+    ("登录已过期，请重新登录", "Sign-in session expired, please sign in again"),
+    ("验证码错误次数过多，请重新登录", "Too many incorrect codes, please sign in again"),
+    ("请提供验证码或恢复码", "Please provide a verification code or recovery code"),
+    ("请提供用户名", "Please provide a username"),
+    ("该账号无法使用通行密钥登录", "This account cannot sign in with a passkey"),
+    ("账号不可用", "Account unavailable"),
+    ("登出成功", "Signed out successfully"),
+    ("会话不存在", "Session not found"),
+    ("会话已注销", "Session revoked"),
+    ("已退出其他设备", "Signed out of all other devices"),
+    // 验证码
+    ("验证码为 6 位数字", "Verification code must be 6 digits"),
+    ("验证码错误", "Incorrect verification code"),
+    ("验证码错误或已过期", "Incorrect or expired verification code"),
+    ("无效验证码", "Invalid verification code"),
+    ("验证码已发送", "Verification code sent"),
+    ("验证成功", "Verified successfully"),
+    ("密码重置成功", "Password reset successfully"),
+    ("邮件服务未配置", "Email service is not configured"),
+    ("邮件发送失败，请稍后重试", "Failed to send email, please try again later"),
+    ("短信服务未配置", "SMS service is not configured"),
+    ("短信发送失败，请稍后重试", "Failed to send SMS, please try again later"),
+    ("发送过于频繁，请 {} 秒后重试", "Sending too frequently, please try again in {} seconds"),
+    ("该地址发送次数过多，请稍后重试", "Too many codes sent to this address, please try again later"),
+    ("当前网络请求验证码次数过多，请稍后重试", "Too many code requests from your network, please try again later"),
+    ("不支持的验证码用途", "Unsupported verification code purpose"),
+    // 两步验证与通行密钥
+    ("已启用两步验证", "Two-factor authentication is already enabled"),
+    ("请使用验证器扫描二维码并输入验证码完成绑定", "Scan the QR code with your authenticator app and enter the code to finish setup"),
+    ("验证码错误或未开始绑定", "Incorrect code or setup not started"),
+    ("两步验证已启用，请妥善保存恢复码", "Two-factor authentication enabled, keep your recovery codes safe"),
+    ("两步验证已重置", "Two-factor authentication reset"),
+    ("两步验证服务未配置", "Two-factor authentication is not configured"),
+    ("验证已过期，请重试", "Verification expired, please try again"),
+    ("该验证器已注册", "This authenticator is already registered"),
+    ("验证器校验失败", "Authenticator verification failed"),
+    ("通行密钥已添加", "Passkey added"),
+    ("通行密钥不存在", "Passkey not found"),
+    ("名称已更新", "Name updated"),
+    ("通行密钥已移除", "Passkey removed"),
+    // API 密钥
+    ("无效的 API 密钥", "Invalid API key"),
+    ("来源 IP 不在 API 密钥允许范围内", "Source IP is not allowed for this API key"),
+    ("API 密钥校验失败", "API key verification failed"),
+    ("API 密钥无权访问该接口", "This API key is not authorized for this endpoint"),
+    ("API 密钥数量已达上限", "API key limit reached"),
+    ("API 密钥已创建，请立即保存，关闭后将无法再次查看", "API key created. Save it now, it will not be shown again"),
+    ("API 密钥不存在", "API key not found"),
+    ("API 密钥已吊销", "API key revoked"),
+    ("过期时间必须晚于当前时间", "Expiry time must be in the future"),
+    ("至少选择一个授权范围", "Select at least one scope"),
+    ("无效的 IP 地址: {}", "Invalid IP address: {}"),
+    // 实名认证
+    ("身份证号格式不正确", "Invalid ID card number"),
+    ("姓名不能超过 50 个字符", "Name must be at most 50 characters"),
+    ("实名认证服务未配置", "Identity verification is not configured"),
+    ("已完成实名认证", "Identity already verified"),
+    ("实名认证正在审核中", "Identity verification is under review"),
+    ("该证件已被其他账号认证", "This ID card has already been verified by another account"),
+    ("姓名与身份证号不一致", "Name does not match the ID card number"),
+    ("实名认证已通过", "Identity verified"),
+    ("已提交实名认证，请等待审核", "Identity verification submitted, please wait for review"),
+    ("实名认证已驳回", "Identity verification rejected"),
+    ("请先完成实名认证", "Please complete identity verification first"),
+    ("无效的审核状态", "Invalid review status"),
+    ("实名认证申请不存在", "Identity verification request not found"),
+    ("该申请已审核", "This request has already been reviewed"),
+    ("审核备注不能超过 500 个字符", "Review note must be at most 500 characters"),
+    // 个人资料
+    ("姓名不能超过 100 个字符", "Name must be at most 100 characters"),
+    ("头像地址不能超过 500 个字符", "Avatar URL must be at most 500 characters"),
+    ("头像地址格式不正确", "Invalid avatar URL"),
+    ("省份不能超过 50 个字符", "Province must be at most 50 characters"),
+    ("城市不能超过 50 个字符", "City must be at most 50 characters"),
+    ("实名认证后不能修改姓名", "Your name cannot be changed after identity verification"),
+    ("个人资料已更新", "Profile updated"),
+    ("上传内容格式不正确", "Malformed upload"),
+    ("请选择要上传的头像文件", "Please choose an avatar image to upload"),
+    ("头像文件不能超过 {} MB", "Avatar image must be at most {} MB"),
+    ("头像只支持 PNG、JPEG 或 WebP 格式", "Avatar must be a PNG, JPEG or WebP image"),
+    ("头像已更新", "Avatar updated"),
+    ("请输入当前密码", "Please enter your current password"),
+    ("当前密码错误", "Current password is incorrect"),
+    ("新密码不能与当前密码相同", "New password must be different from the current one"),
+    ("密码已修改", "Password changed"),
+    ("该邮箱已被其他账号使用", "This email address is already used by another account"),
+    ("邮箱已更换", "Email address changed"),
+    ("该手机号已被其他账号使用", "This phone number is already used by another account"),
+    ("手机号已更换", "Phone number changed"),
+    // 账户注销
+    ("员工账号不能自助注销", "Staff accounts cannot be deleted by themselves"),
+    ("还有 {} 台云主机未删除，请先删除全部云主机", "{} instance(s) still exist, please delete all instances first"),
+    ("还有 {} 张账单未支付，请先处理", "{} unpaid invoice(s), please settle them first"),
+    ("账户余额为 {} 元，需为零才能注销", "Your balance is CNY {}, it must be zero to delete the account"),
+    ("您是 {} 个组织的唯一所有者，请先转让所有权", "You are the only owner of {} organization(s), please transfer ownership first"),
+    ("账户已注销", "Account deleted"),
+    // 资源
+    ("实例不存在", "Instance not found"),
+    ("工单不存在", "Ticket not found"),
+    ("账单不存在", "Invoice not found"),
+    ("用户不存在", "User not found"),
+    ("不能修改自己的角色", "You cannot change your own role"),
+    ("角色未变化", "Role unchanged"),
+    ("角色已更新", "Role updated"),
+    ("资源对账完成", "Capacity reconciliation completed"),
+    ("无效的抓取令牌", "Invalid scrape token"),
+    ("不支持的监控指标: {}", "Unsupported metric: {}"),
+    // 组织
+    ("组织不存在", "Organization not found"),
+    ("创建的组织数量已达上限", "You have reached the maximum number of organizations"),
+    ("组织已创建", "Organization created"),
+    ("成员不存在", "Member not found"),
+    ("组织至少需要保留一名所有者", "An organization must keep at least one owner"),
+    ("成员角色已更新", "Member role updated"),
+    ("已退出组织", "You have left the organization"),
+    ("成员已移除", "Member removed"),
+    ("该用户已是组织成员", "This user is already a member of the organization"),
+    ("邀请已发送", "Invitation sent"),
+    ("邀请不存在", "Invitation not found"),
+    ("邀请已撤销", "Invitation revoked"),
+    ("邀请链接无效", "Invalid invitation link"),
+    ("邀请链接无效或已过期", "The invitation link is invalid or has expired"),
+    ("已加入组织", "Joined the organization"),
+    ("您已是该组织成员", "You are already a member of this organization"),
+    ("实例已属于该账户", "The instance already belongs to this account"),
+    ("实例正在创建或删除，暂不能转移", "The instance is being created or deleted and cannot be transferred now"),
+    ("实例已转移", "Instance transferred"),
+    ("只有组织所有者可以把实例转入个人账户", "Only organization owners can move instances into a personal account"),
+    // 告警
+    ("不支持的通知渠道类型", "Unsupported notification channel type"),
+    ("无效的通知地址", "Invalid notification target"),
+    ("通知地址必须是可访问的公网地址", "Notification target must be a reachable public address"),
+    ("通知地址不能超过 500 个字符", "Notification target must be at most 500 characters"),
+    ("通知渠道创建成功", "Notification channel created"),
+    ("通知渠道不存在", "Notification channel not found"),
+    ("通知渠道已删除", "Notification channel deleted"),
+    ("不支持的监控指标", "Unsupported metric"),
+    ("不支持的比较方式", "Unsupported comparator"),
+    ("缺少告警阈值", "Alert threshold is required"),
+    ("持续时间必须在 1 到 1440 分钟之间", "Duration must be between 1 and 1440 minutes"),
+    ("告警规则创建成功", "Alert rule created"),
+    ("告警规则不存在", "Alert rule not found"),
+    ("告警规则更新成功", "Alert rule updated"),
+    ("告警规则已删除", "Alert rule deleted"),
+    // 邮件
+    ("验证邮箱", "verify your email"),
+    ("重置密码", "reset your password"),
+    ("【OpenVirt】邮箱验证码", "[OpenVirt] Email verification code"),
+    ("【OpenVirt】重置密码验证码", "[OpenVirt] Password reset code"),
+    ("更换绑定邮箱", "change your email address"),
+    ("【OpenVirt】更换邮箱验证码", "[OpenVirt] Email change verification code"),
+    (
+        "您好，\n\n您正在{}，验证码为：{}\n\n验证码 {} 分钟内有效，请勿泄露给他人。如非本人操作，请忽略本邮件。\n\nOpenVirt",
+        "Hello,\n\nUse the following code to {}: {}\n\nThe code expires in {} minutes. Never share it with anyone. If you did not request this, you can ignore this email.\n\nOpenVirt",
+    ),
+    ("【OpenVirt】账户已临时锁定", "[OpenVirt] Your account has been temporarily locked"),
+    ("所有者", "owner"),
+    ("管理员", "admin"),
+    ("运维", "operator"),
+    ("只读成员", "viewer"),
+    ("财务", "billing"),
+    ("【OpenVirt】{} 邀请您加入组织 {}", "[OpenVirt] {} invited you to join {}"),
+    (
+        "您好，\n\n{} 邀请您以{}身份加入 OpenVirt 组织“{}”。请登录后打开以下链接接受邀请：\n\n{}\n\n链接 {} 天内有效。如不认识邀请人，请忽略本邮件。\n\nOpenVirt",
+        "Hello,\n\n{} invited you to take the {} role in the OpenVirt organization \"{}\". Sign in and open the link below to accept:\n\n{}\n\nThe link expires in {} days. If you don't know the sender, you can ignore this email.\n\nOpenVirt",
+    ),
+    (
+        "{}，您好：\n\n您的账户连续多次密码错误（最近一次来自 {}），为保护账户安全已临时锁定，将于北京时间 {} 自动解锁。\n\n如非本人操作，建议解锁后立即修改密码并开启两步验证。\n\nOpenVirt",
+        "Hello {},\n\nWe locked your account after repeated failed sign-in attempts (most recently from {}). It will unlock automatically at {} UTC.\n\nIf this wasn't you, change your password and turn on two-factor authentication once the lock expires.\n\nOpenVirt",
+    ),
+];
+
+static EN_MESSAGES: LazyLock<HashMap<&'static str, &'static str>> = LazyLock::new(|| EN.iter().copied().collect());
+
+/// 带参数的消息按 {} 切分，用于从已生成的文本中取回参数
+static EN_TEMPLATES: LazyLock<Vec<(Vec<&'static str>, &'static str)>> = LazyLock::new(|| {
+    EN.iter()
+        .filter(|(zh, _)| zh.contains(PLACEHOLDER))
+        .map(|(zh, en)| (zh.split(PLACEHOLDER).collect(), *en))
+        .collect()
+});
+
+/// 翻译已生成的消息，带参数的按模板匹配后把参数代入译文。目录中没有的消息原样返回
+pub fn translate(locale: Locale, text: &str) -> Cow<'_, str> {
+    if locale == Locale::ZhCn {
+        return Cow::Borrowed(text);
+    }
+    if let Some(translated) = EN_MESSAGES.get(text) {
+        return Cow::Borrowed(translated);
+    }
+    EN_TEMPLATES
+        .iter()
+        .find_map(|(pieces, translated)| Some(substitute(translated, &extract_args(pieces, text)?)))
+        .map_or(Cow::Borrowed(text), Cow::Owned)
+}
+
+/// 按语言选择模板并依次代入参数，用于邮件等需要拼接内容的文本
+pub fn format_message(locale: Locale, template: &'static str, args: &[&dyn Display]) -> String {
+    let template = match locale {
+        Locale::ZhCn => template,
+        Locale::En => EN_MESSAGES.get(template).copied().unwrap_or(template),
+    };
+    substitute(template, args)
+}
+
+fn substitute<T: Display>(template: &str, args: &[T]) -> String {
+    let mut pieces = template.split(PLACEHOLDER);
+    let mut out = pieces.next().unwrap_or_default().to_string();
+    for (index, piece) in pieces.enumerate() {
+        if let Some(arg) = args.get(index) {
+            out.push_str(&arg.to_string());
+        }
+        out.push_str(piece);
+    }
+    out
+}
+
+/// 文本与模板的固定部分逐段对齐，取出各个 {} 位置上的内容
+fn extract_args<'a>(pieces: &[&str], text: &'a str) -> Option<Vec<&'a str>> {
+    let (first, rest) = pieces.split_first()?;
+    let mut remaining = text.strip_prefix(first)?;
+    let mut args = Vec::with_capacity(rest.len());
+    for (index, piece) in rest.iter().enumerate() {
+        let end = if index + 1 == rest.len() {
+            remaining.strip_suffix(piece)?.len()
+        } else {
+            remaining.find(piece)?
+        };
+        args.push(&remaining[..end]);
+        remaining = &remaining[end + piece.len()..];
+    }
+    Some(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extract_args_reads_placeholders_in_order() {
+        assert_eq!(extract_args(&["还有 ", " 张账单未支付"], "还有 3 张账单未支付"), Some(vec!["3"]));
+        assert_eq!(extract_args(&["", " 邀请您加入组织 ", ""], "张三 邀请您加入组织 运维组"), Some(vec!["张三", "运维组"]));
+    }
+
+    #[test]
+    fn extract_args_allows_empty_arguments() {
+        assert_eq!(extract_args(&["名称: ", ""], "名称: "), Some(vec![""]));
+    }
+
+    #[test]
+    fn extract_args_rejects_mismatched_text() {
+        assert_eq!(extract_args(&["还有 ", " 张账单未支付"], "还有 3 台云主机未删除"), None);
+        assert_eq!(extract_args(&["还有 ", " 张账单未支付"], "共有 3 张账单未支付"), None);
+    }
+
+    #[test]
+    fn translate_fills_english_templates() {
+        assert_eq!(translate(Locale::En, "还有 3 张账单未支付，请先处理"), "3 unpaid invoice(s), please settle them first");
+        assert_eq!(translate(Locale::En, "权限不足"), "Permission denied");
+        assert_eq!(translate(Locale::ZhCn, "权限不足"), "权限不足");
+        assert_eq!(translate(Locale::En, "未收录的消息"), "未收录的消息");
+    }
+}
@@ -1,30 +1,329 @@
-use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ed25519_dalek::pkcs8::{DecodePrivateKey, DecodePublicKey};
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, OctetKeyPairParameters,
+    OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+};
+use jsonwebtoken::{decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use rsa::pkcs1::{DecodeRsaPrivateKey, DecodeRsaPublicKey};
+use rsa::traits::PublicKeyParts;
+use rsa::{RsaPrivateKey, RsaPublicKey};
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
+use uuid::Uuid;
 
-#[derive(Debug, Serialize, Deserialize)]
+pub const JWT_ISSUER: &str = "openvirt";
+pub const JWT_AUDIENCE: &str = "openvirt-api";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
-    pub sub: String,  // username
-    pub exp: usize,   // expiry timestamp
+    pub sub: String,       // user id
+    pub username: String,
+    pub role: String,
+    pub sid: String,       // 所属会话（刷新令牌 family）
+    pub jti: String,       // token id，用于吊销
+    pub iat: usize,        // issued at
+    pub exp: usize,        // expiry timestamp
+    pub iss: String,
+    pub aud: String,
+}
+
+/// 令牌所代表的用户
+pub struct TokenSubject<'a> {
+    pub user_id: Uuid,
+    pub username: &'a str,
+    pub role: &'a str,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum JwtKeyError {
+    #[error("环境变量 {0} 未设置")]
+    MissingEnv(&'static str),
+    #[error("读取密钥文件 {path} 失败: {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("密钥文件 {0} 不是受支持的 RSA 或 Ed25519 密钥")]
+    UnsupportedKey(String),
+    #[error("验证密钥配置 {0} 格式错误，应为 kid:path")]
+    InvalidEntry(String),
+    #[error("密钥 ID {0} 重复")]
+    DuplicateKid(String),
+    #[error("加载密钥失败: {0}")]
+    Jwt(#[from] jsonwebtoken::errors::Error),
+}
+
+struct VerificationKey {
+    kid: String,
+    algorithm: Algorithm,
+    decoding_key: DecodingKey,
+    jwk: Jwk,
+}
+
+/// 签名密钥和验证密钥集合。轮换时新密钥用于签名，
+/// 旧密钥的公钥继续保留在验证集合中，直到旧令牌全部过期
+pub struct JwtKeys {
+    signing_kid: String,
+    signing_algorithm: Algorithm,
+    encoding_key: EncodingKey,
+    verification: Vec<VerificationKey>,
+}
+
+fn required_env(name: &'static str) -> Result<String, JwtKeyError> {
+    std::env::var(name)
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+        .ok_or(JwtKeyError::MissingEnv(name))
+}
+
+fn read_key_file(path: &str) -> Result<String, JwtKeyError> {
+    std::fs::read_to_string(path).map_err(|source| JwtKeyError::Io { path: path.to_owned(), source })
+}
+
+fn rsa_params(key: &RsaPublicKey) -> (Algorithm, AlgorithmParameters) {
+    let params = RSAKeyParameters {
+        key_type: RSAKeyType::RSA,
+        n: URL_SAFE_NO_PAD.encode(key.n().to_bytes_be()),
+        e: URL_SAFE_NO_PAD.encode(key.e().to_bytes_be()),
+    };
+    (Algorithm::RS256, AlgorithmParameters::RSA(params))
+}
+
+fn ed25519_params(key: &ed25519_dalek::VerifyingKey) -> (Algorithm, AlgorithmParameters) {
+    let params = OctetKeyPairParameters {
+        key_type: OctetKeyPairType::OctetKeyPair,
+        curve: EllipticCurve::Ed25519,
+        x: URL_SAFE_NO_PAD.encode(key.to_bytes()),
+    };
+    (Algorithm::EdDSA, AlgorithmParameters::OctetKeyPair(params))
+}
+
+fn verification_key(kid: &str, (algorithm, params): (Algorithm, AlgorithmParameters)) -> Result<VerificationKey, JwtKeyError> {
+    let jwk = Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            algorithm: Some(algorithm),
+            key_id: Some(kid.to_owned()),
+            ..Default::default()
+        },
+        algorithm: params,
+    };
+    Ok(VerificationKey {
+        kid: kid.to_owned(),
+        algorithm,
+        decoding_key: DecodingKey::from_jwk(&jwk)?,
+        jwk,
+    })
+}
+
+/// 私钥支持 RSA（PKCS#1 或 PKCS#8）和 Ed25519（PKCS#8），算法由密钥类型决定
+fn load_signing_key(kid: &str, path: &str) -> Result<(EncodingKey, VerificationKey), JwtKeyError> {
+    let pem = read_key_file(path)?;
+    if let Ok(key) = RsaPrivateKey::from_pkcs8_pem(&pem).or_else(|_| RsaPrivateKey::from_pkcs1_pem(&pem)) {
+        let encoding_key = EncodingKey::from_rsa_pem(pem.as_bytes())?;
+        return Ok((encoding_key, verification_key(kid, rsa_params(&key.to_public_key()))?));
+    }
+    if let Ok(key) = ed25519_dalek::SigningKey::from_pkcs8_pem(&pem) {
+        let encoding_key = EncodingKey::from_ed_pem(pem.as_bytes())?;
+        return Ok((encoding_key, verification_key(kid, ed25519_params(&key.verifying_key()))?));
+    }
+    Err(JwtKeyError::UnsupportedKey(path.to_owned()))
+}
+
+fn load_public_key(kid: &str, path: &str) -> Result<VerificationKey, JwtKeyError> {
+    let pem = read_key_file(path)?;
+    if let Ok(key) = RsaPublicKey::from_public_key_pem(&pem).or_else(|_| RsaPublicKey::from_pkcs1_pem(&pem)) {
+        return verification_key(kid, rsa_params(&key));
+    }
+    if let Ok(key) = ed25519_dalek::VerifyingKey::from_public_key_pem(&pem) {
+        return verification_key(kid, ed25519_params(&key));
+    }
+    Err(JwtKeyError::UnsupportedKey(path.to_owned()))
 }
 
-pub fn create_jwt(username: &str, secret: &str, expiry: DateTime<Utc>) -> Result<String, jsonwebtoken::errors::Error> {
+impl JwtKeys {
+    /// 从环境变量加载密钥，配置缺失或无效时返回错误，由调用方终止启动
+    pub fn from_env() -> Result<Self, JwtKeyError> {
+        let signing_kid = required_env("JWT_SIGNING_KEY_ID")?;
+        let signing_path = required_env("JWT_SIGNING_KEY_FILE")?;
+        let (encoding_key, signing_public) = load_signing_key(&signing_kid, &signing_path)?;
+        let signing_algorithm = signing_public.algorithm;
+
+        let mut verification = vec![signing_public];
+        // 轮换期间仍需接受的旧公钥，格式 kid:path,kid:path
+        if let Ok(entries) = std::env::var("JWT_VERIFICATION_KEYS") {
+            for entry in entries.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+                let (kid, path) = entry
+                    .split_once(':')
+                    .ok_or_else(|| JwtKeyError::InvalidEntry(entry.to_owned()))?;
+                let key = load_public_key(kid.trim(), path.trim())?;
+                if verification.iter().any(|k| k.kid == key.kid) {
+                    return Err(JwtKeyError::DuplicateKid(key.kid));
+                }
+                verification.push(key);
+            }
+        }
+
+        Ok(Self { signing_kid, signing_algorithm, encoding_key, verification })
+    }
+
+    /// 供其他服务验证令牌的公钥集合
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet { keys: self.verification.iter().map(|k| k.jwk.clone()).collect() }
+    }
+}
+
+pub fn create_jwt(keys: &JwtKeys, subject: &TokenSubject, session_id: Uuid, expiry: DateTime<Utc>) -> Result<String, jsonwebtoken::errors::Error> {
     let claims = Claims {
-        sub: username.to_owned(),
+        sub: subject.user_id.to_string(),
+        username: subject.username.to_owned(),
+        role: subject.role.to_owned(),
+        sid: session_id.to_string(),
+        jti: Uuid::new_v4().to_string(),
+        iat: Utc::now().timestamp() as usize,
         exp: expiry.timestamp() as usize,
+        iss: JWT_ISSUER.to_owned(),
+        aud: JWT_AUDIENCE.to_owned(),
     };
-    
-    encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(secret.as_ref()),
-    )
-}
-
-pub fn validate_jwt(token: &str, secret: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
-    decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(secret.as_ref()),
-        &Validation::new(Algorithm::HS256),
-    ).map(|data| data.claims)
+
+    let mut header = Header::new(keys.signing_algorithm);
+    header.kid = Some(keys.signing_kid.clone());
+    encode(&header, &claims, &keys.encoding_key)
+}
+
+pub fn validate_jwt(keys: &JwtKeys, token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
+    // 按 kid 选择验证密钥，算法以密钥为准而不是信任令牌头
+    let kid = decode_header(token)?.kid.ok_or(ErrorKind::InvalidToken)?;
+    let key = keys
+        .verification
+        .iter()
+        .find(|k| k.kid == kid)
+        .ok_or(ErrorKind::InvalidToken)?;
+
+    let mut validation = Validation::new(key.algorithm);
+    validation.set_issuer(&[JWT_ISSUER]);
+    validation.set_audience(&[JWT_AUDIENCE]);
+
+    decode::<Claims>(token, &key.decoding_key, &validation).map(|data| data.claims)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::pkcs8::spki::der::pem::LineEnding;
+    use ed25519_dalek::pkcs8::{EncodePrivateKey, EncodePublicKey, KeypairBytes};
+
+    /// 把固定种子生成的 Ed25519 密钥写入临时文件，返回私钥和公钥路径。
+    /// 私钥按 openssl genpkey 的 PKCS#8 v1 格式输出，不附带公钥
+    fn key_files(seed: u8) -> (String, String) {
+        let key = ed25519_dalek::SigningKey::from_bytes(&[seed; 32]);
+        let dir = std::env::temp_dir();
+        let private = dir.join(format!("openvirt-jwt-{}.pem", Uuid::new_v4()));
+        let public = dir.join(format!("openvirt-jwt-{}.pub.pem", Uuid::new_v4()));
+        std::fs::write(&private, KeypairBytes { secret_key: [seed; 32], public_key: None }.to_pkcs8_pem(LineEnding::LF).unwrap().as_bytes()).unwrap();
+        std::fs::write(&public, key.verifying_key().to_public_key_pem(LineEnding::LF).unwrap()).unwrap();
+        (private.to_string_lossy().into_owned(), public.to_string_lossy().into_owned())
+    }
+
+    fn keys(kid: &str, seed: u8, previous: &[(&str, u8)]) -> JwtKeys {
+        let (private, _) = key_files(seed);
+        let (encoding_key, signing_public) = load_signing_key(kid, &private).unwrap();
+        let mut verification = vec![signing_public];
+        for (kid, seed) in previous {
+            let (_, public) = key_files(*seed);
+            verification.push(load_public_key(kid, &public).unwrap());
+        }
+        JwtKeys {
+            signing_kid: kid.to_owned(),
+            signing_algorithm: Algorithm::EdDSA,
+            encoding_key,
+            verification,
+        }
+    }
+
+    fn token(keys: &JwtKeys) -> String {
+        let subject = TokenSubject { user_id: Uuid::new_v4(), username: "alice", role: "user" };
+        create_jwt(keys, &subject, Uuid::new_v4(), Utc::now() + chrono::Duration::minutes(5)).unwrap()
+    }
+
+    fn claims() -> Claims {
+        Claims {
+            sub: Uuid::new_v4().to_string(),
+            username: "alice".into(),
+            role: "admin".into(),
+            sid: Uuid::new_v4().to_string(),
+            jti: Uuid::new_v4().to_string(),
+            iat: Utc::now().timestamp() as usize,
+            exp: (Utc::now().timestamp() + 300) as usize,
+            iss: JWT_ISSUER.into(),
+            aud: JWT_AUDIENCE.into(),
+        }
+    }
+
+    #[test]
+    fn signed_token_validates_with_signing_key() {
+        let keys = keys("current", 1, &[]);
+        let claims = validate_jwt(&keys, &token(&keys)).unwrap();
+        assert_eq!(claims.username, "alice");
+        assert_eq!(claims.iss, JWT_ISSUER);
+        assert_eq!(decode_header(&token(&keys)).unwrap().kid.as_deref(), Some("current"));
+    }
+
+    #[test]
+    fn rotated_key_still_validates_old_tokens() {
+        let old = keys("old", 2, &[]);
+        let rotated = keys("new", 1, &[("old", 2)]);
+        assert!(validate_jwt(&rotated, &token(&old)).is_ok());
+        assert_eq!(rotated.jwks().keys.len(), 2);
+    }
+
+    #[test]
+    fn unknown_or_missing_kid_is_rejected() {
+        let keys = keys("current", 1, &[]);
+        let other = self::keys("other", 3, &[]);
+        assert!(validate_jwt(&keys, &token(&other)).is_err());
+
+        let mut header = Header::new(Algorithm::EdDSA);
+        header.kid = None;
+        let (private, _) = key_files(1);
+        let pem = std::fs::read(private).unwrap();
+        let unsigned_kid = encode(&header, &claims(), &EncodingKey::from_ed_pem(&pem).unwrap()).unwrap();
+        assert!(validate_jwt(&keys, &unsigned_kid).is_err());
+    }
+
+    #[test]
+    fn known_kid_with_wrong_key_is_rejected() {
+        let keys = keys("current", 1, &[]);
+        let forged = self::keys("current", 4, &[]);
+        assert!(validate_jwt(&keys, &token(&forged)).is_err());
+    }
+
+    #[test]
+    fn algorithm_in_header_cannot_override_key_algorithm() {
+        let keys = keys("current", 1, &[]);
+        // 用公钥作为 HMAC 密钥伪造 HS256 令牌
+        let (_, public) = key_files(1);
+        let mut header = Header::new(Algorithm::HS256);
+        header.kid = Some("current".into());
+        let forged = encode(&header, &claims(), &EncodingKey::from_secret(&std::fs::read(public).unwrap())).unwrap();
+        assert!(validate_jwt(&keys, &forged).is_err());
+    }
+
+    #[test]
+    fn wrong_audience_or_issuer_is_rejected() {
+        let keys = keys("current", 1, &[]);
+        let mut header = Header::new(Algorithm::EdDSA);
+        header.kid = Some("current".into());
+        let (private, _) = key_files(1);
+        let encoding_key = EncodingKey::from_ed_pem(&std::fs::read(private).unwrap()).unwrap();
+
+        let foreign_audience = Claims { aud: "other-api".into(), ..claims() };
+        assert!(validate_jwt(&keys, &encode(&header, &foreign_audience, &encoding_key).unwrap()).is_err());
+        let foreign_issuer = Claims { iss: "someone-else".into(), ..claims() };
+        assert!(validate_jwt(&keys, &encode(&header, &foreign_issuer, &encoding_key).unwrap()).is_err());
+    }
 }
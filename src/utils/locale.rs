@@ -0,0 +1,101 @@
+use actix_web::dev::Payload;
+use actix_web::http::header::ACCEPT_LANGUAGE;
+use actix_web::{web, Error, FromRequest, HttpMessage, HttpRequest};
+use futures::future::LocalBoxFuture;
+use log::warn;
+
+use crate::database::DbPool;
+use crate::middleware::auth::AuthUser;
+
+/// 界面和通知使用的语言，默认简体中文
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+    #[default]
+    ZhCn,
+    En,
+}
+
+impl Locale {
+    /// 解析语言标签，只看主语言部分，如 zh-CN、zh-Hans、en-US
+    pub fn parse(tag: &str) -> Option<Self> {
+        let primary = tag.trim().split(['-', '_']).next()?.to_ascii_lowercase();
+        match primary.as_str() {
+            "zh" => Some(Locale::ZhCn),
+            "en" => Some(Locale::En),
+            _ => None,
+        }
+    }
+
+    /// 保存到 users.preferred_language 的语言标签
+    pub fn code(self) -> &'static str {
+        match self {
+            Locale::ZhCn => "zh-CN",
+            Locale::En => "en",
+        }
+    }
+
+    /// 用户设置了语言偏好时优先使用，否则按 Accept-Language
+    pub fn for_user(preference: Option<&str>, req: &HttpRequest) -> Self {
+        preference.and_then(Locale::parse).unwrap_or_else(|| Locale::from_request(req))
+    }
+
+    /// 当前请求的语言：已登录用户读取语言偏好，结果缓存在请求扩展中
+    pub async fn resolve(req: &HttpRequest) -> Self {
+        if let Some(locale) = req.extensions().get::<Locale>() {
+            return *locale;
+        }
+
+        let user_id = req.extensions().get::<AuthUser>().map(|user| user.id);
+        let preference = match (user_id, req.app_data::<web::Data<DbPool>>()) {
+            (Some(user_id), Some(pool)) => {
+                sqlx::query_scalar::<_, Option<String>>("SELECT preferred_language FROM users WHERE id = $1")
+                    .bind(user_id)
+                    .fetch_optional(&***pool)
+                    .await
+                    .unwrap_or_else(|e| {
+                        warn!("查询语言偏好失败: {}", e);
+                        None
+                    })
+                    .flatten()
+            }
+            _ => None,
+        };
+
+        let locale = Locale::for_user(preference.as_deref(), req);
+        req.extensions_mut().insert(locale);
+        locale
+    }
+
+    /// 按 Accept-Language 的权重选择第一个支持的语言
+    pub fn from_request(req: &HttpRequest) -> Self {
+        let Some(header) = req.headers().get(ACCEPT_LANGUAGE).and_then(|v| v.to_str().ok()) else {
+            return Locale::default();
+        };
+
+        let mut candidates: Vec<(f32, Locale)> = header
+            .split(',')
+            .filter_map(|item| {
+                let mut parts = item.split(';');
+                let locale = Locale::parse(parts.next()?)?;
+                let quality = parts
+                    .find_map(|p| p.trim().strip_prefix("q="))
+                    .and_then(|q| q.parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                (quality > 0.0).then_some((quality, locale))
+            })
+            .collect();
+        // 稳定排序，权重相同时保留原顺序
+        candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+        candidates.first().map(|(_, locale)| *locale).unwrap_or_default()
+    }
+}
+
+impl FromRequest for Locale {
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        Box::pin(async move { Ok(Locale::resolve(&req).await) })
+    }
+}
@@ -0,0 +1,107 @@
+use std::sync::LazyLock;
+
+use prometheus::{
+    register_gauge_vec, register_histogram_vec, register_int_counter_vec, register_int_gauge,
+    register_int_gauge_vec, Encoder, GaugeVec, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec,
+    TextEncoder,
+};
+
+pub static HTTP_REQUESTS_TOTAL: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "openvirt_http_requests_total",
+        "HTTP 请求总数",
+        &["method", "route", "status"]
+    )
+    .unwrap()
+});
+
+pub static HTTP_REQUEST_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "openvirt_http_request_duration_seconds",
+        "HTTP 请求耗时",
+        &["method", "route"]
+    )
+    .unwrap()
+});
+
+pub static DB_POOL_CONNECTIONS: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "openvirt_db_pool_connections",
+        "数据库连接池连接数",
+        &["state"]
+    )
+    .unwrap()
+});
+
+pub static DB_POOL_MAX_CONNECTIONS: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "openvirt_db_pool_max_connections",
+        "数据库连接池最大连接数"
+    )
+    .unwrap()
+});
+
+pub static PROXMOX_REQUEST_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "openvirt_proxmox_request_duration_seconds",
+        "Proxmox API 调用耗时",
+        &["node"]
+    )
+    .unwrap()
+});
+
+pub static PROXMOX_REQUEST_ERRORS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "openvirt_proxmox_request_errors_total",
+        "Proxmox API 调用失败次数",
+        &["node"]
+    )
+    .unwrap()
+});
+
+pub static JOB_QUEUE_DEPTH: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "openvirt_job_queue_depth",
+        "后台任务待处理数量",
+        &["job"]
+    )
+    .unwrap()
+});
+
+pub static JOB_LAST_RUN: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "openvirt_job_last_run_timestamp_seconds",
+        "后台任务最近一次完成时间",
+        &["job"]
+    )
+    .unwrap()
+});
+
+pub static ACTIVE_USERS: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!("openvirt_active_users", "活跃用户数").unwrap()
+});
+
+pub static VM_INSTANCES: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "openvirt_vm_instances",
+        "各状态虚拟机数量",
+        &["status"]
+    )
+    .unwrap()
+});
+
+pub static NODE_CAPACITY_UTILIZATION: LazyLock<GaugeVec> = LazyLock::new(|| {
+    register_gauge_vec!(
+        "openvirt_node_capacity_utilization",
+        "节点资源分配率 (0-1)",
+        &["node", "resource"]
+    )
+    .unwrap()
+});
+
+/// 以 Prometheus 文本格式导出默认注册表中的全部指标
+pub fn render() -> Result<String, prometheus::Error> {
+    let mut buffer = Vec::new();
+    TextEncoder::new().encode(&prometheus::gather(), &mut buffer)?;
+    String::from_utf8(buffer).map_err(|e| prometheus::Error::Msg(e.to_string()))
+}
@@ -1,3 +1,11 @@
+pub mod crypto;
+pub mod error;
+pub mod i18n;
 pub mod jwt;
+pub mod locale;
+pub mod metrics;
+pub mod password;
+pub mod request;
+pub mod validation;
 
 pub use jwt::{create_jwt, validate_jwt};
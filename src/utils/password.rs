@@ -0,0 +1,235 @@
+use std::collections::HashSet;
+use std::sync::LazyLock;
+
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+use log::{error, info, warn};
+use sha1::{Digest, Sha1};
+
+// OWASP 推荐的 Argon2id 最低参数：19 MiB 内存、2 次迭代、1 个并行度
+const DEFAULT_MEMORY_KIB: u32 = 19 * 1024;
+const DEFAULT_ITERATIONS: u32 = 2;
+const DEFAULT_PARALLELISM: u32 = 1;
+const DEFAULT_MIN_LENGTH: usize = 8;
+const MAX_LENGTH: usize = 128;
+
+// 未配置泄露密码库时使用的常见弱密码
+const COMMON_PASSWORDS: &[&str] = &[
+    "12345678", "123456789", "1234567890", "11111111", "88888888", "00000000", "12341234",
+    "password", "password1", "passw0rd", "qwertyui", "qwerty123", "1qaz2wsx", "abc12345",
+    "a1234567", "iloveyou", "woaini1314", "admin123", "root1234", "aa123456",
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum PasswordError {
+    #[error("密码长度需在 {0} 到 {1} 个字符之间")]
+    Length(usize, usize),
+    #[error("密码需包含字母、数字和符号中的至少两种")]
+    Weak,
+    #[error("该密码已出现在泄露密码库中，请更换")]
+    Breached,
+    #[error("密码加密失败: {0}")]
+    Hash(argon2::password_hash::Error),
+    #[error("密码计算任务失败: {0}")]
+    Task(tokio::task::JoinError),
+}
+
+/// 校验结果，旧算法或参数过时的哈希需要在登录成功后重新计算
+pub struct Verification {
+    pub valid: bool,
+    pub needs_rehash: bool,
+}
+
+fn env_u32(name: &str, default: u32) -> u32 {
+    std::env::var(name)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+/// 参数来自 ARGON2_MEMORY_KIB / ARGON2_ITERATIONS / ARGON2_PARALLELISM，配置无效时启动即报错
+static HASHER: LazyLock<Argon2<'static>> = LazyLock::new(|| {
+    let params = Params::new(
+        env_u32("ARGON2_MEMORY_KIB", DEFAULT_MEMORY_KIB),
+        env_u32("ARGON2_ITERATIONS", DEFAULT_ITERATIONS),
+        env_u32("ARGON2_PARALLELISM", DEFAULT_PARALLELISM),
+        None,
+    )
+    .expect("Invalid Argon2 parameters");
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+});
+
+/// 账户不存在时用来校验的固定哈希，使响应耗时与账户存在时一致
+static DUMMY_HASH: LazyLock<String> = LazyLock::new(|| {
+    let salt = SaltString::generate(&mut OsRng);
+    HASHER
+        .hash_password(b"openvirt-dummy-password", &salt)
+        .expect("Failed to hash dummy password")
+        .to_string()
+});
+
+/// PASSWORD_BREACH_LIST_FILE 每行一个明文密码或 SHA-1（可带 HIBP 格式的 :次数 后缀），统一保存为大写 SHA-1
+static BREACHED: LazyLock<HashSet<String>> = LazyLock::new(|| {
+    let Ok(path) = std::env::var("PASSWORD_BREACH_LIST_FILE") else {
+        return COMMON_PASSWORDS.iter().map(|p| sha1_upper(p)).collect();
+    };
+    match std::fs::read_to_string(&path) {
+        Ok(content) => {
+            let list: HashSet<String> = content
+                .lines()
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty())
+                .map(|line| {
+                    let hash = line.split(':').next().unwrap_or(line);
+                    if hash.len() == 40 && hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+                        hash.to_ascii_uppercase()
+                    } else {
+                        sha1_upper(line)
+                    }
+                })
+                .collect();
+            info!("已加载 {} 条泄露密码记录", list.len());
+            list
+        }
+        Err(e) => {
+            warn!("读取泄露密码库 {} 失败: {}，使用内置弱密码列表", path, e);
+            COMMON_PASSWORDS.iter().map(|p| sha1_upper(p)).collect()
+        }
+    }
+});
+
+fn sha1_upper(password: &str) -> String {
+    hex::encode_upper(Sha1::digest(password.as_bytes()))
+}
+
+/// 启动时加载参数和泄露密码库，配置错误时尽早暴露
+pub fn init() {
+    LazyLock::force(&HASHER);
+    LazyLock::force(&DUMMY_HASH);
+    LazyLock::force(&BREACHED);
+}
+
+/// 注册和重置密码时校验长度、字符种类及是否在泄露密码库中
+pub fn check_policy(password: &str) -> Result<(), PasswordError> {
+    let min_length = std::env::var("PASSWORD_MIN_LENGTH")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_MIN_LENGTH);
+    let length = password.chars().count();
+    if length < min_length || length > MAX_LENGTH {
+        return Err(PasswordError::Length(min_length, MAX_LENGTH));
+    }
+    let classes = [
+        password.chars().any(|c| c.is_alphabetic()),
+        password.chars().any(|c| c.is_ascii_digit()),
+        password.chars().any(|c| !c.is_alphanumeric()),
+    ];
+    if classes.iter().filter(|present| **present).count() < 2 {
+        return Err(PasswordError::Weak);
+    }
+    if BREACHED.contains(&sha1_upper(password)) || BREACHED.contains(&sha1_upper(&password.to_lowercase())) {
+        return Err(PasswordError::Breached);
+    }
+    Ok(())
+}
+
+/// Argon2 计算耗时较长，放到阻塞线程池执行，避免占用异步工作线程
+pub async fn hash_password(password: &str) -> Result<String, PasswordError> {
+    let password = password.to_owned();
+    tokio::task::spawn_blocking(move || hash_blocking(&password))
+        .await
+        .map_err(PasswordError::Task)?
+}
+
+/// 同时支持 Argon2 和旧的 bcrypt 哈希，同样在阻塞线程池中执行
+pub async fn verify_password(password: &str, stored: &str) -> Verification {
+    let (password, stored) = (password.to_owned(), stored.to_owned());
+    tokio::task::spawn_blocking(move || verify_blocking(&password, &stored))
+        .await
+        .unwrap_or_else(|e| {
+            error!("密码校验任务失败: {}", e);
+            Verification { valid: false, needs_rehash: false }
+        })
+}
+
+/// 账户不存在时执行一次同等开销的校验，结果总是失败
+pub async fn verify_dummy(password: &str) {
+    let _ = verify_password(password, &DUMMY_HASH).await;
+}
+
+fn hash_blocking(password: &str) -> Result<String, PasswordError> {
+    let salt = SaltString::generate(&mut OsRng);
+    HASHER
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(PasswordError::Hash)
+}
+
+fn verify_blocking(password: &str, stored: &str) -> Verification {
+    if stored.starts_with("$2") {
+        return Verification {
+            valid: bcrypt::verify(password, stored).unwrap_or(false),
+            needs_rehash: true,
+        };
+    }
+
+    let Ok(parsed) = PasswordHash::new(stored) else {
+        return Verification { valid: false, needs_rehash: false };
+    };
+    let valid = HASHER.verify_password(password.as_bytes(), &parsed).is_ok();
+    let current = HASHER.params();
+    let needs_rehash = parsed.algorithm != argon2::ARGON2ID_IDENT
+        || Params::try_from(&parsed).is_ok_and(|params| {
+            params.m_cost() != current.m_cost()
+                || params.t_cost() != current.t_cost()
+                || params.p_cost() != current.p_cost()
+        });
+    Verification { valid, needs_rehash }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn current_argon2_hash_does_not_need_rehash() {
+        let hash = hash_blocking("correct horse").unwrap();
+        let verification = verify_blocking("correct horse", &hash);
+        assert!(verification.valid);
+        assert!(!verification.needs_rehash);
+        assert!(!verify_blocking("wrong horse", &hash).valid);
+    }
+
+    #[test]
+    fn outdated_argon2_parameters_need_rehash() {
+        let weak = Argon2::new(Algorithm::Argon2id, Version::V0x13, Params::new(8, 1, 1, None).unwrap());
+        let salt = SaltString::generate(&mut OsRng);
+        let hash = weak.hash_password(b"correct horse", &salt).unwrap().to_string();
+        let verification = verify_blocking("correct horse", &hash);
+        assert!(verification.valid);
+        assert!(verification.needs_rehash);
+    }
+
+    #[test]
+    fn bcrypt_hash_needs_rehash() {
+        let hash = bcrypt::hash("correct horse", 4).unwrap();
+        let verification = verify_blocking("correct horse", &hash);
+        assert!(verification.valid);
+        assert!(verification.needs_rehash);
+    }
+
+    #[test]
+    fn malformed_hash_is_rejected() {
+        let verification = verify_blocking("correct horse", "not-a-hash");
+        assert!(!verification.valid);
+        assert!(!verification.needs_rehash);
+    }
+
+    #[tokio::test]
+    async fn async_wrappers_round_trip() {
+        let hash = hash_password("correct horse").await.unwrap();
+        assert!(verify_password("correct horse", &hash).await.valid);
+        assert!(!verify_password("wrong horse", &hash).await.valid);
+    }
+}
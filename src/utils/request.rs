@@ -0,0 +1,34 @@
+use std::net::IpAddr;
+
+use actix_web::HttpRequest;
+
+/// 是否信任 X-Forwarded-For / Forwarded 头。服务直接对外时必须关闭，
+/// 否则客户端可以伪造来源 IP 绕过 IP 白名单
+fn trust_proxy_headers() -> bool {
+    std::env::var("TRUST_PROXY_HEADERS")
+        .ok()
+        .and_then(|v| v.parse::<bool>().ok())
+        .unwrap_or(false)
+}
+
+/// 客户端 IP，部署在反向代理之后时使用代理传递的真实地址，无法解析时回退到连接地址
+pub fn client_ip(req: &HttpRequest) -> String {
+    let forwarded = if trust_proxy_headers() {
+        req.connection_info()
+            .realip_remote_addr()
+            .and_then(|addr| addr.parse::<IpAddr>().ok())
+    } else {
+        None
+    };
+    forwarded
+        .or_else(|| req.peer_addr().map(|addr| addr.ip()))
+        .map(|ip| ip.to_string())
+        .unwrap_or_else(|| "0.0.0.0".to_string())
+}
+
+pub fn user_agent(req: &HttpRequest) -> Option<String> {
+    req.headers()
+        .get("User-Agent")
+        .and_then(|ua| ua.to_str().ok())
+        .map(|ua| ua.chars().take(500).collect())
+}
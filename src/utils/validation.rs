@@ -0,0 +1,150 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::ops::Deref;
+
+use actix_web::dev::Payload;
+use actix_web::{web, Error, FromRequest, HttpRequest};
+use futures::future::LocalBoxFuture;
+use serde::de::DeserializeOwned;
+use validator::{Validate, ValidationError, ValidationErrors, ValidationErrorsKind};
+
+use crate::services::api_key_service;
+use crate::services::identity_service::normalize_id_card;
+use crate::services::sms_service::normalize_phone;
+use crate::utils::error::AppError;
+use crate::utils::password;
+
+const USERNAME_MIN: usize = 3;
+const USERNAME_MAX: usize = 32;
+
+fn invalid(code: &'static str, message: impl Into<Cow<'static, str>>) -> ValidationError {
+    let mut error = ValidationError::new(code);
+    error.message = Some(message.into());
+    error
+}
+
+/// 用户名以字母开头，只含字母、数字和下划线。登录时用户名、邮箱、手机号共用一个输入框，
+/// 这样可以避免用户名与他人的邮箱或手机号相同
+pub fn validate_username(username: &str) -> Result<(), ValidationError> {
+    let length = username.chars().count();
+    let valid = (USERNAME_MIN..=USERNAME_MAX).contains(&length)
+        && username.starts_with(|c: char| c.is_ascii_alphabetic())
+        && username.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(invalid("username", "用户名需为 3 到 32 位字母、数字或下划线，并以字母开头"))
+    }
+}
+
+/// 中国大陆手机号，允许带 +86 前缀
+pub fn validate_phone(phone: &str) -> Result<(), ValidationError> {
+    match normalize_phone(phone) {
+        Some(_) => Ok(()),
+        None => Err(invalid("phone", "手机号格式不正确")),
+    }
+}
+
+/// 18 位居民身份证号，校验出生日期和末位校验码
+pub fn validate_id_card(id_card: &str) -> Result<(), ValidationError> {
+    match normalize_id_card(id_card) {
+        Some(_) => Ok(()),
+        None => Err(invalid("id_card", "身份证号格式不正确")),
+    }
+}
+
+/// 新密码需满足长度、字符种类和泄露密码库检查
+pub fn validate_password(password: &str) -> Result<(), ValidationError> {
+    password::check_policy(password).map_err(|e| invalid("password", e.to_string()))
+}
+
+/// 6 位数字验证码
+pub fn validate_code(code: &str) -> Result<(), ValidationError> {
+    let code = code.trim();
+    if code.len() == 6 && code.bytes().all(|b| b.is_ascii_digit()) {
+        Ok(())
+    } else {
+        Err(invalid("code", "验证码为 6 位数字"))
+    }
+}
+
+/// 头像地址只接受外部 http(s) 链接，空字符串表示清除头像；本站存储的头像只能通过上传接口设置
+pub fn validate_avatar_url(url: &str) -> Result<(), ValidationError> {
+    let url = url.trim();
+    let valid = url.is_empty() || url.starts_with("https://") || url.starts_with("http://");
+    if valid && !url.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        Ok(())
+    } else {
+        Err(invalid("avatar_url", "头像地址格式不正确"))
+    }
+}
+
+pub fn validate_not_blank(value: &str) -> Result<(), ValidationError> {
+    if value.trim().is_empty() {
+        Err(invalid("blank", "不能为空"))
+    } else {
+        Ok(())
+    }
+}
+
+pub fn validate_cidrs(values: &[String]) -> Result<(), ValidationError> {
+    match values.iter().find(|value| !api_key_service::is_valid_cidr(value)) {
+        Some(value) => Err(invalid("cidr", format!("无效的 IP 地址: {}", value))),
+        None => Ok(()),
+    }
+}
+
+/// 把嵌套的校验错误展开为 字段路径 -> 错误信息列表
+fn collect_errors(prefix: &str, errors: &ValidationErrors, out: &mut BTreeMap<String, Vec<String>>) {
+    for (field, kind) in errors.errors() {
+        let path = if prefix.is_empty() { field.to_string() } else { format!("{}.{}", prefix, field) };
+        match kind {
+            ValidationErrorsKind::Field(errors) => {
+                let messages = errors
+                    .iter()
+                    .map(|e| e.message.as_ref().map(|m| m.to_string()).unwrap_or_else(|| e.code.to_string()));
+                out.entry(path).or_default().extend(messages);
+            }
+            ValidationErrorsKind::Struct(errors) => collect_errors(&path, errors, out),
+            ValidationErrorsKind::List(items) => {
+                for (index, errors) in items {
+                    collect_errors(&format!("{}[{}]", path, index), errors, out);
+                }
+            }
+        }
+    }
+}
+
+/// 统一的 422 错误，列出所有不合法的字段
+pub fn validation_error(errors: &ValidationErrors) -> AppError {
+    let mut fields = BTreeMap::new();
+    collect_errors("", errors, &mut fields);
+    AppError::Validation(fields)
+}
+
+/// 反序列化后按 Validate 规则校验的 JSON 请求体，校验失败直接返回 422
+pub struct ValidatedJson<T>(pub T);
+
+impl<T> Deref for ValidatedJson<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: DeserializeOwned + Validate + 'static> FromRequest for ValidatedJson<T> {
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let json = web::Json::<T>::from_request(req, payload);
+        Box::pin(async move {
+            let value = json.await?.into_inner();
+            match value.validate() {
+                Ok(()) => Ok(ValidatedJson(value)),
+                Err(errors) => Err(validation_error(&errors).into()),
+            }
+        })
+    }
+}